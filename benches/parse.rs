@@ -0,0 +1,71 @@
+use criterion::{criterion_group, criterion_main, Criterion, Throughput};
+use std::hint::black_box;
+
+// a config file with many nested sections, comments and multiline values.
+fn generated() -> String {
+    let mut output = String::new();
+    for i in 0..1000 {
+        output.push_str(&format!(
+            "; service {i}\nservice-{i}\n  image = registry.example.com/app:{i}\n  ports\n    = 80\n    = \"443\"\n  env\n    DEBUG = false\n  script = \"\"\"sh\n    echo {i}\n    exit 0\n"
+        ));
+    }
+    output
+}
+
+fn bench(c: &mut Criterion) {
+    let inputs = [
+        ("example", std::fs::read_to_string("example.conl").unwrap()),
+        ("generated", generated()),
+    ];
+    for (name, input) in &inputs {
+        let json = conl::to_json(input.as_bytes(), Default::default()).unwrap();
+        let mut group = c.benchmark_group(*name);
+        group.throughput(Throughput::Bytes(input.len() as u64));
+        group.bench_function("tokenize", |b| {
+            b.iter(|| conl::tokenize(black_box(input.as_bytes())).count())
+        });
+        group.bench_function("parse", |b| {
+            b.iter(|| conl::parse(black_box(input.as_bytes())).count())
+        });
+        // input that is already known to be UTF-8 (e.g. a validated memory-mapped file)
+        group.bench_function("parse_str", |b| {
+            b.iter(|| conl::parse_str(black_box(input)).count())
+        });
+        group.bench_function("parse_trusted", |b| {
+            // SAFETY: input is a String, so it is valid UTF-8
+            b.iter(|| unsafe { conl::parse_trusted(black_box(input.as_bytes())) }.count())
+        });
+        group.bench_function("parse_value", |b| {
+            b.iter(|| conl::parse_value(black_box(input.as_bytes())))
+        });
+        group.bench_function("to_json", |b| {
+            b.iter(|| conl::to_json(black_box(input.as_bytes()), Default::default()))
+        });
+        // the same document as JSON, for comparison
+        group.bench_function("serde_json", |b| {
+            b.iter(|| serde_json::from_str::<serde_json::Value>(black_box(&json)))
+        });
+        group.finish();
+    }
+}
+
+// a single quoted value of about 1MB, with no escapes (plain) or with one at the end.
+fn bench_long_value(c: &mut Criterion) {
+    let long = "x".repeat(1 << 20);
+    let inputs = [
+        ("plain", format!("a = \"{long}\"\n")),
+        ("escaped", format!("a = \"{long}\\n\"\n")),
+    ];
+    let mut group = c.benchmark_group("long_value");
+    for (name, input) in &inputs {
+        group.throughput(Throughput::Bytes(input.len() as u64));
+        let token = conl::tokenize(input.as_bytes()).nth(1).unwrap().unwrap();
+        group.bench_function(format!("unescape_{name}"), |b| {
+            b.iter(|| black_box(&token).unescape().map(|value| value.len()))
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, bench, bench_long_value);
+criterion_main!(benches);
@@ -0,0 +1,44 @@
+#![no_main]
+
+// Run with `cargo +nightly fuzz run parse` from the root of the repository.
+// Every entry point must return errors (not panic) for any input.
+
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|input: &[u8]| {
+    for token in conl::tokenize(input) {
+        let _ = token.map(|token| token.unescape().is_ok());
+    }
+    for token in conl::parse(input).recover() {
+        let _ = token;
+    }
+    for token in conl::parse(input).verbatim_multiline().hint_before_value() {
+        if token.is_err() {
+            break;
+        }
+    }
+    for token in conl::parse(input).deny_duplicate_keys().max_depth(8) {
+        if token.is_err() {
+            break;
+        }
+    }
+    for token in conl::parse_reader(input) {
+        if token.is_err() {
+            break;
+        }
+    }
+    for event in conl::events(input).with_comments() {
+        if event.is_err() {
+            break;
+        }
+    }
+    let _ = conl::pairs(input).count();
+    let _ = conl::parse_documents(input, "---").count();
+    let _ = conl::to_json(input, conl::JsonOptions::default());
+    let _ = conl::format(input);
+    let _ = conl::from_slice::<std::collections::BTreeMap<String, Option<String>>>(input);
+    let _ = conl::parse_value(input).map(|value| value.to_string());
+    if let Ok(input) = std::str::from_utf8(input) {
+        let _ = conl::json_to_conl(input);
+    }
+});
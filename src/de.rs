@@ -0,0 +1,672 @@
+use alloc::borrow::Cow;
+use alloc::collections::{BTreeMap, BTreeSet};
+use alloc::string::{String, ToString};
+
+use serde::de::{self, DeserializeSeed, IntoDeserializer, Unexpected, Visitor};
+
+use crate::{parse, parse_str, Location, Parser, SyntaxError, Token};
+
+impl de::Error for SyntaxError {
+    fn custom<T: core::fmt::Display>(msg: T) -> Self {
+        SyntaxError::new(0, 0, msg.to_string())
+    }
+}
+
+/// Deserialize an instance of `T` from CONL.
+///
+/// Maps are deserialized as structs or maps, lists as sequences, and
+/// values as scalars (parsed from their unescaped contents as requested).
+/// A missing value can be deserialized as unit, `None`, an empty string, or
+/// an empty map or list. A document with no keys or list items (e.g. an empty file,
+/// or one with only comments) is a missing value.
+/// If a key appears more than once in a map, each occurrence is passed to serde, so derived
+/// structs return a "duplicate field" error (see [from_slice_with] to change this).
+pub fn from_slice<'de, T: de::Deserialize<'de>>(input: &'de [u8]) -> Result<T, SyntaxError> {
+    let mut deserializer = Deserializer::new(input);
+    T::deserialize(&mut deserializer).map_err(|e| deserializer.locate(e))
+}
+
+/// See [from_slice]
+pub fn from_str<'de, T: de::Deserialize<'de>>(input: &'de str) -> Result<T, SyntaxError> {
+    from_str_with(input, DeserializeOptions::default())
+}
+
+/// from_slice_with is like [from_slice], but configurable with [DeserializeOptions].
+pub fn from_slice_with<'de, T: de::Deserialize<'de>>(
+    input: &'de [u8],
+    options: DeserializeOptions,
+) -> Result<T, SyntaxError> {
+    let mut deserializer = Deserializer::with_options(input, options);
+    T::deserialize(&mut deserializer).map_err(|e| deserializer.locate(e))
+}
+
+/// See [from_slice_with]
+pub fn from_str_with<'de, T: de::Deserialize<'de>>(
+    input: &'de str,
+    options: DeserializeOptions,
+) -> Result<T, SyntaxError> {
+    let mut deserializer = Deserializer::with_parser(parse_str(input), options);
+    T::deserialize(&mut deserializer).map_err(|e| deserializer.locate(e))
+}
+
+/// DeserializeOptions configures [from_slice_with] and [Deserializer::with_options].
+///
+/// By default duplicate keys are passed through to serde ([DuplicateKeys::PassThrough]),
+/// and each map is read once.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct DeserializeOptions {
+    /// what to do if the same key appears more than once in a map
+    pub duplicate_keys: DuplicateKeys,
+}
+
+/// DuplicateKeys is how the [Deserializer] handles a key that appears more than once in
+/// one map (after unescaping). See [DeserializeOptions].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum DuplicateKeys {
+    /// pass every occurrence to serde (the default). Derived structs return a
+    /// "duplicate field" error, and most maps keep the last value.
+    #[default]
+    PassThrough,
+    /// use the value of the last occurrence. The earlier values are skipped, so this
+    /// works for structs as well as maps, at the cost of reading each map twice.
+    LastWins,
+    /// use the value of the first occurrence, skipping later ones
+    FirstWins,
+    /// return a "duplicate key" error (as [crate::Parser::deny_duplicate_keys])
+    Error,
+}
+
+/// A serde Deserializer that reads CONL. See [from_slice].
+pub struct Deserializer<'de> {
+    parser: Parser<'de>,
+    peek: Option<Option<Token<'de>>>,
+    loc: Location,
+    duplicate_keys: DuplicateKeys,
+}
+
+enum Next<'de> {
+    Scalar(Cow<'de, str>),
+    Map,
+    List,
+    Empty,
+}
+
+impl<'de> Deserializer<'de> {
+    /// new creates a Deserializer that reads the given CONL document.
+    pub fn new(input: &'de [u8]) -> Self {
+        Self::with_options(input, DeserializeOptions::default())
+    }
+
+    /// See [from_slice_with]
+    pub fn with_options(input: &'de [u8], options: DeserializeOptions) -> Self {
+        Self::with_parser(parse(input), options)
+    }
+
+    fn with_parser(mut parser: Parser<'de>, options: DeserializeOptions) -> Self {
+        parser.deny_duplicate_keys = options.duplicate_keys == DuplicateKeys::Error;
+        Deserializer {
+            parser,
+            peek: None,
+            loc: Location::default(),
+            duplicate_keys: options.duplicate_keys,
+        }
+    }
+
+    // errors raised by serde (e.g. missing fields) don't know where they happened,
+    // so we point them at the last token we saw.
+    fn locate(&self, mut error: SyntaxError) -> SyntaxError {
+        if error.lno == 0 {
+            error.lno = self.loc.lno;
+            error.col = self.loc.col;
+        }
+        error
+    }
+
+    fn peek(&mut self) -> Result<Option<&Token<'de>>, SyntaxError> {
+        if self.peek.is_none() {
+            let next = loop {
+                match self.parser.next().transpose()? {
+                    Some(Token::Newline(..) | Token::Comment(..) | Token::MultilineHint(..)) => {}
+                    next => break next,
+                }
+            };
+            self.peek = Some(next);
+        }
+        Ok(self.peek.as_ref().unwrap().as_ref())
+    }
+
+    fn next(&mut self) -> Result<Option<Token<'de>>, SyntaxError> {
+        self.peek()?;
+        let next = self.peek.take().unwrap();
+        if let Some(token) = &next {
+            self.loc = token.location();
+        }
+        Ok(next)
+    }
+
+    // counts the keys of the current map that have not been returned yet.
+    fn count_keys(&self) -> BTreeMap<String, usize> {
+        let mut counts = BTreeMap::new();
+        let peek = self.peek.clone().flatten().map(Ok);
+        let mut depth = 0;
+        for token in peek.into_iter().chain(self.parser.lookahead()) {
+            match token {
+                Ok(Token::Indent(..)) => depth += 1,
+                Ok(Token::Outdent(..)) if depth == 0 => break,
+                Ok(Token::Outdent(..)) => depth -= 1,
+                Ok(token @ Token::MapKey(..)) if depth == 0 => {
+                    if let Ok(key) = token.unescape() {
+                        *counts.entry(key.into_owned()).or_default() += 1;
+                    }
+                }
+                Ok(_) => {}
+                Err(_) => break,
+            }
+        }
+        counts
+    }
+
+    fn next_value(&mut self) -> Result<Next<'de>, SyntaxError> {
+        let section = match self.peek()? {
+            Some(token @ Token::MapKey(..)) => Some((Next::Map, token.location())),
+            Some(token @ Token::ListItem(..)) => Some((Next::List, token.location())),
+            _ => None,
+        };
+        if let Some((next, loc)) = section {
+            self.loc = loc;
+            return Ok(next);
+        }
+        match self.next()? {
+            Some(token @ (Token::Value(..) | Token::MultilineValue(..))) => {
+                Ok(Next::Scalar(token.unescape()?))
+            }
+            Some(Token::Indent(..)) => match self.peek()? {
+                Some(Token::ListItem(..)) => Ok(Next::List),
+                _ => Ok(Next::Map),
+            },
+            _ => Ok(Next::Empty),
+        }
+    }
+
+    fn scalar(&mut self, expected: &str) -> Result<Scalar<'de>, SyntaxError> {
+        match self.next_value()? {
+            Next::Scalar(value) => Ok(Scalar(value)),
+            Next::Map => Err(de::Error::invalid_type(Unexpected::Map, &expected)),
+            Next::List => Err(de::Error::invalid_type(Unexpected::Seq, &expected)),
+            Next::Empty => Err(de::Error::invalid_type(Unexpected::Unit, &expected)),
+        }
+    }
+}
+
+macro_rules! deserialize_scalar {
+    ($($method:ident => $expected:literal,)*) => {
+        $(
+            fn $method<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, SyntaxError> {
+                self.scalar($expected)?.$method(visitor)
+            }
+        )*
+    };
+}
+
+impl<'de> de::Deserializer<'de> for &mut Deserializer<'de> {
+    type Error = SyntaxError;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, SyntaxError> {
+        match self.next_value()? {
+            Next::Scalar(value) => Scalar(value).deserialize_any(visitor),
+            Next::Map => Section::map(self).visit_map(visitor),
+            Next::List => Section::list(self).visit_seq(visitor),
+            Next::Empty => visitor.visit_unit(),
+        }
+    }
+
+    deserialize_scalar! {
+        deserialize_bool => "a boolean",
+        deserialize_char => "a character",
+        deserialize_i8 => "an integer",
+        deserialize_i16 => "an integer",
+        deserialize_i32 => "an integer",
+        deserialize_i64 => "an integer",
+        deserialize_i128 => "an integer",
+        deserialize_u8 => "an integer",
+        deserialize_u16 => "an integer",
+        deserialize_u32 => "an integer",
+        deserialize_u64 => "an integer",
+        deserialize_u128 => "an integer",
+        deserialize_f32 => "a number",
+        deserialize_f64 => "a number",
+        deserialize_bytes => "bytes",
+        deserialize_byte_buf => "bytes",
+    }
+
+    fn deserialize_str<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, SyntaxError> {
+        match self.next_value()? {
+            Next::Scalar(value) => Scalar(value).deserialize_any(visitor),
+            Next::Empty => visitor.visit_borrowed_str(""),
+            Next::Map => Err(de::Error::invalid_type(Unexpected::Map, &visitor)),
+            Next::List => Err(de::Error::invalid_type(Unexpected::Seq, &visitor)),
+        }
+    }
+
+    fn deserialize_string<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, SyntaxError> {
+        self.deserialize_str(visitor)
+    }
+
+    fn deserialize_identifier<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, SyntaxError> {
+        self.deserialize_str(visitor)
+    }
+
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, SyntaxError> {
+        match self.peek()? {
+            None | Some(Token::NoValue(..)) => {
+                self.next()?;
+                visitor.visit_none()
+            }
+            _ => visitor.visit_some(self),
+        }
+    }
+
+    fn deserialize_unit<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, SyntaxError> {
+        match self.next_value()? {
+            Next::Empty => visitor.visit_unit(),
+            Next::Scalar(value) => Err(de::Error::invalid_type(Unexpected::Str(&value), &visitor)),
+            Next::Map => Err(de::Error::invalid_type(Unexpected::Map, &visitor)),
+            Next::List => Err(de::Error::invalid_type(Unexpected::Seq, &visitor)),
+        }
+    }
+
+    fn deserialize_unit_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, SyntaxError> {
+        self.deserialize_unit(visitor)
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, SyntaxError> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    fn deserialize_seq<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, SyntaxError> {
+        match self.next_value()? {
+            Next::List => Section::list(self).visit_seq(visitor),
+            Next::Empty => visitor.visit_seq(Section::empty(self)),
+            Next::Scalar(value) => Err(de::Error::invalid_type(Unexpected::Str(&value), &visitor)),
+            Next::Map => Err(de::Error::invalid_type(Unexpected::Map, &visitor)),
+        }
+    }
+
+    fn deserialize_tuple<V: Visitor<'de>>(
+        self,
+        _len: usize,
+        visitor: V,
+    ) -> Result<V::Value, SyntaxError> {
+        self.deserialize_seq(visitor)
+    }
+
+    fn deserialize_tuple_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        _len: usize,
+        visitor: V,
+    ) -> Result<V::Value, SyntaxError> {
+        self.deserialize_seq(visitor)
+    }
+
+    fn deserialize_map<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, SyntaxError> {
+        match self.next_value()? {
+            Next::Map => Section::map(self).visit_map(visitor),
+            Next::Empty => visitor.visit_map(Section::empty(self)),
+            Next::Scalar(value) => Err(de::Error::invalid_type(Unexpected::Str(&value), &visitor)),
+            Next::List => Err(de::Error::invalid_type(Unexpected::Seq, &visitor)),
+        }
+    }
+
+    fn deserialize_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        _fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, SyntaxError> {
+        self.deserialize_map(visitor)
+    }
+
+    fn deserialize_enum<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        _variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, SyntaxError> {
+        match self.next_value()? {
+            Next::Scalar(value) => visitor.visit_enum(value.into_deserializer()),
+            Next::Map => visitor.visit_enum(Section::map(self)),
+            Next::List => Err(de::Error::invalid_type(Unexpected::Seq, &visitor)),
+            Next::Empty => Err(de::Error::invalid_type(Unexpected::Unit, &visitor)),
+        }
+    }
+
+    fn deserialize_ignored_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, SyntaxError> {
+        self.deserialize_any(visitor)
+    }
+}
+
+// Section gives serde access to the entries of a map or list.
+// It consumes the Outdent (or end of input) that closes the section.
+struct Section<'a, 'de> {
+    de: &'a mut Deserializer<'de>,
+    list: bool,
+    done: bool,
+    // the number of keys or list items returned so far
+    count: usize,
+    // the keys returned so far (for DuplicateKeys::FirstWins)
+    seen: BTreeSet<String>,
+    // the number of times each key appears in the rest of the map (for DuplicateKeys::LastWins)
+    remaining: Option<BTreeMap<String, usize>>,
+}
+
+impl<'a, 'de> Section<'a, 'de> {
+    fn map(de: &'a mut Deserializer<'de>) -> Self {
+        Section {
+            de,
+            list: false,
+            done: false,
+            count: 0,
+            seen: BTreeSet::new(),
+            remaining: None,
+        }
+    }
+
+    fn list(de: &'a mut Deserializer<'de>) -> Self {
+        Section {
+            list: true,
+            ..Section::map(de)
+        }
+    }
+
+    fn empty(de: &'a mut Deserializer<'de>) -> Self {
+        Section {
+            done: true,
+            ..Section::map(de)
+        }
+    }
+
+    fn visit_map<V: Visitor<'de>>(mut self, visitor: V) -> Result<V::Value, SyntaxError> {
+        let value = visitor.visit_map(&mut self)?;
+        self.finish()?;
+        Ok(value)
+    }
+
+    fn visit_seq<V: Visitor<'de>>(mut self, visitor: V) -> Result<V::Value, SyntaxError> {
+        let value = visitor.visit_seq(&mut self)?;
+        self.finish()?;
+        Ok(value)
+    }
+
+    // skips any entries the visitor did not read (e.g. the extra items given for a tuple),
+    // and reports them as an invalid length.
+    fn finish(&mut self) -> Result<(), SyntaxError> {
+        let expected = self.count;
+        let mut first = None;
+        while self.next_entry()?.is_some() {
+            first.get_or_insert(self.de.loc);
+            <de::IgnoredAny as de::Deserialize>::deserialize(&mut *self.de)?;
+        }
+        if let Some(loc) = first {
+            // point the error at the first unread entry
+            self.de.loc = loc;
+            return Err(de::Error::invalid_length(
+                self.count,
+                &ExpectedLength(expected, self.list),
+            ));
+        }
+        Ok(())
+    }
+
+    // returns true if the value of this key should be skipped, as another
+    // occurrence of the key takes precedence.
+    fn is_duplicate(&mut self, key: &str) -> bool {
+        match self.de.duplicate_keys {
+            DuplicateKeys::LastWins => match self.remaining.as_mut().and_then(|r| r.get_mut(key)) {
+                Some(count) => {
+                    *count -= 1;
+                    *count > 0
+                }
+                None => false,
+            },
+            DuplicateKeys::FirstWins => !self.seen.insert(key.to_string()),
+            DuplicateKeys::PassThrough | DuplicateKeys::Error => false,
+        }
+    }
+
+    fn next_entry(&mut self) -> Result<Option<Token<'de>>, SyntaxError> {
+        if self.done {
+            return Ok(None);
+        }
+        match self.de.next()? {
+            Some(token @ Token::MapKey(..)) if !self.list => {
+                self.count += 1;
+                Ok(Some(token))
+            }
+            Some(token @ Token::ListItem(..)) if self.list => {
+                self.count += 1;
+                Ok(Some(token))
+            }
+            Some(Token::MapKey(..)) => Err(de::Error::invalid_type(Unexpected::Map, &"a list")),
+            Some(Token::ListItem(..)) => Err(de::Error::invalid_type(Unexpected::Seq, &"a map")),
+            _ => {
+                self.done = true;
+                Ok(None)
+            }
+        }
+    }
+}
+
+impl<'de> de::MapAccess<'de> for Section<'_, 'de> {
+    type Error = SyntaxError;
+
+    fn next_key_seed<K: DeserializeSeed<'de>>(
+        &mut self,
+        seed: K,
+    ) -> Result<Option<K::Value>, SyntaxError> {
+        if self.de.duplicate_keys == DuplicateKeys::LastWins && self.remaining.is_none() {
+            self.remaining = Some(self.de.count_keys());
+        }
+        loop {
+            let Some(key) = self.next_entry()? else {
+                return Ok(None);
+            };
+            let key = key.unescape()?;
+            if self.is_duplicate(&key) {
+                <de::IgnoredAny as de::Deserialize>::deserialize(&mut *self.de)?;
+                continue;
+            }
+            return seed.deserialize(Scalar(key)).map(Some);
+        }
+    }
+
+    fn next_value_seed<V: DeserializeSeed<'de>>(
+        &mut self,
+        seed: V,
+    ) -> Result<V::Value, SyntaxError> {
+        seed.deserialize(&mut *self.de)
+    }
+}
+
+impl<'de> de::SeqAccess<'de> for Section<'_, 'de> {
+    type Error = SyntaxError;
+
+    fn next_element_seed<T: DeserializeSeed<'de>>(
+        &mut self,
+        seed: T,
+    ) -> Result<Option<T::Value>, SyntaxError> {
+        if self.next_entry()?.is_none() {
+            return Ok(None);
+        }
+        seed.deserialize(&mut *self.de).map(Some)
+    }
+}
+
+impl<'de> de::EnumAccess<'de> for Section<'_, 'de> {
+    type Error = SyntaxError;
+    type Variant = Self;
+
+    fn variant_seed<V: DeserializeSeed<'de>>(
+        mut self,
+        seed: V,
+    ) -> Result<(V::Value, Self), SyntaxError> {
+        let Some(key) = self.next_entry()? else {
+            return Err(de::Error::custom("expected an enum variant"));
+        };
+        let variant = seed.deserialize(Scalar(key.unescape()?))?;
+        Ok((variant, self))
+    }
+}
+
+impl<'de> de::VariantAccess<'de> for Section<'_, 'de> {
+    type Error = SyntaxError;
+
+    fn unit_variant(mut self) -> Result<(), SyntaxError> {
+        <() as de::Deserialize>::deserialize(&mut *self.de)?;
+        self.end()
+    }
+
+    fn newtype_variant_seed<T: DeserializeSeed<'de>>(
+        mut self,
+        seed: T,
+    ) -> Result<T::Value, SyntaxError> {
+        let value = seed.deserialize(&mut *self.de)?;
+        self.end()?;
+        Ok(value)
+    }
+
+    fn tuple_variant<V: Visitor<'de>>(
+        mut self,
+        _len: usize,
+        visitor: V,
+    ) -> Result<V::Value, SyntaxError> {
+        let value = de::Deserializer::deserialize_seq(&mut *self.de, visitor)?;
+        self.end()?;
+        Ok(value)
+    }
+
+    fn struct_variant<V: Visitor<'de>>(
+        mut self,
+        _fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, SyntaxError> {
+        let value = de::Deserializer::deserialize_map(&mut *self.de, visitor)?;
+        self.end()?;
+        Ok(value)
+    }
+}
+
+impl Section<'_, '_> {
+    // an enum is represented as a map with exactly one key
+    fn end(&mut self) -> Result<(), SyntaxError> {
+        match self.next_entry()? {
+            None => Ok(()),
+            Some(_) => Err(de::Error::custom("expected a map with a single key")),
+        }
+    }
+}
+
+// ExpectedLength describes the number of entries a visitor read from a map or list.
+struct ExpectedLength(usize, bool);
+
+impl de::Expected for ExpectedLength {
+    fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+        let kind = if self.1 { "list item" } else { "key" };
+        let plural = if self.0 == 1 { "" } else { "s" };
+        write!(f, "{} {}{}", self.0, kind, plural)
+    }
+}
+
+// Scalar deserializes the unescaped contents of a key or value.
+struct Scalar<'de>(Cow<'de, str>);
+
+impl Scalar<'_> {
+    fn parse<T: core::str::FromStr, V: de::Expected>(&self, visitor: &V) -> Result<T, SyntaxError> {
+        self.0
+            .parse()
+            .map_err(|_| de::Error::invalid_value(Unexpected::Str(&self.0), visitor))
+    }
+}
+
+macro_rules! deserialize_from_str {
+    ($($method:ident => $visit:ident,)*) => {
+        $(
+            fn $method<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, SyntaxError> {
+                let value = self.parse(&visitor)?;
+                visitor.$visit(value)
+            }
+        )*
+    };
+}
+
+impl<'de> de::Deserializer<'de> for Scalar<'de> {
+    type Error = SyntaxError;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, SyntaxError> {
+        match self.0 {
+            Cow::Borrowed(value) => visitor.visit_borrowed_str(value),
+            Cow::Owned(value) => visitor.visit_string(value),
+        }
+    }
+
+    deserialize_from_str! {
+        deserialize_bool => visit_bool,
+        deserialize_char => visit_char,
+        deserialize_i8 => visit_i8,
+        deserialize_i16 => visit_i16,
+        deserialize_i32 => visit_i32,
+        deserialize_i64 => visit_i64,
+        deserialize_i128 => visit_i128,
+        deserialize_u8 => visit_u8,
+        deserialize_u16 => visit_u16,
+        deserialize_u32 => visit_u32,
+        deserialize_u64 => visit_u64,
+        deserialize_u128 => visit_u128,
+        deserialize_f32 => visit_f32,
+        deserialize_f64 => visit_f64,
+    }
+
+    fn deserialize_bytes<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, SyntaxError> {
+        match self.0 {
+            Cow::Borrowed(value) => visitor.visit_borrowed_bytes(value.as_bytes()),
+            Cow::Owned(value) => visitor.visit_byte_buf(value.into_bytes()),
+        }
+    }
+
+    fn deserialize_byte_buf<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, SyntaxError> {
+        self.deserialize_bytes(visitor)
+    }
+
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, SyntaxError> {
+        visitor.visit_some(self)
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, SyntaxError> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    fn deserialize_enum<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        _variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, SyntaxError> {
+        visitor.visit_enum(self.0.into_deserializer())
+    }
+
+    serde::forward_to_deserialize_any! {
+        str string unit unit_struct seq tuple tuple_struct map struct identifier ignored_any
+    }
+}
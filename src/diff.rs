@@ -0,0 +1,81 @@
+use alloc::collections::BTreeMap;
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+
+use crate::value::join_path;
+use crate::Value;
+
+/// Change is a difference between two values found by [diff]. Paths are dotted paths
+/// (as accepted by [Value::pointer]).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Change<'a> {
+    /// the key or list item at the path is only in the new value
+    Added(String),
+    /// the key or list item at the path is only in the old value
+    Removed(String),
+    /// the value at the path is different (the old value, then the new value)
+    Changed(String, &'a Value, &'a Value),
+}
+
+/// diff returns the differences between an old and a new value.
+///
+/// Maps are compared by key, ignoring the order of the keys (if a key is repeated only its
+/// first value is compared, as with [Value::get]). Lists are compared item by item, so an
+/// item inserted in the middle of a list changes every item after it. A multiline value is
+/// the same as a single-line value with the same contents. Changes are returned in the order
+/// of the old value, followed by additions in the order of the new value.
+pub fn diff<'a>(old: &'a Value, new: &'a Value) -> Vec<Change<'a>> {
+    let mut changes = Vec::new();
+    diff_at(String::new(), old, new, &mut changes);
+    changes
+}
+
+fn diff_at<'a>(path: String, old: &'a Value, new: &'a Value, changes: &mut Vec<Change<'a>>) {
+    match (old, new) {
+        (Value::Map(old_entries), Value::Map(new_entries)) => {
+            let old_values = first_values(old_entries);
+            let new_values = first_values(new_entries);
+            for (key, value) in old_entries {
+                if !core::ptr::eq(old_values[&key[..]], value) {
+                    continue;
+                }
+                match new_values.get(&key[..]) {
+                    Some(new_value) => diff_at(join_path(&path, key), value, new_value, changes),
+                    None => changes.push(Change::Removed(join_path(&path, key))),
+                }
+            }
+            for (key, value) in new_entries {
+                if core::ptr::eq(new_values[&key[..]], value) && !old_values.contains_key(&key[..])
+                {
+                    changes.push(Change::Added(join_path(&path, key)));
+                }
+            }
+        }
+        (Value::List(old_items), Value::List(new_items)) => {
+            for (i, value) in old_items.iter().enumerate() {
+                let item_path = join_path(&path, &i.to_string());
+                match new_items.get(i) {
+                    Some(new_value) => diff_at(item_path, value, new_value, changes),
+                    None => changes.push(Change::Removed(item_path)),
+                }
+            }
+            for i in old_items.len()..new_items.len() {
+                changes.push(Change::Added(join_path(&path, &i.to_string())));
+            }
+        }
+        (Value::Null, Value::Null) => {}
+        _ => match (old.as_str(), new.as_str()) {
+            (Some(a), Some(b)) if a == b => {}
+            _ => changes.push(Change::Changed(path, old, new)),
+        },
+    }
+}
+
+// returns the first value of each key in a map.
+fn first_values(entries: &[(String, Value)]) -> BTreeMap<&str, &Value> {
+    let mut values = BTreeMap::new();
+    for (key, value) in entries {
+        values.entry(&key[..]).or_insert(value);
+    }
+    values
+}
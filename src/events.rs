@@ -0,0 +1,254 @@
+use alloc::borrow::Cow;
+use alloc::collections::VecDeque;
+use alloc::string::{String, ToString};
+use alloc::{vec, vec::Vec};
+
+use crate::{parse, Location, Parser, SectionType, SyntaxError, Token};
+
+/// Event is a structural event in a CONL document. See [events].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Event<'tok> {
+    /// the start of a map, followed by pairs of [Event::Key] and a value
+    StartMap,
+    /// the end of the current map
+    EndMap,
+    /// the start of a list, followed by its values
+    StartList,
+    /// the end of the current list
+    EndList,
+    /// a map key (unescaped)
+    Key(Cow<'tok, str>),
+    /// a single-line or multiline value (unescaped). `quoted` is true if it was written in
+    /// quotes or as a multiline value (see [Token::is_quoted]), so is always a string.
+    Scalar { value: Cow<'tok, str>, quoted: bool },
+    /// a key or list item with no value
+    Null,
+}
+
+/// events iterates over a CONL document as a stream of [Event]s, so you don't have to
+/// track [Token::Indent] and [Token::Outdent] yourself.
+///
+/// The document is a single value: either [Event::StartMap] ... [Event::EndMap]
+/// or [Event::StartList] ... [Event::EndList]. A document with no keys or list items
+/// (for example only comments) has no events, as [crate::parse_value] returns [crate::Value::Null].
+/// Each value is one of [Event::Scalar], [Event::Null], or a nested map or list.
+/// Comments, newlines and multiline hints are skipped.
+pub fn events(input: &[u8]) -> Events<'_> {
+    parse(input).events()
+}
+
+/// See [events]
+pub struct Events<'tok> {
+    parser: Parser<'tok>,
+    // the type of each open section, None until its first key or list item
+    stack: Vec<Option<SectionType>>,
+    pending: Option<(Event<'tok>, Comments)>,
+    location: Location,
+    finished: bool,
+    // the state used by [Events::with_comments]
+    track_comments: bool,
+    lookahead: VecDeque<Result<Token<'tok>, SyntaxError>>,
+    comments_before: Vec<String>,
+    item_comments: Option<Comments>,
+    comments: Comments,
+}
+
+/// CommentedEvent is an [Event] with the comments attached to it. See [Events::with_comments].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CommentedEvent<'tok> {
+    pub event: Event<'tok>,
+    /// the comments on lines of their own before the key or list item
+    pub comments_before: Vec<String>,
+    /// the comment at the end of the line of the key or list item
+    pub comment_after: Option<String>,
+}
+
+/// See [Events::with_comments]
+pub struct CommentedEvents<'tok> {
+    events: Events<'tok>,
+}
+
+#[derive(Default)]
+struct Comments {
+    before: Vec<String>,
+    after: Option<String>,
+}
+
+impl<'tok> Parser<'tok> {
+    /// events converts the parser into a stream of [Event]s. See [events].
+    pub fn events(self) -> Events<'tok> {
+        Events {
+            parser: self,
+            stack: vec![None],
+            pending: None,
+            location: Location::default(),
+            finished: false,
+            track_comments: false,
+            lookahead: VecDeque::new(),
+            comments_before: Vec::new(),
+            item_comments: None,
+            comments: Comments::default(),
+        }
+    }
+}
+
+impl<'tok> Events<'tok> {
+    /// location returns the location of the token that produced the most recent event.
+    /// This is useful for reporting errors in the content of the document.
+    pub fn location(&self) -> Location {
+        self.location
+    }
+
+    /// with_comments attaches comments to the events for the key or list item they describe
+    /// (for editors that need to preserve them).
+    ///
+    /// Comments on lines of their own are attached to the [Event::Key] that follows them
+    /// (or for list items, the first event of the item's value), as is a comment at the end
+    /// of the line of the key or list item. Comments at the end of the document are attached
+    /// to the final [Event::EndMap] or [Event::EndList].
+    pub fn with_comments(mut self) -> CommentedEvents<'tok> {
+        self.track_comments = true;
+        CommentedEvents { events: self }
+    }
+
+    fn next_token(&mut self) -> Option<Result<Token<'tok>, SyntaxError>> {
+        self.lookahead.pop_front().or_else(|| self.parser.next())
+    }
+
+    // returns the comments for the key or list item that was just read, removing
+    // any comment at the end of its line from the token stream.
+    fn take_comments(&mut self) -> Comments {
+        if !self.track_comments {
+            return Comments::default();
+        }
+        while !matches!(
+            self.lookahead.back(),
+            Some(Ok(Token::Newline(..) | Token::Indent(..)) | Err(_))
+        ) {
+            let Some(token) = self.parser.next() else {
+                break;
+            };
+            self.lookahead.push_back(token);
+        }
+        let after = self
+            .lookahead
+            .iter()
+            .position(|token| matches!(token, Ok(Token::Comment(..))))
+            .and_then(|i| self.lookahead.remove(i))
+            .map(|token| match token {
+                Ok(Token::Comment(_, comment)) => comment.to_string(),
+                _ => unreachable!(),
+            });
+        Comments {
+            before: core::mem::take(&mut self.comments_before),
+            after,
+        }
+    }
+
+    // opens the current section (if this is its first entry), returning the start event.
+    fn start(&mut self, kind: SectionType) -> Option<Event<'tok>> {
+        let current = self.stack.last_mut().unwrap();
+        if current.is_some() {
+            return None;
+        }
+        *current = Some(kind);
+        Some(match kind {
+            SectionType::Map => Event::StartMap,
+            SectionType::List => Event::StartList,
+        })
+    }
+
+    fn end(&mut self) -> Option<Event<'tok>> {
+        Some(match self.stack.pop()? {
+            Some(SectionType::List) => Event::EndList,
+            _ => Event::EndMap,
+        })
+    }
+
+    fn next_event(&mut self) -> Result<Option<Event<'tok>>, SyntaxError> {
+        if let Some((event, comments)) = self.pending.take() {
+            self.comments = comments;
+            return Ok(Some(event));
+        }
+        self.comments = Comments::default();
+        while let Some(token) = self.next_token() {
+            let token = token?;
+            self.location = token.location();
+            match token {
+                Token::Comment(_, comment) if self.track_comments => {
+                    self.comments_before.push(comment.to_string())
+                }
+                Token::Newline(..) | Token::Comment(..) | Token::MultilineHint(..) => {}
+                Token::Indent(..) => self.stack.push(None),
+                Token::Outdent(..) => return Ok(self.end()),
+                Token::ListItem(..) => {
+                    let comments = self.take_comments();
+                    let start = self.start(SectionType::List);
+                    self.item_comments = Some(comments);
+                    if start.is_some() {
+                        return Ok(start);
+                    }
+                }
+                Token::MapKey(..) => {
+                    let key = Event::Key(token.unescape()?);
+                    let comments = self.take_comments();
+                    let Some(event) = self.start(SectionType::Map) else {
+                        self.comments = comments;
+                        return Ok(Some(key));
+                    };
+                    self.pending = Some((key, comments));
+                    return Ok(Some(self.attach((event, Comments::default()))));
+                }
+                Token::Value(..) | Token::MultilineValue(..) => {
+                    let event = Event::Scalar {
+                        value: token.unescape()?,
+                        quoted: token.is_quoted(),
+                    };
+                    return Ok(Some(self.attach((event, Comments::default()))));
+                }
+                Token::NoValue(..) => {
+                    return Ok(Some(self.attach((Event::Null, Comments::default()))))
+                }
+            }
+        }
+        self.comments.before = core::mem::take(&mut self.comments_before);
+        if self.stack.len() == 1 && self.stack[0].is_none() {
+            return Ok(None);
+        }
+        Ok(self.end())
+    }
+
+    // records the comments for the event. If the event is the value of a list item
+    // the comments of the list item are used instead.
+    fn attach(&mut self, (event, comments): (Event<'tok>, Comments)) -> Event<'tok> {
+        self.comments = self.item_comments.take().unwrap_or(comments);
+        event
+    }
+}
+
+impl<'tok> Iterator for Events<'tok> {
+    type Item = Result<Event<'tok>, SyntaxError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.finished {
+            return None;
+        }
+        let result = self.next_event().transpose();
+        self.finished = matches!(result, None | Some(Err(_)));
+        result
+    }
+}
+
+impl<'tok> Iterator for CommentedEvents<'tok> {
+    type Item = Result<CommentedEvent<'tok>, SyntaxError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let event = self.events.next()?;
+        let comments = core::mem::take(&mut self.events.comments);
+        Some(event.map(|event| CommentedEvent {
+            event,
+            comments_before: comments.before,
+            comment_after: comments.after,
+        }))
+    }
+}
@@ -0,0 +1,262 @@
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+
+use crate::{escape_scalar, parse, tokenize, SyntaxError, Token};
+
+/// IndentStyle controls how [format_with] indents nested sections.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IndentStyle {
+    /// indent by the given number of spaces per level
+    Spaces(usize),
+    /// indent by one tab per level
+    Tabs,
+}
+
+impl Default for IndentStyle {
+    fn default() -> Self {
+        IndentStyle::Spaces(2)
+    }
+}
+
+/// NewlineStyle is the line ending used in a document. See [detect_newline].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum NewlineStyle {
+    /// `\n`
+    #[default]
+    Lf,
+    /// `\r\n`
+    CrLf,
+    /// `\r`
+    Cr,
+}
+
+impl NewlineStyle {
+    /// returns the line ending as a string.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            NewlineStyle::Lf => "\n",
+            NewlineStyle::CrLf => "\r\n",
+            NewlineStyle::Cr => "\r",
+        }
+    }
+}
+
+/// detect_newline returns the most common line ending in the input.
+/// If there are no line endings (or there is a tie with `\n`), [NewlineStyle::Lf] is returned.
+pub fn detect_newline(input: &[u8]) -> NewlineStyle {
+    let mut counts = [0usize; 3];
+    let mut i = 0;
+    while i < input.len() {
+        match &input[i..] {
+            [b'\r', b'\n', ..] => {
+                counts[1] += 1;
+                i += 1;
+            }
+            [b'\r', ..] => counts[2] += 1,
+            [b'\n', ..] => counts[0] += 1,
+            _ => {}
+        }
+        i += 1;
+    }
+    let styles = [NewlineStyle::Lf, NewlineStyle::CrLf, NewlineStyle::Cr];
+    // max_by_key returns the last maximum, so search in reverse to prefer Lf
+    styles
+        .into_iter()
+        .zip(counts)
+        .rev()
+        .max_by_key(|(_, count)| *count)
+        .map_or(NewlineStyle::Lf, |(style, _)| style)
+}
+
+/// FormatOptions configures [format_with]. An [IndentStyle] can be used in its place.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct FormatOptions {
+    /// how to indent nested sections
+    pub indent: IndentStyle,
+    /// the line ending to use (by default the most common one in the input, see [detect_newline])
+    pub newline: Option<NewlineStyle>,
+}
+
+impl From<IndentStyle> for FormatOptions {
+    fn from(indent: IndentStyle) -> Self {
+        FormatOptions {
+            indent,
+            ..Default::default()
+        }
+    }
+}
+
+/// format re-emits a CONL document in a canonical style (like gofmt).
+///
+/// * sections are indented by two spaces per level (see [format_with] to change this);
+/// * keys and values are quoted only if necessary (see [crate::escape]);
+/// * comments are written as `; comment` and indented to match the following line;
+/// * runs of blank lines are collapsed to one, and leading and trailing blank lines are removed;
+/// * lines end with the most common line ending in the input.
+///
+/// Comments and multiline hints are preserved, and formatting a formatted document
+/// leaves it unchanged. If the input is not valid CONL, the first error is returned.
+pub fn format(input: &[u8]) -> Result<String, SyntaxError> {
+    format_with(input, FormatOptions::default())
+}
+
+/// format_with is like [format], but indents sections using the given style (and optionally
+/// uses the given line ending). The indentation of the input is ignored, only the nesting
+/// depth is preserved. If [IndentStyle::Spaces] is zero, one space is used (as sections must be indented).
+pub fn format_with(input: &[u8], options: impl Into<FormatOptions>) -> Result<String, SyntaxError> {
+    let options = options.into();
+    parse(input).validate()?;
+
+    let mut formatter = Formatter {
+        output: String::new(),
+        indent: match options.indent {
+            IndentStyle::Spaces(n) => " ".repeat(n.max(1)),
+            IndentStyle::Tabs => "\t".to_string(),
+        },
+        depth: 0,
+        line_open: false,
+        after_key: false,
+        after_comment: false,
+        pending: Vec::new(),
+    };
+    for token in tokenize(input) {
+        formatter.write(token?)?;
+    }
+    if formatter.line_open {
+        formatter.output.push('\n');
+    }
+    formatter.flush_pending(false);
+    // values are unescaped, so the only newlines in the output are line endings
+    Ok(
+        match options.newline.unwrap_or_else(|| detect_newline(input)) {
+            NewlineStyle::Lf => formatter.output,
+            style => formatter.output.replace('\n', style.as_str()),
+        },
+    )
+}
+
+struct Formatter<'tok> {
+    output: String,
+    indent: String,
+    depth: usize,
+    // true if a key or list item has been written on the current line
+    line_open: bool,
+    // true if the current line has a map key (as opposed to a list item)
+    after_key: bool,
+    // true if the last token was a comment on a line of its own
+    after_comment: bool,
+    // blank lines (None) and comments on lines of their own, which are written
+    // at the indentation of the next key or list item.
+    pending: Vec<Option<&'tok str>>,
+}
+
+impl<'tok> Formatter<'tok> {
+    fn write(&mut self, token: Token<'tok>) -> Result<(), SyntaxError> {
+        match token {
+            Token::Newline(..) => {
+                if self.line_open {
+                    self.output.push('\n');
+                    self.line_open = false;
+                } else if self.after_comment {
+                    self.after_comment = false;
+                } else {
+                    self.pending.push(None);
+                }
+            }
+            Token::Comment(_, comment) => {
+                if self.line_open {
+                    self.output.push_str(" ;");
+                    if !comment.is_empty() {
+                        self.output.push(' ');
+                        self.output.push_str(comment);
+                    }
+                } else {
+                    self.pending.push(Some(comment));
+                    self.after_comment = true;
+                }
+            }
+            Token::Indent(..) => self.depth += 1,
+            Token::Outdent(..) => self.depth = self.depth.saturating_sub(1),
+            Token::ListItem(..) => {
+                self.start_line();
+                self.output.push('=');
+                self.after_key = false;
+            }
+            Token::MapKey(..) => {
+                self.start_line();
+                self.output
+                    .push_str(&escape_scalar(&token.unescape()?, true));
+                self.after_key = true;
+            }
+            Token::Value(..) => {
+                self.write_separator();
+                self.output
+                    .push_str(&escape_scalar(&token.unescape()?, false));
+            }
+            Token::MultilineHint(_, hint) => {
+                self.write_separator();
+                self.output.push_str("\"\"\"");
+                self.output.push_str(hint);
+            }
+            Token::MultilineValue(..) => {
+                // blank lines before the content are not part of the value
+                self.pending.clear();
+                for line in token.unescape()?.split('\n') {
+                    if !line.is_empty() {
+                        self.write_indent(self.depth + 1);
+                        self.output.push_str(line);
+                    }
+                    self.output.push('\n');
+                }
+            }
+            Token::NoValue(..) => {}
+        }
+        Ok(())
+    }
+
+    fn write_indent(&mut self, depth: usize) {
+        for _ in 0..depth {
+            self.output.push_str(&self.indent);
+        }
+    }
+
+    fn write_separator(&mut self) {
+        self.output
+            .push_str(if self.after_key { " = " } else { " " });
+    }
+
+    fn start_line(&mut self) {
+        self.flush_pending(true);
+        self.write_indent(self.depth);
+        self.line_open = true;
+    }
+
+    // writes any pending comments and blank lines. If there is no following line,
+    // trailing blank lines are dropped.
+    fn flush_pending(&mut self, more: bool) {
+        let mut blank = false;
+        for line in core::mem::take(&mut self.pending) {
+            match line {
+                None => blank = true,
+                Some(comment) => {
+                    self.write_blank(blank);
+                    blank = false;
+                    self.write_indent(self.depth);
+                    self.output.push(';');
+                    if !comment.is_empty() {
+                        self.output.push(' ');
+                        self.output.push_str(comment);
+                    }
+                    self.output.push('\n');
+                }
+            }
+        }
+        self.write_blank(blank && more);
+    }
+
+    fn write_blank(&mut self, blank: bool) {
+        if blank && !self.output.is_empty() {
+            self.output.push('\n');
+        }
+    }
+}
@@ -0,0 +1,47 @@
+use crate::json::is_number;
+use crate::Token;
+
+/// Scalar is the type of a value, as inferred by [infer_scalar].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Scalar {
+    /// an integer that fits in an i64 (e.g. `-12`)
+    Int(i64),
+    /// any other number in JSON syntax (e.g. `1.5`, `1e10`, or an integer too large for an i64)
+    Float(f64),
+    /// `true` or `false`
+    Bool(bool),
+    /// `null`
+    Null,
+    /// anything else
+    Str,
+}
+
+/// infer_scalar infers the type of an (unescaped) value.
+///
+/// CONL itself has no types, so this is only a convention, but it is the one used by
+/// [crate::to_json] with [crate::JsonOptions::infer_types]. Numbers must be in JSON syntax
+/// (so `+1`, `01`, `.5` and `0x10` are strings), and `true`, `false` and `null` are
+/// case-sensitive. See [infer_token] to treat quoted values as strings.
+pub fn infer_scalar(value: &str) -> Scalar {
+    match value {
+        "true" => Scalar::Bool(true),
+        "false" => Scalar::Bool(false),
+        "null" => Scalar::Null,
+        _ if is_number(value) => match value.parse() {
+            Ok(int) => Scalar::Int(int),
+            Err(_) => Scalar::Float(value.parse().unwrap_or(f64::NAN)),
+        },
+        _ => Scalar::Str,
+    }
+}
+
+/// infer_token is like [infer_scalar], but values that are quoted (see [Token::is_quoted])
+/// are always [Scalar::Str], and a [Token::NoValue] is [Scalar::Null].
+/// Tokens other than [Token::Value] are [Scalar::Str].
+pub fn infer_token(token: &Token<'_>) -> Scalar {
+    match token {
+        Token::NoValue(..) => Scalar::Null,
+        Token::Value(_, value) if !token.is_quoted() => infer_scalar(value),
+        _ => Scalar::Str,
+    }
+}
@@ -0,0 +1,73 @@
+use serde::ser::Error;
+
+use crate::Value;
+
+/// from_toml converts a TOML document to a [Value] (which can be written as CONL with `to_string()`).
+///
+/// Tables become maps (in the order they appear in the document), and arrays (including arrays
+/// of tables) become lists. Numbers, booleans and datetimes are written in TOML syntax.
+pub fn from_toml(input: &str) -> Result<Value, toml::de::Error> {
+    Ok(toml_to_value(toml::Value::Table(input.parse()?)))
+}
+
+/// to_toml converts a [Value] to a TOML document.
+///
+/// The value must be a map, and may not contain keys or list items with no value (as TOML has no null).
+/// Values that look like integers, floats, booleans or datetimes are written as such,
+/// and all other values are written as strings.
+pub fn to_toml(value: &Value) -> Result<String, toml::ser::Error> {
+    match value {
+        Value::Map(_) => toml::to_string(&value_to_toml(value)?),
+        Value::Null => Ok(String::new()),
+        _ => Err(toml::ser::Error::custom("TOML documents must be a map")),
+    }
+}
+
+fn toml_to_value(value: toml::Value) -> Value {
+    match value {
+        toml::Value::String(value) => Value::Scalar(value),
+        toml::Value::Table(table) => Value::Map(
+            table
+                .into_iter()
+                .map(|(key, value)| (key, toml_to_value(value)))
+                .collect(),
+        ),
+        toml::Value::Array(items) => Value::List(items.into_iter().map(toml_to_value).collect()),
+        toml::Value::Datetime(value) => Value::Scalar(value.to_string()),
+        value => Value::Scalar(value.to_string()),
+    }
+}
+
+fn value_to_toml(value: &Value) -> Result<toml::Value, toml::ser::Error> {
+    Ok(match value {
+        Value::Scalar(value) | Value::Multiline { value, .. } => scalar_to_toml(value),
+        Value::Map(entries) => toml::Value::Table(
+            entries
+                .iter()
+                .map(|(key, value)| Ok((key.clone(), value_to_toml(value)?)))
+                .collect::<Result<_, _>>()?,
+        ),
+        Value::List(items) => {
+            toml::Value::Array(items.iter().map(value_to_toml).collect::<Result<_, _>>()?)
+        }
+        Value::Null => return Err(toml::ser::Error::custom("TOML has no null value")),
+    })
+}
+
+fn scalar_to_toml(value: &str) -> toml::Value {
+    if let Ok(value) = value.parse() {
+        return toml::Value::Boolean(value);
+    }
+    if crate::json::is_number(value) {
+        if let Ok(value) = value.parse() {
+            return toml::Value::Integer(value);
+        }
+        if let Ok(value) = value.parse() {
+            return toml::Value::Float(value);
+        }
+    }
+    if let Ok(value) = value.parse() {
+        return toml::Value::Datetime(value);
+    }
+    toml::Value::String(value.into())
+}
@@ -0,0 +1,401 @@
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+use alloc::{format, vec};
+
+#[cfg(feature = "std")]
+use crate::ReadError;
+use crate::{events, infer_scalar, Event, Scalar, SyntaxError, Value};
+
+/// JsonOptions configures [to_json] and [write_json].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct JsonOptions {
+    /// indent nested objects and arrays by two spaces (like `JSON.stringify(v, null, 2)`)
+    pub pretty: bool,
+    /// write values that look like JSON numbers or booleans (`1.5`, `true`) as numbers or
+    /// booleans instead of strings. As with [crate::infer_token], quoted and multiline values
+    /// are always strings.
+    pub infer_types: bool,
+    /// how to write keys and list items with no value (`null` by default)
+    pub null: NullStyle,
+}
+
+/// NullStyle controls how [to_json] writes keys and list items with no value.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum NullStyle {
+    /// write `null`
+    #[default]
+    Null,
+    /// write an empty string (`""`)
+    EmptyString,
+    /// write an empty object (`{}`), as CONL writes an empty section with no value
+    EmptyObject,
+}
+
+impl NullStyle {
+    pub(crate) fn as_json(self) -> &'static str {
+        match self {
+            NullStyle::Null => "null",
+            NullStyle::EmptyString => "\"\"",
+            NullStyle::EmptyObject => "{}",
+        }
+    }
+}
+
+/// to_json converts a CONL document to JSON.
+///
+/// Maps become objects, lists become arrays, and keys or list items with no value become `null`
+/// (see [JsonOptions::null]). An empty document is an empty object. By default all values are strings, see [JsonOptions]
+/// to infer numbers and booleans.
+pub fn to_json(input: &[u8], options: JsonOptions) -> Result<String, SyntaxError> {
+    let mut output = String::new();
+    write_events(input, options, &mut |s: &str| {
+        output.push_str(s);
+        Ok::<_, SyntaxError>(())
+    })?;
+    Ok(output)
+}
+
+/// write_json is like [to_json], but writes the JSON to `w` as the input is parsed,
+/// so the output is never held in memory. Output is written in small pieces, so if `w`
+/// is not already buffered, wrap it in a [std::io::BufWriter].
+///
+/// If the input is invalid, any JSON written before the error is left in `w`.
+#[cfg(feature = "std")]
+pub fn write_json<W: std::io::Write>(
+    input: &[u8],
+    w: &mut W,
+    options: JsonOptions,
+) -> Result<(), ReadError> {
+    write_events(input, options, &mut |s: &str| {
+        w.write_all(s.as_bytes()).map_err(ReadError::Io)
+    })
+}
+
+fn write_events<E: From<SyntaxError>>(
+    input: &[u8],
+    options: JsonOptions,
+    write: &mut impl FnMut(&str) -> Result<(), E>,
+) -> Result<(), E> {
+    // whether each open object or array has any entries yet
+    let mut stack: Vec<bool> = Vec::new();
+    let mut after_key = false;
+    let mut empty = true;
+    for event in events(input) {
+        empty = false;
+        let event = event?;
+        if let Event::EndMap | Event::EndList = event {
+            if stack.pop().unwrap_or_default() && options.pretty {
+                write_newline(write, stack.len())?;
+            }
+            write(if event == Event::EndMap { "}" } else { "]" })?;
+            continue;
+        }
+        if let Some(has_entries) = stack.last_mut().filter(|_| !after_key) {
+            if core::mem::replace(has_entries, true) {
+                write(",")?;
+            }
+            if options.pretty {
+                write_newline(write, stack.len())?;
+            }
+        }
+        after_key = false;
+        match event {
+            Event::StartMap | Event::StartList => {
+                write(if event == Event::StartMap { "{" } else { "[" })?;
+                stack.push(false);
+            }
+            Event::Key(key) => {
+                write_string(&key, write)?;
+                write(if options.pretty { ": " } else { ":" })?;
+                after_key = true;
+            }
+            Event::Scalar { value, quoted } => {
+                let typed = !quoted
+                    && matches!(
+                        infer_scalar(&value),
+                        Scalar::Int(_) | Scalar::Float(_) | Scalar::Bool(_)
+                    );
+                if options.infer_types && typed {
+                    write(&value)?
+                } else {
+                    write_string(&value, write)?
+                }
+            }
+            Event::Null => write(options.null.as_json())?,
+            Event::EndMap | Event::EndList => unreachable!(),
+        }
+    }
+    if empty {
+        write("{}")?;
+    }
+    Ok(())
+}
+
+/// json_to_conl converts a JSON document to CONL (with two-space indentation).
+///
+/// The document must be an object or an array. Numbers and booleans are written as
+/// values, and `null` as a key or list item with no value. CONL cannot represent an
+/// empty object or array, so they are also written with no value.
+/// Errors in the JSON are returned as a [SyntaxError] with the line and column in the input,
+/// as is nesting deeper than 128 objects or arrays.
+pub fn json_to_conl(input: &str) -> Result<String, SyntaxError> {
+    let mut parser = JsonParser {
+        input,
+        offset: 0,
+        lno: 1,
+        line_start: 0,
+    };
+    parser.skip_whitespace();
+    if !input[parser.offset..].starts_with(['{', '[']) {
+        return Err(parser.error("expected object or array"));
+    }
+    let value = parser.parse_value(0)?;
+    parser.skip_whitespace();
+    if parser.offset < input.len() {
+        return Err(parser.error("unexpected characters after JSON value"));
+    }
+    Ok(value.to_string())
+}
+
+fn write_newline<E>(write: &mut impl FnMut(&str) -> Result<(), E>, depth: usize) -> Result<(), E> {
+    write("\n")?;
+    for _ in 0..depth {
+        write("  ")?;
+    }
+    Ok(())
+}
+
+fn write_string<E>(input: &str, write: &mut impl FnMut(&str) -> Result<(), E>) -> Result<(), E> {
+    write("\"")?;
+    let mut start = 0;
+    for (i, c) in input.char_indices() {
+        let control;
+        let escaped = match c {
+            '"' => "\\\"",
+            '\\' => "\\\\",
+            '\x08' => "\\b",
+            '\x0c' => "\\f",
+            '\n' => "\\n",
+            '\r' => "\\r",
+            '\t' => "\\t",
+            _ if c.is_ascii_control() => {
+                control = format!("\\u{:04x}", c as u32);
+                &control
+            }
+            _ => continue,
+        };
+        write(&input[start..i])?;
+        write(escaped)?;
+        start = i + c.len_utf8();
+    }
+    write(&input[start..])?;
+    write("\"")
+}
+
+// returns true if the value is a number in JSON syntax.
+pub(crate) fn is_number(value: &str) -> bool {
+    number_len(value) == Some(value.len())
+}
+
+// returns the length of the JSON number at the start of input.
+fn number_len(input: &str) -> Option<usize> {
+    let bytes = input.as_bytes();
+    let digits = |i: usize| bytes[i..].iter().take_while(|c| c.is_ascii_digit()).count();
+    let mut i = usize::from(bytes.first() == Some(&b'-'));
+    match digits(i) {
+        0 => return None,
+        n if n > 1 && bytes[i] == b'0' => return None,
+        n => i += n,
+    }
+    if bytes.get(i) == Some(&b'.') {
+        match digits(i + 1) {
+            0 => return None,
+            n => i += n + 1,
+        }
+    }
+    if matches!(bytes.get(i), Some(b'e' | b'E')) {
+        i += 1;
+        if matches!(bytes.get(i), Some(b'+' | b'-')) {
+            i += 1;
+        }
+        match digits(i) {
+            0 => return None,
+            n => i += n,
+        }
+    }
+    Some(i)
+}
+
+// the maximum nesting of objects and arrays accepted by json_to_conl.
+const MAX_JSON_DEPTH: usize = 128;
+
+struct JsonParser<'a> {
+    input: &'a str,
+    offset: usize,
+    lno: usize,
+    line_start: usize,
+}
+
+impl<'a> JsonParser<'a> {
+    fn error(&self, msg: impl Into<String>) -> SyntaxError {
+        SyntaxError::new(self.lno, self.offset - self.line_start + 1, msg)
+    }
+
+    fn rest(&self) -> &'a str {
+        &self.input[self.offset..]
+    }
+
+    fn skip_whitespace(&mut self) {
+        for c in self.rest().bytes() {
+            match c {
+                b'\n' => {
+                    self.lno += 1;
+                    self.line_start = self.offset + 1;
+                }
+                b' ' | b'\t' | b'\r' => {}
+                _ => break,
+            }
+            self.offset += 1;
+        }
+    }
+
+    // consumes the given character (after any whitespace) if it is next.
+    fn eat(&mut self, c: char) -> bool {
+        self.skip_whitespace();
+        let found = self.rest().starts_with(c);
+        if found {
+            self.offset += 1;
+        }
+        found
+    }
+
+    fn expect(&mut self, c: char) -> Result<(), SyntaxError> {
+        if !self.eat(c) {
+            return Err(self.error(format!("expected '{}'", c)));
+        }
+        Ok(())
+    }
+
+    fn parse_value(&mut self, depth: usize) -> Result<Value, SyntaxError> {
+        self.skip_whitespace();
+        let rest = self.rest();
+        if depth >= MAX_JSON_DEPTH && rest.starts_with(['{', '[']) {
+            return Err(self.error("too deeply nested"));
+        }
+        if self.eat('{') {
+            let mut entries = vec![];
+            if self.eat('}') {
+                return Ok(Value::Map(entries));
+            }
+            loop {
+                self.skip_whitespace();
+                if !self.rest().starts_with('"') {
+                    return Err(self.error("expected string"));
+                }
+                let key = self.parse_string()?;
+                self.expect(':')?;
+                entries.push((key, self.parse_value(depth + 1)?));
+                if self.eat('}') {
+                    return Ok(Value::Map(entries));
+                }
+                self.expect(',')?;
+            }
+        } else if self.eat('[') {
+            let mut items = vec![];
+            if self.eat(']') {
+                return Ok(Value::List(items));
+            }
+            loop {
+                items.push(self.parse_value(depth + 1)?);
+                if self.eat(']') {
+                    return Ok(Value::List(items));
+                }
+                self.expect(',')?;
+            }
+        } else if rest.starts_with('"') {
+            Ok(Value::Scalar(self.parse_string()?))
+        } else if let Some(len) = number_len(rest) {
+            self.offset += len;
+            Ok(Value::Scalar(rest[..len].into()))
+        } else if let Some(word) = ["true", "false", "null"]
+            .into_iter()
+            .find(|w| rest.starts_with(w))
+        {
+            self.offset += word.len();
+            Ok(match word {
+                "null" => Value::Null,
+                _ => Value::Scalar(word.into()),
+            })
+        } else if rest.is_empty() {
+            Err(self.error("unexpected end of input"))
+        } else {
+            Err(self.error("expected value"))
+        }
+    }
+
+    fn parse_string(&mut self) -> Result<String, SyntaxError> {
+        let mut output = String::new();
+        self.offset += 1;
+        loop {
+            let Some(c) = self.rest().chars().next() else {
+                return Err(self.error("unclosed string"));
+            };
+            match c {
+                '"' => {
+                    self.offset += 1;
+                    return Ok(output);
+                }
+                '\\' => output.push(self.parse_escape()?),
+                c if (c as u32) < 0x20 => return Err(self.error("control character in string")),
+                c => {
+                    output.push(c);
+                    self.offset += c.len_utf8();
+                }
+            }
+        }
+    }
+
+    fn parse_escape(&mut self) -> Result<char, SyntaxError> {
+        let c = match self.rest().as_bytes().get(1) {
+            Some(b'"') => '"',
+            Some(b'\\') => '\\',
+            Some(b'/') => '/',
+            Some(b'b') => '\x08',
+            Some(b'f') => '\x0c',
+            Some(b'n') => '\n',
+            Some(b'r') => '\r',
+            Some(b't') => '\t',
+            Some(b'u') => {
+                let high = self.parse_hex()?;
+                if !(0xd800..0xdc00).contains(&high) {
+                    return char::from_u32(high).ok_or_else(|| self.error("invalid escape code"));
+                }
+                let low = match self.rest().starts_with("\\u") {
+                    true => self.parse_hex()?,
+                    false => 0,
+                };
+                if !(0xdc00..0xe000).contains(&low) {
+                    return Err(self.error("invalid surrogate pair"));
+                }
+                return Ok(
+                    char::from_u32(0x10000 + ((high - 0xd800) << 10) + (low - 0xdc00)).unwrap(),
+                );
+            }
+            _ => return Err(self.error("invalid escape code")),
+        };
+        self.offset += 2;
+        Ok(c)
+    }
+
+    // parses a \uXXXX escape.
+    fn parse_hex(&mut self) -> Result<u32, SyntaxError> {
+        let Some(hex) =
+            (self.rest().get(2..6)).filter(|hex| hex.bytes().all(|c| c.is_ascii_hexdigit()))
+        else {
+            return Err(self.error("invalid escape code"));
+        };
+        let value = u32::from_str_radix(hex, 16).unwrap();
+        self.offset += 6;
+        Ok(value)
+    }
+}
@@ -0,0 +1,99 @@
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+
+use serde_json::{Map, Number};
+
+use crate::{events, infer_scalar, Event, JsonOptions, NullStyle, Scalar, SyntaxError, Value};
+
+/// to_serde_json converts a CONL document to a [serde_json::Value] (requires the
+/// `serde_json` feature).
+///
+/// It is the same as parsing the output of [crate::to_json]: maps become objects, lists
+/// become arrays, keys or list items with no value become `null` (see [JsonOptions::null]),
+/// and an empty document is an empty object. With [JsonOptions::infer_types] numbers and booleans are inferred
+/// (numbers that cannot be represented by serde_json are kept as strings).
+/// [JsonOptions::pretty] is ignored. If a key appears more than once, the last value is used.
+pub fn to_serde_json(input: &[u8], options: JsonOptions) -> Result<serde_json::Value, SyntaxError> {
+    // the open objects and arrays, with the key each will be stored under in its parent
+    let mut stack: Vec<(Option<String>, serde_json::Value)> = Vec::new();
+    let mut key = None;
+    for event in events(input) {
+        let value = match event? {
+            Event::StartMap => {
+                stack.push((key.take(), serde_json::Value::Object(Map::new())));
+                continue;
+            }
+            Event::StartList => {
+                stack.push((key.take(), serde_json::Value::Array(Vec::new())));
+                continue;
+            }
+            Event::Key(k) => {
+                key = Some(k.into_owned());
+                continue;
+            }
+            Event::Scalar { value, quoted } => scalar_to_json(value.into_owned(), quoted, options),
+            Event::Null => null_to_json(options),
+            Event::EndMap | Event::EndList => {
+                let (k, value) = stack.pop().unwrap();
+                if stack.is_empty() {
+                    return Ok(value);
+                }
+                key = k;
+                value
+            }
+        };
+        match &mut stack.last_mut().unwrap().1 {
+            serde_json::Value::Object(entries) => {
+                entries.insert(key.take().unwrap(), value);
+            }
+            serde_json::Value::Array(items) => items.push(value),
+            _ => unreachable!(),
+        }
+    }
+    // a document with no keys or list items
+    Ok(serde_json::Value::Object(Map::new()))
+}
+
+/// from_serde_json converts a [serde_json::Value] to a [Value] (which can be written as
+/// CONL with `to_string()`), as [crate::json_to_conl] does for JSON text.
+///
+/// Numbers and booleans become values, and `null` becomes [Value::Null]. CONL cannot
+/// represent an empty object or array, so they are written with no value.
+pub fn from_serde_json(value: &serde_json::Value) -> Value {
+    match value {
+        serde_json::Value::Null => Value::Null,
+        serde_json::Value::Bool(value) => Value::Scalar(value.to_string()),
+        serde_json::Value::Number(value) => Value::Scalar(value.to_string()),
+        serde_json::Value::String(value) => Value::Scalar(value.clone()),
+        serde_json::Value::Array(items) => Value::List(items.iter().map(from_serde_json).collect()),
+        serde_json::Value::Object(entries) => Value::Map(
+            entries
+                .iter()
+                .map(|(key, value)| (key.clone(), from_serde_json(value)))
+                .collect(),
+        ),
+    }
+}
+
+fn null_to_json(options: JsonOptions) -> serde_json::Value {
+    match options.null {
+        NullStyle::Null => serde_json::Value::Null,
+        NullStyle::EmptyString => serde_json::Value::String(String::new()),
+        NullStyle::EmptyObject => serde_json::Value::Object(Map::new()),
+    }
+}
+
+fn scalar_to_json(value: String, quoted: bool, options: JsonOptions) -> serde_json::Value {
+    if !options.infer_types || quoted {
+        return serde_json::Value::String(value);
+    }
+    match infer_scalar(&value) {
+        Scalar::Int(int) => serde_json::Value::Number(int.into()),
+        Scalar::Float(float) => match Number::from_f64(float) {
+            Some(number) => serde_json::Value::Number(number),
+            None => serde_json::Value::String(value),
+        },
+        Scalar::Bool(bool) => serde_json::Value::Bool(bool),
+        Scalar::Null | Scalar::Str => serde_json::Value::String(value),
+    }
+}
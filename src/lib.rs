@@ -1,53 +1,244 @@
-use std::borrow::Cow;
+#![cfg_attr(not(any(feature = "std", test)), no_std)]
 
+extern crate alloc;
+
+use alloc::borrow::Cow;
+use alloc::collections::BTreeSet;
+use alloc::string::{String, ToString};
+use alloc::{format, vec, vec::Vec};
+use core::str::Utf8Error;
+
+#[cfg(feature = "serde")]
+mod de;
+mod diff;
+mod events;
+mod format;
+mod infer;
+#[cfg(feature = "toml")]
+mod interop;
+mod json;
+#[cfg(feature = "serde_json")]
+mod json_value;
+mod lint;
+#[cfg(feature = "macros")]
+mod macros;
+mod outline;
+mod pairs;
+#[cfg(feature = "std")]
+mod reader;
+mod schema;
+#[cfg(feature = "serde")]
+mod ser;
+mod stats;
 #[cfg(test)]
 mod test;
+mod tree;
+mod trivia;
+mod value;
+mod visitor;
+mod writer;
+
+#[cfg(feature = "serde")]
+pub use de::{
+    from_slice, from_slice_with, from_str, from_str_with, DeserializeOptions, Deserializer,
+    DuplicateKeys,
+};
+pub use diff::{diff, Change};
+pub use events::{events, CommentedEvent, CommentedEvents, Event, Events};
+pub use format::{detect_newline, format, format_with, FormatOptions, IndentStyle, NewlineStyle};
+pub use infer::{infer_scalar, infer_token, Scalar};
+#[cfg(feature = "toml")]
+pub use interop::{from_toml, to_toml};
+#[cfg(feature = "std")]
+pub use json::write_json;
+pub use json::{json_to_conl, to_json, JsonOptions, NullStyle};
+#[cfg(feature = "serde_json")]
+pub use json_value::{from_serde_json, to_serde_json};
+pub use lint::{lint, Lint, LintKind};
+pub use outline::outline;
+pub use pairs::{pairs, Pairs};
+#[cfg(feature = "futures")]
+pub use reader::parse_async;
+#[cfg(feature = "std")]
+pub use reader::{parse_reader, ReadError, ReaderParser};
+pub use schema::{validate, validate_spanned, Field, Schema, SchemaError};
+#[cfg(feature = "serde")]
+pub use ser::{to_string, SerializeError, Serializer};
+pub use stats::{stats, Stats};
+pub use tree::{EditError, SyntaxTree};
+pub use trivia::{TriviaItem, TriviaPiece, WithTrivia};
+pub use value::{
+    canonicalize, parse_documents, parse_value, parse_value_sorted, parse_value_spanned, Documents,
+    Spanned, SpannedValue, Value,
+};
+pub use visitor::{drive, Visitor};
+pub use writer::{WriteError, Writer};
+
+/// Span is a range of byte offsets into the input.
+#[derive(Debug, Eq, PartialEq, Clone, Copy, Default)]
+pub struct Span {
+    pub start: usize,
+    pub end: usize,
+}
+
+impl From<Span> for core::ops::Range<usize> {
+    fn from(span: Span) -> Self {
+        span.start..span.end
+    }
+}
+
+/// Location records where a [Token] is in the input.
+/// Line and column numbers start from 1.
+#[derive(Debug, Eq, PartialEq, Clone, Copy, Default)]
+pub struct Location {
+    /// the line number
+    pub lno: usize,
+    /// the column as a byte offset into the line
+    pub col: usize,
+    /// the column counted in characters (useful for highlighting in editors).
+    /// A tab counts as one column unless [Tokenizer::tab_width] is set.
+    pub char_col: usize,
+    /// the bytes of the input that produced the token
+    pub span: Span,
+}
 
-/// A Token is a single token in the input with a line number attached.
+/// A Token is a single token in the input with its [Location] attached.
 /// They are generated by [parse] and [tokenize]. Use [Token::unescape] to get the actual value.
 #[derive(Debug, Eq, PartialEq, Clone)]
 pub enum Token<'tok> {
     /// Newline is \r \n or \r\n (you can likely skip this token unless building a formatter)
-    Newline(usize),
+    Newline(Location),
     /// Comment (you can likely skip this token unless building a formatter)
-    Comment(usize, &'tok str),
+    Comment(Location, &'tok str),
     /// Indent marks the beginning of a new section.
     /// Once you receive the first [Token::MapKey] or [Token::ListItem] you know if it's a map or a list
-    Indent(usize),
+    /// Its location is where the indentation ends, and its span covers the indentation.
+    /// It contains the complete indentation of the line (so formatters can tell tabs from spaces).
+    Indent(Location, &'tok str),
     /// Outdent marks the end of a section. You will receive one [Token::Outdent] per [Token::Indent]
     /// except in case of errors.
-    Outdent(usize),
+    /// Its location is where the indentation ends, and its span is empty.
+    Outdent(Location),
     /// ListItem indicates a new list item. Its value will be the next [Token::Value], [Token::MultilineValue] or [Token::Indent] you receive.
-    ListItem(usize),
+    ListItem(Location),
     /// Key indicates a new map key. Its value will be the next [Token::Value], [Token::MultilineValue] or [Token::Indent] you receive.
-    MapKey(usize, &'tok str),
-    /// Value contains a single-line value
-    Value(usize, &'tok str),
+    /// Spaces and tabs around the key are not part of it, but those within it are.
+    MapKey(Location, &'tok str),
+    /// Value contains a single-line value.
+    /// Spaces and tabs around the value are not part of it (and not in its span), but those within it are.
+    /// Use quotes to keep leading or trailing whitespace.
+    Value(Location, &'tok str),
     /// MultilineHint contains the language tag for a multiline value (you can likely skip this token unless building a formatter)
-    MultilineHint(usize, &'tok str),
+    /// A comment after the hint is yielded as a [Token::Comment] that follows it.
+    MultilineHint(Location, &'tok str),
     /// MultilineValue contains a multiline value
-    MultilineValue(usize, &'tok str, &'tok str),
+    MultilineValue(Location, &'tok str, &'tok str),
     /// NoValue indicates that a key or item had no value.
-    NoValue(usize),
+    /// Its location is that of the key or item, and its span is empty.
+    /// A key with nothing (or only whitespace) after the `=` also has no value, as does
+    /// a list item `=` on its own; the empty string must be written as `""`.
+    NoValue(Location),
 }
 
 impl<'tok> Token<'tok> {
+    /// returns the location at which the token starts
+    pub fn location(&self) -> Location {
+        match self {
+            Token::Newline(loc) => *loc,
+            Token::Comment(loc, _) => *loc,
+            Token::Indent(loc, _) => *loc,
+            Token::Outdent(loc) => *loc,
+            Token::ListItem(loc) => *loc,
+            Token::MapKey(loc, _) => *loc,
+            Token::Value(loc, _) => *loc,
+            Token::MultilineHint(loc, _) => *loc,
+            Token::MultilineValue(loc, _, _) => *loc,
+            Token::NoValue(loc) => *loc,
+        }
+    }
+
+    /// returns the range of bytes in the input that produced the token.
+    /// For quoted values this includes the quotes.
+    pub fn span(&self) -> Span {
+        self.location().span
+    }
+
     /// returns the line on which the token starts
     pub fn line_number(&self) -> usize {
+        self.location().lno
+    }
+
+    /// returns the byte column at which the token starts
+    /// (see [Location::char_col] for the column in characters)
+    pub fn column_number(&self) -> usize {
+        self.location().col
+    }
+
+    /// returns true if the token is a [Token::MapKey] or [Token::Value] written in quotes, or a
+    /// [Token::MultilineValue] (which is always a string). This is useful when inferring types,
+    /// for example to treat `"123"` as a string but `123` as a number.
+    pub fn is_quoted(&self) -> bool {
         match self {
-            Token::Newline(lno) => *lno,
-            Token::Comment(lno, _) => *lno,
-            Token::Indent(lno) => *lno,
-            Token::Outdent(lno) => *lno,
-            Token::ListItem(lno) => *lno,
-            Token::MapKey(lno, _) => *lno,
-            Token::Value(lno, _) => *lno,
-            Token::MultilineHint(lno, _) => *lno,
-            Token::MultilineValue(lno, _, _) => *lno,
-            Token::NoValue(lno) => *lno,
+            Token::MapKey(_, val) | Token::Value(_, val) => val.starts_with('"'),
+            Token::MultilineValue(..) => true,
+            _ => false,
         }
     }
 
+    /// returns the text of the token as written, with no unescaping: a [Token::MapKey] or
+    /// [Token::Value] including any quotes, a [Token::MultilineValue] including the indentation
+    /// of each line after the first, and a [Token::Comment] or [Token::MultilineHint] without the
+    /// leading `;` or `"""`. For a [Token::Indent] it returns the indentation, and for tokens
+    /// with no text (like [Token::Outdent]) it returns "".
+    ///
+    /// When writing a value back out, `raw()` is the original quoting, and is the same as
+    /// [escape] of the unescaped value if that quoting was already minimal.
+    pub fn raw(&self) -> &'tok str {
+        match self {
+            Token::Indent(_, raw)
+            | Token::Comment(_, raw)
+            | Token::MapKey(_, raw)
+            | Token::Value(_, raw)
+            | Token::MultilineHint(_, raw)
+            | Token::MultilineValue(_, _, raw) => raw,
+            _ => "",
+        }
+    }
+
+    /// returns the value as a bool (`true` or `false`).
+    pub fn as_bool(&self) -> Result<bool, SyntaxError> {
+        self.parse_scalar("bool")
+    }
+
+    /// returns the value as an integer.
+    pub fn as_i64(&self) -> Result<i64, SyntaxError> {
+        self.parse_scalar("integer")
+    }
+
+    /// returns the value as a floating point number.
+    pub fn as_f64(&self) -> Result<f64, SyntaxError> {
+        self.parse_scalar("number")
+    }
+
+    fn parse_scalar<T: core::str::FromStr>(&self, expected: &str) -> Result<T, SyntaxError> {
+        let loc = self.location();
+        let got = match self {
+            Token::MapKey(..) | Token::Value(..) | Token::MultilineValue(..) => {
+                let value = self.unescape()?;
+                if let Ok(result) = value.parse() {
+                    return Ok(result);
+                }
+                format!("'{}'", value)
+            }
+            _ => self.name().to_string(),
+        };
+        Err(SyntaxError::new(
+            loc.lno,
+            loc.col,
+            format!("expected {}, got {}", expected, got),
+        ))
+    }
+
     /// returns the line on which the token starts (to put in error messages like: "expected a map key got a X")
     pub fn name(&self) -> &'static str {
         match self {
@@ -68,142 +259,379 @@ impl<'tok> Token<'tok> {
     /// This is most useful for [Token::MapKey], [Token::Value] and [Token::MultilineValue]; but also
     /// returns the contents of a [Token::Comment] or [Token::MultilineHint] for formatters.
     /// Other tokens always return Ok(Cow::Borrowed(""))
+    ///
+    /// Escape sequences are only processed in quoted keys and values; in bare (unquoted) ones
+    /// a backslash is just a backslash (so `path = C:\new` needs no quoting). Use [Token::raw]
+    /// to get the text as written. In particular there is no `\=` or `\;`: a key that contains
+    /// `=` or `;`, or a value that contains `;`, must be quoted (`"a=b" = ";c"`), as [escape] does.
+    ///
+    /// `""` is the empty string, and a lone `"` is an unclosed quote (an error). As a value
+    /// `"""` is not a quoted string but the start of a multiline value.
     pub fn unescape(&self) -> Result<Cow<'tok, str>, SyntaxError> {
         use Token::*;
         match self {
-            MapKey(lno, val) | Value(lno, val) => {
+            MapKey(loc, val) | Value(loc, val) => {
                 if !val.starts_with('"') {
                     return Ok(Cow::Borrowed(val));
                 }
-                if val.starts_with('"') && val.ends_with('"') && val.len() > 1 {
-                    let possible = &val[1..val.len() - 1];
-                    if !possible.contains(['"', '\\']) {
-                        return Ok(Cow::Borrowed(possible));
-                    }
-                }
-
-                let mut output = String::new();
-                let mut chars = val.chars().skip(1);
-                let mut escaped = false;
-                let mut closed = false;
-                'outer: while let Some(c) = chars.next() {
-                    if !escaped {
-                        if c == '\\' {
-                            escaped = true
-                        } else if c == '"' {
-                            closed = true;
-                            break 'outer;
-                        } else {
-                            output.push(c)
-                        }
-                        continue;
-                    }
-                    match c {
-                        '"' => output.push(c),
-                        '\\' => output.push('\\'),
-                        'n' => output.push('\n'),
-                        'r' => output.push('\r'),
-                        't' => output.push('\t'),
-                        '{' => {
-                            let mut found = String::new();
-                            loop {
-                                match chars.next() {
-                                    None => break 'outer,
-                                    Some('}') => break,
-                                    Some(c) => found.push(c),
-                                }
-                            }
-                            let Some(ch) = u32::from_str_radix(&found, 16)
-                                .ok()
-                                .filter(|_| found.len() <= 8)
-                                .and_then(|num| num.try_into().ok())
-                            else {
-                                return Err(SyntaxError {
-                                    lno: *lno,
-                                    msg: format!("invalid escape code: \\{{{}}}", found),
-                                });
-                            };
-                            output.push(ch)
-                        }
-                        _ => {
-                            return Err(SyntaxError {
-                                lno: *lno,
-                                msg: format!("invalid escape code: \\{}", c),
-                            })
-                        }
-                    }
-                    escaped = false;
-                }
-                if escaped {
-                    return Err(SyntaxError {
-                        lno: *lno,
-                        msg: "invalid escape code: end of string".to_string(),
-                    });
-                }
-                if chars.next().is_some() {
-                    return Err(SyntaxError {
-                        lno: *lno,
-                        msg: "extra characters after quotes".to_string(),
-                    });
-                }
-                if !closed {
-                    return Err(SyntaxError {
-                        lno: *lno,
-                        msg: "unclosed quotes".to_string(),
-                    });
+                // the value can be borrowed if the first quote or backslash closes it
+                let plain = find_quote_or_backslash(&val[1..]).map_or(val.len(), |i| i + 1);
+                if plain == val.len() - 1 && val.ends_with('"') {
+                    return Ok(Cow::Borrowed(&val[1..plain]));
                 }
+                let mut output = String::with_capacity(val.len());
+                output.push_str(&val[1..plain]);
+                unescape_quoted_from(loc, val, plain, &mut output)?;
                 Ok(Cow::Owned(output))
             }
             MultilineValue(_, indent, val) => {
                 if !val.chars().any(is_newline_char) {
-                    return Ok(Cow::Borrowed(val));
+                    return Ok(Cow::Borrowed(val.strip_prefix(indent).unwrap_or(val)));
                 }
-                let content = val
-                    .lines()
-                    .flat_map(|line| line.split('\r'))
-                    .enumerate()
-                    .flat_map(|(i, line)| {
-                        let newline = if i > 0 { "\n" } else { "" };
-                        if let Some(content) = line.strip_prefix(indent) {
-                            vec![newline, content]
-                        } else if i == 0 {
-                            vec![newline, line]
-                        } else {
-                            vec![newline]
-                        }
-                    })
-                    .collect::<String>();
-                Ok(Cow::Owned(content))
+                let mut output = String::with_capacity(val.len());
+                self.unescape_into(&mut output)?;
+                Ok(Cow::Owned(output))
             }
             Comment(.., comment) => Ok(Cow::Borrowed(comment)),
             MultilineHint(.., hint) => Ok(Cow::Borrowed(hint)),
             _ => Ok(Cow::Borrowed("")),
         }
     }
+
+    /// to_source returns the token as it should be written in a CONL file, where
+    /// indent_level is the number of enclosing sections (indented by two spaces each).
+    /// Concatenating the results for each token of a document produces an equivalent document.
+    ///
+    /// * [Token::MapKey] is written as `key =` and [Token::ListItem] as `=` (both indented).
+    /// * [Token::Value] is written as ` value` (quoted if necessary, see [escape]), and
+    ///   [Token::MultilineHint] as ` """hint`.
+    /// * [Token::MultilineValue] is written on the following lines, indented one level deeper.
+    /// * [Token::Comment] is written as `; comment` after the indentation (or one space at the top level),
+    ///   so that it can appear either after a value or on a line of its own.
+    /// * [Token::Indent], [Token::Outdent] and [Token::NoValue] are empty; increment or decrement
+    ///   indent_level on [Token::Indent] and [Token::Outdent] so that the keys in the section are indented.
+    pub fn to_source(&self, indent_level: usize) -> Result<String, SyntaxError> {
+        let indent = "  ".repeat(indent_level);
+        // at the top level a comment may follow a value, so it needs a space before it
+        let comment_indent = if indent.is_empty() { " " } else { &indent };
+        Ok(match self {
+            Token::Newline(..) => "\n".to_string(),
+            Token::Comment(_, "") => format!("{};", comment_indent),
+            Token::Comment(_, comment) => format!("{}; {}", comment_indent, comment),
+            Token::Indent(..) | Token::Outdent(..) | Token::NoValue(..) => String::new(),
+            Token::ListItem(..) => format!("{}=", indent),
+            Token::MapKey(..) => format!("{}{} =", indent, escape_scalar(&self.unescape()?, true)),
+            Token::Value(..) => format!(" {}", escape_scalar(&self.unescape()?, false)),
+            Token::MultilineHint(_, hint) => format!(" \"\"\"{}", hint),
+            Token::MultilineValue(..) => {
+                let mut output = String::new();
+                for line in self.unescape()?.split('\n') {
+                    if !line.is_empty() {
+                        output.push_str(&indent);
+                        output.push_str("  ");
+                        output.push_str(line);
+                    }
+                    output.push('\n');
+                }
+                output
+            }
+        })
+    }
+
+    /// unescape_or is like [Token::unescape], but returns `default` for a [Token::NoValue]
+    /// (a key or list item with no value, including `key =`). A quoted empty string `""`
+    /// is still returned as "", so absent and empty values can be told apart.
+    pub fn unescape_or(&self, default: &'tok str) -> Result<Cow<'tok, str>, SyntaxError> {
+        match self {
+            Token::NoValue(..) => Ok(Cow::Borrowed(default)),
+            _ => self.unescape(),
+        }
+    }
+
+    /// unescape_into is like [Token::unescape], but appends the value to the given buffer.
+    /// Re-using one buffer for many tokens avoids allocating a new string for each value
+    /// that contains escape sequences or newlines.
+    pub fn unescape_into(&self, buf: &mut String) -> Result<(), SyntaxError> {
+        match self {
+            Token::MapKey(loc, val) | Token::Value(loc, val) if val.starts_with('"') => {
+                buf.reserve(val.len());
+                unescape_quoted(loc, val, buf)
+            }
+            Token::MultilineValue(_, indent, val) => {
+                buf.reserve(val.len());
+                for (i, (line, _)) in split_lines(val).enumerate() {
+                    if i > 0 {
+                        buf.push('\n');
+                    }
+                    match line.strip_prefix(indent) {
+                        Some(content) => buf.push_str(content),
+                        None if i == 0 => buf.push_str(line),
+                        None => {}
+                    }
+                }
+                Ok(())
+            }
+            _ => {
+                buf.push_str(&self.unescape()?);
+                Ok(())
+            }
+        }
+    }
+
+    /// unescape_raw is like [Token::unescape], but for a [Token::MultilineValue] it returns
+    /// the original text with only the indentation removed from each line. Newlines are
+    /// returned as they were in the input (\n, \r\n or \r) instead of being normalized to \n.
+    /// (Combine with [Tokenizer::verbatim_multiline] to get back exactly the bytes of an embedded file).
+    pub fn unescape_raw(&self) -> Result<Cow<'tok, str>, SyntaxError> {
+        let Token::MultilineValue(_, indent, val) = self else {
+            return self.unescape();
+        };
+        if !val.chars().any(is_newline_char) {
+            return Ok(Cow::Borrowed(val.strip_prefix(indent).unwrap_or(val)));
+        }
+        let mut content = String::with_capacity(val.len());
+        for (i, (line, newline)) in split_lines(val).enumerate() {
+            match line.strip_prefix(indent) {
+                Some(line) => content.push_str(line),
+                None if i == 0 => content.push_str(line),
+                None => {}
+            }
+            content.push_str(newline);
+        }
+        Ok(Cow::Owned(content))
+    }
+}
+
+// returns the byte offset of the first `"` or `\` in the string.
+fn find_quote_or_backslash(s: &str) -> Option<usize> {
+    s.bytes().position(|c| c == b'"' || c == b'\\')
+}
+
+// unescapes a quoted key or value, appending the result to output.
+fn unescape_quoted(loc: &Location, val: &str, output: &mut String) -> Result<(), SyntaxError> {
+    unescape_quoted_from(loc, val, 1, output)
+}
+
+// unescapes the rest of a quoted key or value from byte i, appending the result to output.
+// Runs of characters without quotes or backslashes are copied at once.
+fn unescape_quoted_from(
+    loc: &Location,
+    val: &str,
+    mut i: usize,
+    output: &mut String,
+) -> Result<(), SyntaxError> {
+    loop {
+        let Some(run) = find_quote_or_backslash(&val[i..]) else {
+            return Err(SyntaxError::new(loc.lno, loc.col, "unclosed quotes"));
+        };
+        output.push_str(&val[i..i + run]);
+        i += run;
+        if val.as_bytes()[i] == b'"' {
+            break;
+        }
+
+        let start = i;
+        let end_of_string = || {
+            SyntaxError::new(
+                loc.lno,
+                loc.col + start,
+                "invalid escape code: end of string",
+            )
+        };
+        let c = val[i + 1..].chars().next().ok_or_else(end_of_string)?;
+        i += 1 + c.len_utf8();
+        match c {
+            '"' => output.push('"'),
+            '\\' => output.push('\\'),
+            'n' => output.push('\n'),
+            'r' => output.push('\r'),
+            't' => output.push('\t'),
+            '{' => {
+                let len = val[i..].find('}').ok_or_else(end_of_string)?;
+                let found = &val[i..i + len];
+                i += len + 1;
+                let Some(ch) = u32::from_str_radix(found, 16)
+                    .ok()
+                    .filter(|_| found.len() <= 8)
+                    .and_then(|num| num.try_into().ok())
+                else {
+                    return Err(SyntaxError::new(
+                        loc.lno,
+                        loc.col + start,
+                        format!("invalid escape code: \\{{{}}}", found),
+                    ));
+                };
+                output.push(ch)
+            }
+            _ => {
+                return Err(SyntaxError::new(
+                    loc.lno,
+                    loc.col + start,
+                    format!("invalid escape code: \\{}", c),
+                ))
+            }
+        }
+    }
+    if i + 1 < val.len() {
+        return Err(SyntaxError::new(
+            loc.lno,
+            loc.col + i + 1,
+            "extra characters after quotes",
+        ));
+    }
+    Ok(())
+}
+
+/// OwnedToken is a [Token] that owns its contents, so that it can outlive the input.
+/// See [Token::into_owned].
+#[derive(Debug, Eq, PartialEq, Clone)]
+pub enum OwnedToken {
+    Newline(Location),
+    Comment(Location, String),
+    Indent(Location, String),
+    Outdent(Location),
+    ListItem(Location),
+    MapKey(Location, String),
+    Value(Location, String),
+    MultilineHint(Location, String),
+    MultilineValue(Location, String, String),
+    NoValue(Location),
+}
+
+impl Token<'_> {
+    /// returns a copy of the token that does not borrow from the input.
+    pub fn into_owned(self) -> OwnedToken {
+        match self {
+            Token::Newline(loc) => OwnedToken::Newline(loc),
+            Token::Comment(loc, comment) => OwnedToken::Comment(loc, comment.to_string()),
+            Token::Indent(loc, indent) => OwnedToken::Indent(loc, indent.to_string()),
+            Token::Outdent(loc) => OwnedToken::Outdent(loc),
+            Token::ListItem(loc) => OwnedToken::ListItem(loc),
+            Token::MapKey(loc, key) => OwnedToken::MapKey(loc, key.to_string()),
+            Token::Value(loc, value) => OwnedToken::Value(loc, value.to_string()),
+            Token::MultilineHint(loc, hint) => OwnedToken::MultilineHint(loc, hint.to_string()),
+            Token::MultilineValue(loc, indent, value) => {
+                OwnedToken::MultilineValue(loc, indent.to_string(), value.to_string())
+            }
+            Token::NoValue(loc) => OwnedToken::NoValue(loc),
+        }
+    }
 }
 
-#[derive(Debug)]
+impl OwnedToken {
+    /// returns a [Token] that borrows from this one (so you can call [Token::unescape] etc.).
+    pub fn as_token(&self) -> Token<'_> {
+        match self {
+            OwnedToken::Newline(loc) => Token::Newline(*loc),
+            OwnedToken::Comment(loc, comment) => Token::Comment(*loc, comment),
+            OwnedToken::Indent(loc, indent) => Token::Indent(*loc, indent),
+            OwnedToken::Outdent(loc) => Token::Outdent(*loc),
+            OwnedToken::ListItem(loc) => Token::ListItem(*loc),
+            OwnedToken::MapKey(loc, key) => Token::MapKey(*loc, key),
+            OwnedToken::Value(loc, value) => Token::Value(*loc, value),
+            OwnedToken::MultilineHint(loc, hint) => Token::MultilineHint(*loc, hint),
+            OwnedToken::MultilineValue(loc, indent, value) => {
+                Token::MultilineValue(*loc, indent, value)
+            }
+            OwnedToken::NoValue(loc) => Token::NoValue(*loc),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
 /// SyntaxError is returned when the input is invalid.
 pub struct SyntaxError {
     pub lno: usize,
+    /// the byte column (starting from 1) at which the error starts
+    pub col: usize,
     pub msg: String,
 }
 
 impl SyntaxError {
-    fn new(lno: usize, msg: impl Into<String>) -> Self {
+    fn new(lno: usize, col: usize, msg: impl Into<String>) -> Self {
         Self {
             lno,
+            col,
             msg: msg.into(),
         }
     }
+
+    /// returns a multi-line diagnostic that shows the offending line of the source
+    /// with a caret under the column at which the error starts.
+    pub fn with_caret(&self, source: &[u8]) -> String {
+        let mut rest = source;
+        for _ in 1..self.lno {
+            let Some(i) = rest.iter().position(is_newline) else {
+                rest = &[];
+                break;
+            };
+            rest = &rest[i + newline_size(&rest[i..])..];
+        }
+        let end = rest.iter().position(is_newline).unwrap_or(rest.len());
+        let line = String::from_utf8_lossy(&rest[..end]);
+        let prefix = line
+            .char_indices()
+            .take_while(|(i, _)| *i + 1 < self.col)
+            .map(|(_, c)| if c == '\t' { '\t' } else { ' ' })
+            .collect::<String>();
+        format!(
+            "{}:{}: {}\n{}\n{}^",
+            self.lno, self.col, self.msg, line, prefix
+        )
+    }
 }
 
-impl std::fmt::Display for SyntaxError {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+impl core::fmt::Display for SyntaxError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
         write!(f, "{}: {}", self.lno, self.msg)
     }
 }
 
+/// SyntaxError implements [core::error::Error] (which is `std::error::Error`, so also without
+/// the `std` feature), so it can be returned with `?` from functions that return
+/// `Box<dyn Error>`.
+impl core::error::Error for SyntaxError {}
+
+/// escape returns the string as it should be written in a CONL file (the inverse of [Token::unescape]).
+/// If the string can be written as is, it is returned unchanged; otherwise
+/// it is wrapped in quotes and any quotes, backslashes or control characters are escaped.
+/// The result is safe to use as either a map key or a value.
+pub fn escape(value: &str) -> Cow<'_, str> {
+    escape_scalar(value, true)
+}
+
+// values (unlike keys) may contain an = without quoting.
+fn escape_scalar(value: &str, is_key: bool) -> Cow<'_, str> {
+    let needs_quotes = value.is_empty()
+        || value.starts_with(['"', ' ', '\t'])
+        || value.ends_with([' ', '\t'])
+        || value
+            .contains(|c: char| c == ';' || (c == '=' && is_key) || (c.is_control() && c != '\t'));
+    if !needs_quotes {
+        return Cow::Borrowed(value);
+    }
+    Cow::Owned(quote(value))
+}
+
+// wraps the value in quotes, escaping it as necessary.
+fn quote(value: &str) -> String {
+    let mut output = String::with_capacity(value.len() + 2);
+    output.push('"');
+    for c in value.chars() {
+        match c {
+            '"' => output.push_str("\\\""),
+            '\\' => output.push_str("\\\\"),
+            '\n' => output.push_str("\\n"),
+            '\r' => output.push_str("\\r"),
+            '\t' => output.push_str("\\t"),
+            _ if c.is_control() => output.push_str(&format!("\\{{{:x}}}", c as u32)),
+            _ => output.push(c),
+        }
+    }
+    output.push('"');
+    output
+}
+
 fn is_whitespace(&c: &u8) -> bool {
     c == b' ' || c == b'\t'
 }
@@ -216,6 +644,22 @@ fn is_newline(&c: &u8) -> bool {
 fn is_newline_char(c: char) -> bool {
     c == '\r' || c == '\n'
 }
+// splits on \r, \n or \r\n, returning each line and the newline that ended it. Unlike
+// str::lines a trailing newline results in an empty last line.
+fn split_lines(s: &str) -> impl Iterator<Item = (&str, &str)> {
+    let mut rest = Some(s);
+    core::iter::from_fn(move || {
+        let s = rest?;
+        let Some(i) = s.find(is_newline_char) else {
+            rest = None;
+            return Some((s, ""));
+        };
+        let end = i + newline_size(&s.as_bytes()[i..]);
+        rest = Some(&s[end..]);
+        Some((&s[..i], &s[i..end]))
+    })
+}
+
 fn newline_size(s: &[u8]) -> usize {
     if s.first() == Some(&b'\r') && s.get(1) == Some(&b'\n') {
         2
@@ -224,35 +668,414 @@ fn newline_size(s: &[u8]) -> usize {
     }
 }
 
+pub(crate) const BOM: &[u8] = b"\xEF\xBB\xBF";
+
 /// tokenize iterates over the CONL tokens in the input. It does not
 /// validate the structure of the file, so is suitable for using if you
 /// need error-tolerant parsing (e.g. for a linter).
-/// It continues after yielding errors.
-/// See [parse] for a stricter interface.
+/// It continues after yielding errors: each error consumes the offending key, value,
+/// comment or multiline value, and tokenizing resumes with the rest of the line
+/// (or the next line). See [parse] for a stricter interface.
+///
+/// A UTF-8 byte order mark at the start of the input is skipped.
 pub fn tokenize(input: &[u8]) -> Tokenizer<'_> {
+    let bom = if input.starts_with(BOM) { BOM.len() } else { 0 };
     Tokenizer {
-        input,
+        source: input,
+        input: &input[bom..],
         expect_indent: true,
         expect_value: false,
         expect_multiline: false,
         current_indent: None,
         indent_stack: vec![&[]],
+        indent_lines: vec![0],
         lno: 1,
+        line_start: bom,
+        source_offset: 0,
+        is_utf8: false,
+        verbatim_multiline: false,
+        raw_multiline_hint: false,
+        trim_values: true,
+        deny_nul: false,
+        consistent_indent: false,
+        consistent_sibling_indent: false,
+        sibling_indents: Vec::new(),
+        tab_width: 1,
+        comment_char: b';',
+        limits: ParserLimits::default(),
+    }
+}
+
+/// Checkpoint is the state of a [Tokenizer] at the start of a line.
+/// See [Tokenizer::checkpoint] and [Tokenizer::resume].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Checkpoint {
+    offset: usize,
+    lno: usize,
+    indent_stack: Vec<Vec<u8>>,
+    indent_lines: Vec<usize>,
+    sibling_indents: Vec<Option<(Vec<u8>, usize)>>,
+    expect_multiline: bool,
+}
+
+impl Checkpoint {
+    /// returns the byte offset in the input of the line at which the checkpoint was taken
+    pub fn offset(&self) -> usize {
+        self.offset
+    }
+
+    /// returns the line number at which the checkpoint was taken
+    pub fn line_number(&self) -> usize {
+        self.lno
+    }
+}
+
+/// TokenizerOptions configures [tokenize_with] and [parse_with] to read dialects of CONL.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TokenizerOptions {
+    /// the character that starts a comment (`;` by default).
+    /// It must be ASCII punctuation other than `=` or `"`.
+    pub comment_char: u8,
+}
+
+impl Default for TokenizerOptions {
+    fn default() -> Self {
+        TokenizerOptions { comment_char: b';' }
+    }
+}
+
+/// tokenize_with is like [tokenize], but with the given options.
+///
+/// # Panics
+///
+/// If [TokenizerOptions::comment_char] is not ASCII punctuation, or is `=` or `"`.
+pub fn tokenize_with(input: &[u8], options: TokenizerOptions) -> Tokenizer<'_> {
+    let c = options.comment_char;
+    assert!(
+        c.is_ascii_punctuation() && c != b'=' && c != b'"',
+        "invalid comment character: {:?}",
+        c as char
+    );
+    Tokenizer {
+        comment_char: c,
+        ..tokenize(input)
+    }
+}
+
+/// tokenize_str is like [tokenize], but takes a `&str`. As the input is known to
+/// be valid UTF-8, tokens are not validated again.
+pub fn tokenize_str(input: &str) -> Tokenizer<'_> {
+    Tokenizer {
+        is_utf8: true,
+        ..tokenize(input.as_bytes())
+    }
+}
+
+/// ParserLimits restricts the size of a document, for parsing untrusted input.
+/// Lengths are in bytes, as written in the input (before unescaping).
+/// By default there are no limits. See [Parser::limits].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ParserLimits {
+    /// the maximum length of a map key
+    pub max_key_len: usize,
+    /// the maximum length of a single-line value
+    pub max_value_len: usize,
+    /// the maximum length of a multiline value (including the indentation of each line)
+    pub max_multiline_bytes: usize,
+    /// the maximum nesting depth (see [Parser::max_depth])
+    pub max_depth: usize,
+}
+
+impl Default for ParserLimits {
+    fn default() -> Self {
+        ParserLimits {
+            max_key_len: usize::MAX,
+            max_value_len: usize::MAX,
+            max_multiline_bytes: usize::MAX,
+            max_depth: usize::MAX,
+        }
     }
 }
 
 /// See [tokenize]
+#[derive(Clone)]
 pub struct Tokenizer<'tok> {
+    source: &'tok [u8],
     input: &'tok [u8],
     indent_stack: Vec<&'tok [u8]>,
+    // the line on which each section in the indent_stack started
+    indent_lines: Vec<usize>,
     current_indent: Option<&'tok [u8]>,
     expect_indent: bool,
     expect_value: bool,
     expect_multiline: bool,
     lno: usize,
+    line_start: usize,
+    // where the source starts in the complete input (see parse_reader)
+    source_offset: usize,
+    // true if the source is known to be valid UTF-8 (see tokenize_str)
+    is_utf8: bool,
+    verbatim_multiline: bool,
+    raw_multiline_hint: bool,
+    trim_values: bool,
+    deny_nul: bool,
+    consistent_indent: bool,
+    consistent_sibling_indent: bool,
+    // the indentation and line of the first section opened in each section of the
+    // indent_stack (only tracked if consistent_sibling_indent is set)
+    sibling_indents: Vec<Option<(&'tok [u8], usize)>>,
+    tab_width: usize,
+    comment_char: u8,
+    limits: ParserLimits,
 }
 
 impl<'tok> Tokenizer<'tok> {
+    /// verbatim_multiline makes [Token::MultilineValue] include any blank lines
+    /// before and after the content, and the final newline. When unescaped only the
+    /// indentation of each line is removed, which makes it possible to embed files exactly.
+    /// (By default leading and trailing whitespace is removed from multiline values).
+    pub fn verbatim_multiline(mut self) -> Self {
+        self.verbatim_multiline = true;
+        self
+    }
+
+    /// raw_multiline_hint makes [Token::MultilineHint] contain the hint exactly as written
+    /// (up to any comment or the end of the line), for passing to tools that are sensitive
+    /// to whitespace. (By default leading and trailing whitespace is removed from the hint).
+    pub fn raw_multiline_hint(mut self) -> Self {
+        self.raw_multiline_hint = true;
+        self
+    }
+
+    /// trim_values controls whether leading and trailing whitespace is removed from
+    /// [Token::Value] (the default). With `trim_values(false)` a bare value is everything
+    /// between the `=` (or key) and the comment or end of the line, including the space that
+    /// separates them, so `a =  x ` has the value `"  x "`. Quoted values are always trimmed
+    /// (whitespace inside the quotes is kept either way), and a line with only whitespace
+    /// after the key still has no value.
+    pub fn trim_values(mut self, trim: bool) -> Self {
+        self.trim_values = trim;
+        self
+    }
+
+    /// deny_nul makes it an error for a key or value (including a multiline value) to contain
+    /// a NUL byte, which usually indicates a corrupt file. (A `\{0}` escape in a quoted key or
+    /// value is still allowed).
+    pub fn deny_nul(mut self) -> Self {
+        self.deny_nul = true;
+        self
+    }
+
+    /// consistent_indent makes it an error for a section to be indented with different
+    /// whitespace from its parent (e.g. tabs inside a section indented with spaces),
+    /// or for a line to be outdented to an indentation that doesn't match an enclosing section.
+    /// (By default only the indentation of the enclosing sections is compared).
+    pub fn consistent_indent(mut self) -> Self {
+        self.consistent_indent = true;
+        self
+    }
+
+    /// consistent_sibling_indent makes it an error for sections with the same parent to be
+    /// indented differently (e.g. one map indented by two spaces and the next by four).
+    /// (By default each section only needs to be indented more than its parent).
+    pub fn consistent_sibling_indent(mut self) -> Self {
+        self.consistent_sibling_indent = true;
+        self.sibling_indents = vec![None; self.indent_stack.len()];
+        self
+    }
+
+    /// checkpoint returns the state of the tokenizer, so that tokenizing can be resumed
+    /// from this point with [Tokenizer::resume] (for example by an editor that only
+    /// re-tokenizes the lines after an edit). Checkpoints can only be taken at the start
+    /// of a line (before any tokens on it have been returned, and not within a multiline
+    /// value), so this returns None elsewhere.
+    pub fn checkpoint(&self) -> Option<Checkpoint> {
+        let offset = self.offset(self.input);
+        if !self.expect_indent || self.current_indent.is_some() || offset != self.line_start {
+            return None;
+        }
+        Some(Checkpoint {
+            offset,
+            lno: self.lno,
+            indent_stack: self.indent_stack.iter().map(|i| i.to_vec()).collect(),
+            indent_lines: self.indent_lines.clone(),
+            sibling_indents: self
+                .sibling_indents
+                .iter()
+                .map(|sibling| sibling.map(|(indent, lno)| (indent.to_vec(), lno)))
+                .collect(),
+            expect_multiline: self.expect_multiline,
+        })
+    }
+
+    /// resume continues tokenizing from a [Checkpoint] taken by [Tokenizer::checkpoint].
+    /// The input must be the same up to [Checkpoint::offset] (the input after that may
+    /// have changed), and locations are reported relative to the start of the input.
+    /// Options (like [Tokenizer::verbatim_multiline]) are not part of the checkpoint, and
+    /// should be set on this tokenizer as they were on the original.
+    ///
+    /// # Panics
+    /// If [Checkpoint::offset] is not the start of a line in this input (for example because
+    /// the input before it has changed, or it is from a different document).
+    pub fn resume(mut self, checkpoint: &'tok Checkpoint) -> Self {
+        let offset = checkpoint.offset;
+        let at_line_start = offset <= self.source.len()
+            && (offset == 0
+                || is_newline(&self.source[offset - 1])
+                || (offset == BOM.len() && self.source.starts_with(BOM)));
+        // as a line starts after an ASCII newline, it is also a character boundary
+        assert!(at_line_start, "checkpoint is not at the start of a line");
+        self.input = &self.source[offset..];
+        self.line_start = offset;
+        self.lno = checkpoint.lno;
+        self.indent_stack = checkpoint.indent_stack.iter().map(|i| &i[..]).collect();
+        self.indent_lines = checkpoint.indent_lines.clone();
+        self.sibling_indents = checkpoint
+            .sibling_indents
+            .iter()
+            .map(|sibling| sibling.as_ref().map(|(indent, lno)| (&indent[..], *lno)))
+            .collect();
+        self.expect_indent = true;
+        self.expect_value = false;
+        self.expect_multiline = checkpoint.expect_multiline;
+        self.current_indent = None;
+        self
+    }
+
+    /// tab_width makes a tab advance [Location::char_col] to the next multiple of `n`
+    /// columns (as most editors display it), so that diagnostics line up with what the
+    /// user sees. (By default a tab counts as one column, like any other character).
+    pub fn tab_width(mut self, n: usize) -> Self {
+        self.tab_width = n.max(1);
+        self
+    }
+
+    // returns the column (starting from 1) after the given start of a line, counting
+    // characters and expanding tabs to tab_width.
+    fn char_col(&self, line: &[u8]) -> usize {
+        line.iter()
+            .filter(|&c| c & 0xc0 != 0x80)
+            .fold(0, |col, &c| match c {
+                b'\t' => (col / self.tab_width + 1) * self.tab_width,
+                _ => col + 1,
+            })
+            + 1
+    }
+
+    fn pop_indent(&mut self) {
+        self.indent_stack.pop();
+        self.indent_lines.pop();
+        if self.consistent_sibling_indent {
+            self.sibling_indents.pop();
+        }
+    }
+
+    fn inconsistent_indent(&self, loc: Location, section: usize) -> SyntaxError {
+        SyntaxError::new(
+            loc.lno,
+            loc.col,
+            format!("inconsistent indentation{}", self.opened_on(section)),
+        )
+    }
+
+    // describes where the section at the given depth of the indent stack started,
+    // so that indentation errors can point to the indentation that should have matched.
+    fn opened_on(&self, section: usize) -> String {
+        match section {
+            0 => String::new(),
+            _ => format!(" (section opened on line {})", self.indent_lines[section]),
+        }
+    }
+
+    fn to_str(&self, slice: &'tok [u8]) -> Result<&'tok str, Utf8Error> {
+        if self.is_utf8 {
+            // SAFETY: the source is valid UTF-8, and tokens are only split at ASCII characters.
+            return Ok(unsafe { core::str::from_utf8_unchecked(slice) });
+        }
+        core::str::from_utf8(slice)
+    }
+
+    fn offset(&self, slice: &[u8]) -> usize {
+        slice.as_ptr() as usize - self.source.as_ptr() as usize
+    }
+
+    fn input_offset(&self, slice: &[u8]) -> usize {
+        self.source_offset + self.offset(slice)
+    }
+
+    fn location(&self, slice: &[u8]) -> Location {
+        let line = &self.source[self.line_start..self.offset(slice)];
+        let start = self.input_offset(slice);
+        Location {
+            lno: self.lno,
+            col: line.len() + 1,
+            char_col: self.char_col(line),
+            span: Span {
+                start,
+                end: start + slice.len(),
+            },
+        }
+    }
+
+    // returns the location of rest extended to the end of the (possibly empty) trimmed part.
+    fn location_until(&self, rest: &'tok [u8], prefix: usize, trimmed: &str) -> Location {
+        let mut loc = self.location(&rest[..prefix]);
+        if !trimmed.is_empty() {
+            loc.span.end = self.input_offset(trimmed.as_bytes()) + trimmed.len();
+        }
+        loc
+    }
+
+    // returns the location of slice, which may be several lines after the start of
+    // line (which is on line number lno).
+    fn location_from(&self, lno: usize, line: &[u8], slice: &[u8]) -> Location {
+        let skipped = &self.source[self.offset(line)..self.offset(slice)];
+        let start = self.input_offset(slice);
+        let mut loc = Location {
+            lno,
+            span: Span {
+                start,
+                end: start + slice.len(),
+            },
+            ..Location::default()
+        };
+        let mut line = skipped;
+        let mut was_cr = false;
+        for (i, c) in skipped.iter().enumerate() {
+            if is_newline(c) {
+                if !(was_cr && *c == b'\n') {
+                    loc.lno += 1;
+                }
+                line = &skipped[i + 1..];
+            }
+            was_cr = *c == b'\r';
+        }
+        loc.col = line.len() + 1;
+        loc.char_col = self.char_col(line);
+        loc
+    }
+
+    // returns the position of the first NUL byte in the slice if deny_nul is set.
+    fn find_nul(&self, slice: &[u8]) -> Option<usize> {
+        self.deny_nul
+            .then(|| slice.iter().position(|&c| c == 0))
+            .flatten()
+    }
+
+    // reports the position of the first invalid byte in the slice
+    fn invalid_utf8(&self, slice: &[u8], err: Utf8Error) -> SyntaxError {
+        let bad = &slice[err.valid_up_to()..];
+        self.utf8_error(self.location(bad))
+    }
+
+    fn utf8_error(&self, loc: Location) -> SyntaxError {
+        SyntaxError::new(
+            loc.lno,
+            loc.col,
+            format!("invalid UTF-8 at byte {}", loc.span.start),
+        )
+    }
+
     fn consume_whitespace(&mut self) -> (&'tok [u8], &'tok [u8]) {
         let i = self.input.iter().position(|c| !is_whitespace(c));
         self.input.split_at(i.unwrap_or(self.input.len()))
@@ -260,60 +1083,90 @@ impl<'tok> Tokenizer<'tok> {
 
     fn consume_comment(&mut self, rest: &'tok [u8]) -> Result<Token<'tok>, SyntaxError> {
         let i = rest.iter().position(is_newline).unwrap_or(rest.len());
-        let (comment, rest) = rest.split_at(i);
-        self.input = rest;
-        let str = std::str::from_utf8(comment)
-            .map_err(|_| SyntaxError::new(self.lno, "invalid UTF-8"))?;
+        let (comment, remaining) = rest.split_at(i);
+        self.input = remaining;
+        let str = self
+            .to_str(&comment[1..])
+            .map_err(|e| self.invalid_utf8(&comment[1..], e))?;
+        let comment = str.trim_matches(is_whitespace_char);
         Ok(Token::Comment(
-            self.lno,
-            str.trim_matches(is_whitespace_char),
+            self.location_until(rest, 1, comment),
+            comment,
         ))
     }
 
-    fn consume_value(&mut self, rest: &'tok [u8]) -> Result<Token<'tok>, SyntaxError> {
-        if let Some(hint) = rest.strip_prefix(&[b'"', b'"', b'"']) {
-            return self.consume_multiline_hint(hint);
+    // leading is the whitespace between the key (or `=`) and rest.
+    fn consume_value(
+        &mut self,
+        leading: &'tok [u8],
+        rest: &'tok [u8],
+    ) -> Result<Token<'tok>, SyntaxError> {
+        if rest.starts_with(b"\"\"\"") {
+            return self.consume_multiline_hint(rest);
         }
 
         let mut quoted = rest.first() == Some(&b'"');
         let mut end = rest.len();
         let mut was_escape = false;
         for (i, c) in rest.iter().enumerate() {
-            if is_newline(c) || (c == &b';' && !quoted) {
+            if is_newline(c) || (c == &self.comment_char && !quoted) {
                 end = i;
                 break;
             }
             if i > 0 && !was_escape && c == &b'"' {
                 quoted = false;
             }
-            was_escape = c == &b'\\'
+            was_escape = c == &b'\\' && !was_escape
         }
 
         let (value, rest) = rest.split_at(end);
         self.input = rest;
-        let str =
-            std::str::from_utf8(value).map_err(|_| SyntaxError::new(self.lno, "invalid UTF-8"))?;
-        let value = str.trim_matches(is_whitespace_char);
-        Ok(Token::Value(self.lno, value))
+        if let Some(i) = self.find_nul(value) {
+            let loc = self.location(&value[i..]);
+            return Err(SyntaxError::new(loc.lno, loc.col, "NUL byte in value"));
+        }
+        let str = self
+            .to_str(value)
+            .map_err(|e| self.invalid_utf8(value, e))?;
+        let mut value = str.trim_matches(is_whitespace_char);
+        if !self.trim_values && !value.starts_with('"') {
+            let raw = &self.source[self.offset(leading)..self.offset(rest)];
+            // leading is only spaces and tabs, so raw is valid UTF-8 if str is
+            value = self.to_str(raw).unwrap_or(str);
+        }
+        let loc = self.location(value.as_bytes());
+        if value.len() > self.limits.max_value_len {
+            return Err(SyntaxError::new(loc.lno, loc.col, "value too long"));
+        }
+        Ok(Token::Value(loc, value))
     }
 
     fn consume_multiline_hint(&mut self, rest: &'tok [u8]) -> Result<Token<'tok>, SyntaxError> {
-        let mut end = rest.len();
-        for (i, c) in rest.iter().enumerate() {
-            if is_newline(c) || c == &b';' {
+        let hint = &rest[3..];
+        let mut end = hint.len();
+        for (i, c) in hint.iter().enumerate() {
+            if is_newline(c) || c == &self.comment_char {
                 end = i;
                 break;
             }
         }
-        let (value, rest) = rest.split_at(end);
-        self.input = rest;
+        let (value, remaining) = hint.split_at(end);
+        self.input = remaining;
 
-        let str =
-            std::str::from_utf8(value).map_err(|_| SyntaxError::new(self.lno, "invalid UTF-8"))?;
-        let value = str.trim_matches(is_whitespace_char);
+        let str = self
+            .to_str(value)
+            .map_err(|e| self.invalid_utf8(value, e))?;
+        let value = if self.raw_multiline_hint {
+            str
+        } else {
+            str.trim_matches(is_whitespace_char)
+        };
 
         self.expect_multiline = true;
-        Ok(Token::MultilineHint(self.lno, value))
+        Ok(Token::MultilineHint(
+            self.location_until(rest, 3, value),
+            value,
+        ))
     }
 
     fn consume_key(&mut self, rest: &'tok [u8]) -> Result<Token<'tok>, SyntaxError> {
@@ -322,14 +1175,14 @@ impl<'tok> Tokenizer<'tok> {
         let mut quoted = rest.first() == Some(&b'"');
 
         for (i, c) in rest.iter().enumerate() {
-            if is_newline(c) || (c == &b';' && !quoted) || (c == &b'=' && !quoted) {
+            if is_newline(c) || (c == &self.comment_char && !quoted) || (c == &b'=' && !quoted) {
                 end = i;
                 break;
             }
             if i > 0 && !was_escape && c == &b'"' {
                 quoted = false;
             }
-            was_escape = c == &b'\\'
+            was_escape = c == &b'\\' && !was_escape
         }
 
         let (key, rest) = rest.split_at(end);
@@ -339,17 +1192,36 @@ impl<'tok> Tokenizer<'tok> {
             self.input = &self.input[1..];
         }
 
-        let str =
-            std::str::from_utf8(key).map_err(|_| SyntaxError::new(self.lno, "invalid UTF-8"))?;
-        Ok(Token::MapKey(
-            self.lno,
-            str.trim_matches(is_whitespace_char),
-        ))
+        if let Some(i) = self.find_nul(key) {
+            let loc = self.location(&key[i..]);
+            return Err(SyntaxError::new(loc.lno, loc.col, "NUL byte in key"));
+        }
+        let str = self.to_str(key).map_err(|e| self.invalid_utf8(key, e))?;
+        let key = str.trim_matches(is_whitespace_char);
+        let loc = self.location(key.as_bytes());
+        if key.len() > self.limits.max_key_len {
+            return Err(SyntaxError::new(loc.lno, loc.col, "key too long"));
+        }
+        Ok(Token::MapKey(loc, key))
+    }
+
+    // in verbatim mode, a multiline value may start with blank lines. If the input
+    // is at the start of one, returns the indentation of the first non-blank line.
+    fn multiline_indent(&self) -> Option<&'tok [u8]> {
+        let line = self
+            .input
+            .split_inclusive(is_newline)
+            .find(|line| !line.iter().all(|c| is_whitespace(c) || is_newline(c)))?;
+        let i = line.iter().position(|c| !is_whitespace(c)).unwrap();
+        let indent = &line[..i];
+        let current = self.indent_stack.last().unwrap();
+        (indent.len() > current.len() && indent.starts_with(current)).then_some(indent)
     }
 
     fn consume_multiline(&mut self, indent: &'tok [u8]) -> Result<Token<'tok>, SyntaxError> {
         let mut end = 0;
         let lno = self.lno;
+        let start = self.input;
         let mut was_cr = false;
 
         for line in self.input.split_inclusive(is_newline) {
@@ -365,13 +1237,40 @@ impl<'tok> Tokenizer<'tok> {
         }
         let (value, rest) = self.input.split_at(end);
         self.input = rest;
+        if let Some(i) = value.iter().rposition(is_newline) {
+            self.line_start = self.offset(value) + i + 1;
+        }
+        if let Some(i) = self.find_nul(value) {
+            let loc = self.location_from(lno, start, &value[i..]);
+            return Err(SyntaxError::new(loc.lno, loc.col, "NUL byte in value"));
+        }
 
-        let str = std::str::from_utf8(value).map_err(|_| SyntaxError::new(lno, "invalid UTF-8"))?;
-        Ok(Token::MultilineValue(
-            lno,
-            std::str::from_utf8(indent).unwrap(),
-            str.trim_matches(|c| is_newline_char(c) || is_whitespace_char(c)),
-        ))
+        let str = self.to_str(value).map_err(|e| {
+            let bad = &value[e.valid_up_to()..];
+            self.utf8_error(self.location_from(lno, start, bad))
+        })?;
+        self.expect_indent = true;
+        let content = if self.verbatim_multiline {
+            str
+        } else {
+            str.trim_matches(|c| is_newline_char(c) || is_whitespace_char(c))
+        };
+
+        // the content may start after some blank lines
+        let loc = self.location_from(lno, start, content.as_bytes());
+
+        let indent = self.to_str(indent).map_err(|e| {
+            let bad = &indent[e.valid_up_to()..];
+            self.utf8_error(self.location_from(lno, start, bad))
+        })?;
+        if value.len() > self.limits.max_multiline_bytes {
+            return Err(SyntaxError::new(
+                loc.lno,
+                loc.col,
+                "multiline value too long",
+            ));
+        }
+        Ok(Token::MultilineValue(loc, indent, content))
     }
 }
 
@@ -385,23 +1284,32 @@ impl<'tok> Iterator for Tokenizer<'tok> {
             self.consume_whitespace()
         };
         if rest.first().is_some_and(is_newline) {
-            self.input = &rest[newline_size(rest)..];
+            if self.verbatim_multiline && self.expect_multiline && self.expect_indent {
+                if let Some(indent) = self.multiline_indent() {
+                    self.expect_multiline = false;
+                    return Some(self.consume_multiline(indent));
+                }
+            }
+            let (newline, remaining) = rest.split_at(newline_size(rest));
+            let loc = self.location(newline);
+            self.input = remaining;
             self.lno += 1;
+            self.line_start = self.offset(self.input);
             self.expect_indent = true;
             self.expect_value = false;
-            return Some(Ok(Token::Newline(self.lno - 1)));
+            return Some(Ok(Token::Newline(loc)));
         }
 
         let Some(first) = rest.first() else {
             if self.indent_stack.len() > 1 {
-                self.indent_stack.pop();
-                return Some(Ok(Token::Outdent(self.lno)));
+                self.pop_indent();
+                return Some(Ok(Token::Outdent(self.location(rest))));
             }
             return None;
         };
 
-        if *first == b';' && !(self.expect_indent && self.expect_multiline) {
-            return Some(self.consume_comment(&rest[1..]));
+        if *first == self.comment_char && !(self.expect_indent && self.expect_multiline) {
+            return Some(self.consume_comment(rest));
         }
 
         if self.expect_indent {
@@ -414,15 +1322,50 @@ impl<'tok> Iterator for Tokenizer<'tok> {
                 }
             }
             if indent != current {
+                self.input = rest;
+                let mut loc = self.location(&rest[..0]);
                 if indent.len() > current.len() && indent.starts_with(current) {
+                    let extra = &indent[current.len()..];
+                    let ws = current.first().unwrap_or(&extra[0]);
+                    if self.consistent_indent && extra.iter().any(|c| c != ws) {
+                        let section = self.indent_stack.len() - 1;
+                        return Some(Err(self.inconsistent_indent(loc, section)));
+                    }
+                    if self.consistent_sibling_indent {
+                        let first = self.sibling_indents.last_mut().unwrap();
+                        match *first {
+                            Some((sibling, lno)) if sibling != indent => {
+                                return Some(Err(SyntaxError::new(
+                                    loc.lno,
+                                    loc.col,
+                                    format!(
+                                        "sibling indentation mismatch (section opened on line {})",
+                                        lno
+                                    ),
+                                )));
+                            }
+                            _ => *first = Some((indent, self.lno)),
+                        }
+                        self.sibling_indents.push(None);
+                    }
                     self.indent_stack.push(indent);
-                    self.input = rest;
-                    return Some(Ok(Token::Indent(self.lno)));
+                    self.indent_lines.push(self.lno);
+                    loc.span.start = self.input_offset(indent);
+                    // indentation is only spaces and tabs, so is always valid UTF-8
+                    let indent = self.to_str(indent).unwrap_or_default();
+                    return Some(Ok(Token::Indent(loc, indent)));
                 } else {
-                    self.indent_stack.pop();
+                    if self.consistent_indent && !self.indent_stack.contains(&indent) {
+                        let section = self
+                            .indent_stack
+                            .iter()
+                            .rposition(|i| indent.starts_with(i));
+                        return Some(Err(self.inconsistent_indent(loc, section.unwrap_or(0))));
+                    }
+                    self.pop_indent();
                     self.current_indent = Some(indent);
                     self.expect_indent = true;
-                    return Some(Ok(Token::Outdent(self.lno)));
+                    return Some(Ok(Token::Outdent(loc)));
                 }
             }
         }
@@ -431,23 +1374,36 @@ impl<'tok> Iterator for Tokenizer<'tok> {
             b'=' if !self.expect_value => {
                 self.expect_value = true;
                 self.input = &rest[1..];
-                Some(Ok(Token::ListItem(self.lno)))
+                Some(Ok(Token::ListItem(self.location(&rest[..1]))))
             }
             _ if self.expect_value => {
                 self.expect_value = false;
-                Some(self.consume_value(rest))
+                Some(self.consume_value(indent, rest))
             }
             _ => Some(self.consume_key(rest)),
         }
     }
 }
 
-#[derive(PartialEq)]
-enum SectionType {
+/// SectionType is whether a section contains map keys or list items.
+/// Within one [Token::Indent] ... [Token::Outdent] block (or the top level) the type never
+/// changes: the parser returns an error for a list item in a map or a key in a list.
+/// See [Parser::section_type] and [Parser::peek_section_type].
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum SectionType {
     List,
     Map,
 }
 
+// Section tracks the type of each enclosing section (and the line of its first
+// entry), and (if duplicate keys are denied) the keys seen in it so far.
+#[derive(Default)]
+struct Section {
+    kind: Option<SectionType>,
+    lno: usize,
+    keys: BTreeSet<String>,
+}
+
 /// parse iterates over a CONL file, returning [Token]s. In the case of an error it will
 /// yield a [SyntaxError] and then stop returning more tokens.
 /// You can likely ignore [Token::Newline], [Token::Comment] and [Token::MultilineHint].
@@ -458,30 +1414,329 @@ enum SectionType {
 /// Within a given Indent/Outdent section you'll always see either only [Token::MapKey] or [Token::ListItem].
 /// For an error-tolerant version see [tokenize].
 pub fn parse(input: &[u8]) -> Parser<'_> {
-    Parser::new(input)
+    Parser::new(tokenize(input))
+}
+
+/// parse_with is like [parse], but with the given options (see [tokenize_with]).
+pub fn parse_with(input: &[u8], options: TokenizerOptions) -> Parser<'_> {
+    Parser::new(tokenize_with(input, options))
+}
+
+/// parse_str is like [parse], but takes a `&str`. As the input is known to
+/// be valid UTF-8, tokens are not validated again.
+///
+/// For a large file (for example one that is memory-mapped) it is faster to validate the
+/// whole input once with [core::str::from_utf8] and use parse_str than to validate each token.
+/// If the input is already known to be valid UTF-8, [parse_trusted] skips validation entirely.
+pub fn parse_str(input: &str) -> Parser<'_> {
+    Parser::new(tokenize_str(input))
+}
+
+/// parse_trusted is like [parse], but does not check that the input is valid UTF-8
+/// (for example for a large memory-mapped file that has already been validated).
+///
+/// # Safety
+/// The input must be valid UTF-8. Tokens are converted to `&str` without checking,
+/// so invalid UTF-8 is undefined behavior.
+pub unsafe fn parse_trusted(input: &[u8]) -> Parser<'_> {
+    parse_str(core::str::from_utf8_unchecked(input))
 }
 
 /// See [parse]
 pub struct Parser<'tok> {
     tokenizer: Tokenizer<'tok>,
     peek: Option<Option<Token<'tok>>>,
-    multiline_hint: Option<usize>,
-    needs_value: Option<usize>,
+    multiline_hint: Option<Location>,
+    needs_value: Option<Location>,
+    // the key or list item that was just given a value on the same line
+    has_value: Option<Location>,
     errored: bool,
-    stack: Vec<Option<SectionType>>,
+    stack: Vec<Section>,
+    deny_duplicate_keys: bool,
+    max_depth: usize,
+    require_content: bool,
+    recover: bool,
+    errors: Vec<SyntaxError>,
+    // in recovery mode, the number of (unexpected) indents that are being skipped
+    skipping: Option<usize>,
+    // true if the last token from the tokenizer ended a line
+    line_start: bool,
+    hint_before_value: bool,
+    no_inline_comments: bool,
+    deferred_hint: Option<Token<'tok>>,
 }
 
 impl<'tok> Parser<'tok> {
-    fn new(input: &'tok [u8]) -> Self {
+    fn new(tokenizer: Tokenizer<'tok>) -> Self {
         Parser {
-            tokenizer: tokenize(input),
+            tokenizer,
             multiline_hint: None,
             needs_value: None,
+            has_value: None,
             errored: false,
-            stack: vec![None],
+            stack: vec![Section::default()],
             peek: None,
+            deny_duplicate_keys: false,
+            max_depth: usize::MAX,
+            require_content: false,
+            recover: false,
+            errors: Vec::new(),
+            skipping: None,
+            line_start: true,
+            hint_before_value: false,
+            no_inline_comments: false,
+            deferred_hint: None,
         }
     }
+
+    /// deny_duplicate_keys makes the parser return a [SyntaxError] if the same key
+    /// appears twice in one map. Keys are compared after unescaping, so `a` and `"a"`
+    /// are considered the same.
+    pub fn deny_duplicate_keys(mut self) -> Self {
+        self.deny_duplicate_keys = true;
+        self
+    }
+
+    /// recover makes the parser continue after an error (which is useful for editors
+    /// that want to show all diagnostics). Each error is still yielded, but then the
+    /// rest of the line (and any section nested under it) is skipped and parsing
+    /// resumes at the next key or list item at the current or a lower indentation.
+    /// Keys and list items that lost their value are followed by a [Token::NoValue],
+    /// so the structure described in [parse] still holds.
+    pub fn recover(mut self) -> Self {
+        self.recover = true;
+        self
+    }
+
+    /// See [Tokenizer::verbatim_multiline]
+    pub fn verbatim_multiline(mut self) -> Self {
+        self.tokenizer.verbatim_multiline = true;
+        self
+    }
+
+    /// See [Tokenizer::raw_multiline_hint]
+    pub fn raw_multiline_hint(mut self) -> Self {
+        self.tokenizer.raw_multiline_hint = true;
+        self
+    }
+
+    /// See [Tokenizer::trim_values]
+    pub fn trim_values(mut self, trim: bool) -> Self {
+        self.tokenizer.trim_values = trim;
+        self
+    }
+
+    /// See [Tokenizer::deny_nul]
+    pub fn deny_nul(mut self) -> Self {
+        self.tokenizer.deny_nul = true;
+        self
+    }
+
+    /// See [Tokenizer::consistent_indent]
+    pub fn consistent_indent(mut self) -> Self {
+        self.tokenizer.consistent_indent = true;
+        self
+    }
+
+    /// See [Tokenizer::consistent_sibling_indent]
+    pub fn consistent_sibling_indent(mut self) -> Self {
+        self.tokenizer = self.tokenizer.consistent_sibling_indent();
+        self
+    }
+
+    /// See [Tokenizer::tab_width]
+    pub fn tab_width(mut self, n: usize) -> Self {
+        self.tokenizer = self.tokenizer.tab_width(n);
+        self
+    }
+
+    /// hint_before_value makes the parser yield each [Token::MultilineHint] immediately
+    /// before the [Token::MultilineValue] it applies to, instead of before the
+    /// newline (and any comment) that follows it. This makes it easy to pair the language
+    /// tag with the value.
+    pub fn hint_before_value(mut self) -> Self {
+        self.hint_before_value = true;
+        self
+    }
+
+    /// no_inline_comments makes the parser return a [SyntaxError] for a comment that
+    /// follows a key, value, list item or multiline hint on the same line, so that
+    /// comments must be on lines of their own.
+    pub fn no_inline_comments(mut self) -> Self {
+        self.no_inline_comments = true;
+        self
+    }
+
+    /// max_depth makes the parser return a [SyntaxError] if sections are nested more than
+    /// `n` levels deep (so with `max_depth(0)` only top-level keys and list items are allowed).
+    /// By default there is no limit, so if you're parsing untrusted input with a recursive
+    /// consumer (like [parse_value]) you should set one to avoid overflowing the stack.
+    pub fn max_depth(mut self, n: usize) -> Self {
+        self.max_depth = n;
+        self
+    }
+
+    /// require_content makes the parser return a [SyntaxError] at the end of a document
+    /// that has no keys or list items (for example an empty file, or one with only comments),
+    /// which is usually a mistake in a required config file.
+    pub fn require_content(mut self) -> Self {
+        self.require_content = true;
+        self
+    }
+
+    /// limits makes the parser return a [SyntaxError] if the document exceeds any of the
+    /// given [ParserLimits] (including [Parser::max_depth], which it replaces).
+    pub fn limits(mut self, limits: ParserLimits) -> Self {
+        self.tokenizer.limits = limits;
+        self.max_depth = limits.max_depth;
+        self
+    }
+
+    /// returns the errors seen so far (in recovery mode there may be more than one).
+    pub fn errors(&self) -> &[SyntaxError] {
+        &self.errors
+    }
+
+    /// validate checks the structure of the rest of the document, returning the first error.
+    /// As tokens are not unescaped, invalid escape sequences in quoted values are not reported.
+    pub fn validate(self) -> Result<(), SyntaxError> {
+        for token in self {
+            token?;
+        }
+        Ok(())
+    }
+
+    /// owned converts the parser into an iterator of [OwnedToken]s, which can be kept
+    /// after the input has been dropped.
+    pub fn owned(self) -> impl Iterator<Item = Result<OwnedToken, SyntaxError>> + 'tok {
+        self.map(|token| token.map(Token::into_owned))
+    }
+
+    /// section_type returns whether the current section (the one opened by the most recent
+    /// [Token::Indent], or the top level) is a map or a list, or None if no key or list item
+    /// has been returned in it yet. See [Parser::peek_section_type] to read ahead.
+    pub fn section_type(&self) -> Option<SectionType> {
+        self.stack.last()?.kind
+    }
+
+    /// peek_section_type returns whether the current section (the one opened by the most
+    /// recent [Token::Indent], or the top level) is a map or a list, reading ahead if its
+    /// first key or list item has not been returned yet. Returns None for an empty document.
+    pub fn peek_section_type(&self) -> Option<SectionType> {
+        if let Some(kind) = self.section_type() {
+            return Some(kind);
+        }
+        for token in self.lookahead() {
+            match token {
+                Ok(Token::Newline(..) | Token::Comment(..)) => {}
+                Ok(Token::MapKey(..)) => return Some(SectionType::Map),
+                Ok(Token::ListItem(..)) => return Some(SectionType::List),
+                _ => return None,
+            }
+        }
+        None
+    }
+
+    // returns the tokens that have not yet been returned, without validating them.
+    pub(crate) fn lookahead(&self) -> impl Iterator<Item = Result<Token<'tok>, SyntaxError>> {
+        let peek = self.peek.clone().flatten().map(Ok);
+        peek.into_iter().chain(self.tokenizer.clone())
+    }
+
+    /// returns the current nesting depth: the number of [Token::Indent]s returned that
+    /// have not yet been closed by a [Token::Outdent] (so 0 at the top level).
+    pub fn depth(&self) -> usize {
+        self.stack.len().saturating_sub(1)
+    }
+
+    /// consumed returns the byte offset in the input up to which the parser has read,
+    /// not counting any token it has read ahead but not yet returned. After an error this
+    /// is where parsing stopped, which is useful for showing the surrounding lines.
+    pub fn consumed(&self) -> usize {
+        match &self.peek {
+            Some(Some(token)) => token.location().span.start,
+            _ => self.tokenizer.input_offset(self.tokenizer.input),
+        }
+    }
+
+    /// is_complete returns true if the tokens returned so far form a complete document:
+    /// every [Token::Indent] has been closed, and no key, list item or multiline hint is
+    /// waiting for its value. A REPL that reads CONL line by line can check this after the
+    /// [Token::Newline] at the end of the input to decide whether to keep reading.
+    /// (Once the parser reaches the end of the input, all sections are closed).
+    pub fn is_complete(&self) -> bool {
+        self.depth() == 0 && self.needs_value.is_none() && self.multiline_hint.is_none()
+    }
+
+    /// skip_value consumes the value of the [Token::MapKey] or [Token::ListItem] that
+    /// was just returned: a [Token::Value], [Token::MultilineValue] or [Token::NoValue], or
+    /// a nested section up to and including its [Token::Outdent].
+    /// This is useful for ignoring unknown keys without tracking the depth yourself.
+    /// If no value is waiting to be read (for example it has already been returned),
+    /// nothing is consumed.
+    pub fn skip_value(&mut self) -> Result<(), SyntaxError> {
+        if self.needs_value.is_none() && self.multiline_hint.is_none() {
+            return Ok(());
+        }
+        let mut depth = 0;
+        for token in self.by_ref() {
+            match token? {
+                Token::Indent(..) => depth += 1,
+                Token::Outdent(..) if depth <= 1 => break,
+                Token::Outdent(..) => depth -= 1,
+                Token::Value(..) | Token::MultilineValue(..) | Token::NoValue(..) if depth == 0 => {
+                    break
+                }
+                _ => {}
+            }
+        }
+        Ok(())
+    }
+
+    // records the error, and either stops parsing or (in recovery mode) starts
+    // skipping tokens.
+    fn error(
+        &mut self,
+        e: SyntaxError,
+        skipping: Option<usize>,
+    ) -> Option<Result<Token<'tok>, SyntaxError>> {
+        self.errors.push(e.clone());
+        if self.recover {
+            self.skipping = skipping;
+            self.deferred_hint = None;
+            if let Some(loc) = self.multiline_hint.take() {
+                self.needs_value = Some(loc);
+            }
+        } else {
+            self.errored = true;
+        }
+        Some(Err(e))
+    }
+
+    // returns true if the token should be skipped while recovering from an error.
+    fn skip(&mut self, token: &Option<Token<'tok>>, line_start: bool) -> bool {
+        let Some(depth) = self.skipping else {
+            return false;
+        };
+        let skip = match token {
+            Some(Token::Newline(..) | Token::Comment(..)) => return false,
+            Some(Token::Indent(..)) => {
+                self.skipping = Some(depth + 1);
+                return true;
+            }
+            Some(Token::Outdent(..)) if depth > 0 => {
+                self.skipping = Some(depth - 1);
+                return true;
+            }
+            Some(Token::MapKey(..) | Token::ListItem(..)) => !line_start || depth > 0,
+            Some(Token::Outdent(..)) | None => false,
+            _ => true,
+        };
+        if !skip {
+            self.skipping = None;
+        }
+        skip
+    }
 }
 
 impl<'tok> Iterator for Parser<'tok> {
@@ -493,78 +1748,176 @@ impl<'tok> Iterator for Parser<'tok> {
         }
         use Token::*;
 
-        let next = if let Some(peek) = self.peek.take() {
-            peek
+        // (comments are never peeked, so line_start only matters for tokens from the tokenizer)
+        let (next, line_start) = if let Some(peek) = self.peek.take() {
+            (peek, false)
         } else {
-            match self.tokenizer.next() {
-                Some(Err(e)) => {
-                    self.errored = true;
-                    return Some(Err(e));
+            loop {
+                let line_start = self.line_start;
+                let next = match self.tokenizer.next() {
+                    Some(Err(_)) if self.skipping.is_some() => continue,
+                    Some(Err(e)) => return self.error(e, Some(0)),
+                    None => None,
+                    Some(Ok(next)) => Some(next),
+                };
+                self.line_start = matches!(
+                    next,
+                    Some(Newline(..) | Indent(..) | Outdent(..) | MultilineValue(..))
+                );
+                if !self.skip(&next, line_start) {
+                    break (next, line_start);
                 }
-                None => None,
-                Some(Ok(next)) => Some(next),
             }
         };
 
         match next {
+            Some(Comment(loc, _)) if self.no_inline_comments && !line_start => {
+                // the structure is unaffected, so (unlike self.error) there is nothing to recover
+                let e = SyntaxError::new(loc.lno, loc.col, "inline comment");
+                self.errors.push(e.clone());
+                self.errored = !self.recover;
+                return Some(Err(e));
+            }
             Some(Newline(..)) | Some(Comment(..)) => return Ok(next).transpose(),
             _ => {}
         };
 
-        let token = if let Some(lno) = self.multiline_hint.take() {
+        let has_value = self.has_value.take();
+        let token = if let Some(loc) = self.multiline_hint.take() {
             match next {
-                Some(MultilineValue(..)) => next,
+                Some(MultilineValue(..)) => {
+                    if let Some(hint) = self.deferred_hint.take() {
+                        self.multiline_hint = Some(loc);
+                        self.peek = Some(next);
+                        Some(hint)
+                    } else {
+                        next
+                    }
+                }
                 _ => {
-                    self.errored = true;
-                    return Some(Err(SyntaxError::new(lno, "missing value")));
+                    self.deferred_hint = None;
+                    self.peek = Some(next);
+                    self.needs_value = Some(loc);
+                    return self.error(SyntaxError::new(loc.lno, loc.col, "missing value"), None);
                 }
             }
-        } else if let Some(lno) = self.needs_value.take() {
+        } else if let Some(loc) = self.needs_value.take() {
             match next {
                 Some(MultilineHint(..)) => {
-                    self.multiline_hint = Some(lno);
+                    self.multiline_hint = Some(loc);
+                    if self.hint_before_value {
+                        self.deferred_hint = next;
+                        return self.next();
+                    }
                     next
                 }
-                Some(Value(..)) => next,
-                Some(Indent(..)) => {
-                    self.stack.push(None);
+                Some(Value(..)) => {
+                    self.has_value = Some(loc);
+                    next
+                }
+                Some(Indent(indent, _)) => {
+                    if self.stack.len() > self.max_depth {
+                        self.needs_value = Some(loc);
+                        return self.error(
+                            SyntaxError::new(
+                                indent.lno,
+                                indent.col,
+                                "maximum nesting depth exceeded",
+                            ),
+                            Some(1),
+                        );
+                    }
+                    self.stack.push(Section::default());
                     next
                 }
                 _ => {
                     self.peek = Some(next);
-                    Some(Token::NoValue(lno))
+                    Some(Token::NoValue(Location {
+                        span: Span {
+                            start: loc.span.start,
+                            end: loc.span.start,
+                        },
+                        ..loc
+                    }))
                 }
             }
         } else {
             match next {
-                Some(MapKey(lno, value)) => {
+                Some(MapKey(loc, value)) => {
                     let last = self.stack.last_mut().unwrap();
-                    if last.get_or_insert(SectionType::Map) == &SectionType::List {
-                        self.errored = true;
-                        return Some(Err(SyntaxError::new(lno, "expected list item")));
+                    if last.kind.is_none() {
+                        last.lno = loc.lno;
+                    }
+                    if last.kind.get_or_insert(SectionType::Map) == &SectionType::List {
+                        let msg = format!(
+                            "expected list item (section started as list on line {})",
+                            last.lno
+                        );
+                        return self.error(SyntaxError::new(loc.lno, loc.col, msg), Some(0));
                     }
-                    self.needs_value = Some(lno);
-                    Some(MapKey(lno, value))
+                    if self.deny_duplicate_keys {
+                        let key = match MapKey(loc, value).unescape() {
+                            Ok(key) => key,
+                            Err(e) => return self.error(e, Some(0)),
+                        };
+                        if !last.keys.insert(key.into_owned()) {
+                            return self.error(
+                                SyntaxError::new(loc.lno, loc.col, "duplicate key"),
+                                Some(0),
+                            );
+                        }
+                    }
+                    self.needs_value = Some(loc);
+                    Some(MapKey(loc, value))
                 }
-                Some(ListItem(lno)) => {
+                Some(ListItem(loc)) => {
                     let last = self.stack.last_mut().unwrap();
-                    if last.get_or_insert(SectionType::List) == &SectionType::Map {
-                        self.errored = true;
-                        return Some(Err(SyntaxError::new(lno, "expected map key")));
+                    if last.kind.is_none() {
+                        last.lno = loc.lno;
+                    }
+                    if last.kind.get_or_insert(SectionType::List) == &SectionType::Map {
+                        let msg = format!(
+                            "expected map key (section started as map on line {})",
+                            last.lno
+                        );
+                        return self.error(SyntaxError::new(loc.lno, loc.col, msg), Some(0));
                     }
-                    self.needs_value = Some(lno);
-                    Some(ListItem(lno))
+                    self.needs_value = Some(loc);
+                    Some(ListItem(loc))
+                }
+                None if self.require_content
+                    && self.stack.len() == 1
+                    && self.stack[0].kind.is_none() =>
+                {
+                    self.require_content = false;
+                    return self.error(SyntaxError::new(1, 1, "empty document"), None);
                 }
                 None | Some(Outdent(_)) => {
                     self.stack.pop();
                     next
                 }
-                Some(Indent(lno)) => {
-                    self.errored = true;
-                    return Some(Err(SyntaxError::new(lno, "unexpected indent")));
+                Some(Indent(loc, _)) => {
+                    let msg = if let Some(entry) = has_value {
+                        let name = match self.stack.last().unwrap().kind {
+                            Some(SectionType::List) => "list item",
+                            _ => "key",
+                        };
+                        format!(
+                            "unexpected indent ({} on line {} already has a value)",
+                            name, entry.lno
+                        )
+                    } else {
+                        // the indent has been pushed, so the enclosing section is the one before it
+                        let section = self.tokenizer.indent_stack.len().saturating_sub(2);
+                        format!("unexpected indent{}", self.tokenizer.opened_on(section))
+                    };
+                    return self.error(SyntaxError::new(loc.lno, loc.col, msg), Some(1));
                 }
-                _ => {
-                    unreachable!()
+                Some(token) => {
+                    // the tokenizer only yields values after keys and list items
+                    let loc = token.location();
+                    let msg = format!("unexpected {}", token.name());
+                    return self.error(SyntaxError::new(loc.lno, loc.col, msg), Some(0));
                 }
             }
         };
@@ -0,0 +1,131 @@
+use alloc::string::String;
+use alloc::vec::Vec;
+use alloc::{format, vec};
+
+use crate::{escape_scalar, tokenize, Token};
+
+/// Lint is a style issue found by [lint].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Lint {
+    pub lno: usize,
+    pub col: usize,
+    pub kind: LintKind,
+    pub message: String,
+}
+
+/// LintKind is the kind of a [Lint].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LintKind {
+    /// a section is indented by a different amount than the first indented section
+    InconsistentIndentWidth,
+    /// a line ends with spaces or tabs
+    TrailingWhitespace,
+    /// indentation mixes tabs and spaces (within a line, or across the document)
+    MixedIndentation,
+    /// a key or value is quoted, but would be the same without quotes
+    RedundantQuotes,
+}
+
+/// lint returns the style issues in a document, sorted by position. Unlike a [crate::SyntaxError]
+/// these do not prevent the document from being parsed (and syntax errors are not reported,
+/// as the document is read with [tokenize]). Columns are in bytes.
+pub fn lint(input: &[u8]) -> Vec<Lint> {
+    let mut lints = Vec::new();
+    let mut push = |lno: usize, col: usize, kind: LintKind, message: String| {
+        lints.push(Lint {
+            lno,
+            col,
+            kind,
+            message,
+        })
+    };
+
+    let mut lno = 1;
+    let mut lines = input
+        .split_inclusive(|&c| c == b'\n' || c == b'\r')
+        .peekable();
+    while let Some(line) = lines.next() {
+        let content = line.strip_suffix(b"\n").unwrap_or(line);
+        let content = content.strip_suffix(b"\r").unwrap_or(content);
+        let len = content.iter().rposition(|&c| c != b' ' && c != b'\t');
+        let len = len.map_or(0, |i| i + 1);
+        if len < content.len() {
+            push(
+                lno,
+                len + 1,
+                LintKind::TrailingWhitespace,
+                "trailing whitespace".into(),
+            );
+        }
+        // a \r\n is split into two lines, but is only one line ending
+        if !(line.ends_with(b"\r") && lines.peek() == Some(&&b"\n"[..])) {
+            lno += 1;
+        }
+    }
+
+    // the indentation of each open section, and of the first indented section
+    let mut indents: Vec<&str> = vec![""];
+    let mut first: Option<&str> = None;
+    for token in tokenize(input).filter_map(Result::ok) {
+        let loc = token.location();
+        match token {
+            Token::Indent(_, indent) => {
+                let parent = indents.last().copied().unwrap_or_default();
+                let extra = indent.get(parent.len()..).unwrap_or(indent);
+                indents.push(indent);
+                if indent.contains(' ') && indent.contains('\t') {
+                    push(
+                        loc.lno,
+                        1,
+                        LintKind::MixedIndentation,
+                        "indentation mixes tabs and spaces".into(),
+                    );
+                    continue;
+                }
+                let Some(first) = first else {
+                    first = Some(extra);
+                    continue;
+                };
+                if extra.starts_with('\t') != first.starts_with('\t') {
+                    push(
+                        loc.lno,
+                        1,
+                        LintKind::MixedIndentation,
+                        "indentation uses tabs and spaces in different sections".into(),
+                    );
+                } else if extra.len() != first.len() {
+                    push(
+                        loc.lno,
+                        1,
+                        LintKind::InconsistentIndentWidth,
+                        format!(
+                            "section is indented by {}, but the first section was indented by {}",
+                            extra.len(),
+                            first.len()
+                        ),
+                    );
+                }
+            }
+            Token::Outdent(..) => {
+                indents.pop();
+            }
+            Token::MapKey(..) | Token::Value(..) if token.is_quoted() => {
+                let Ok(unescaped) = token.unescape() else {
+                    continue;
+                };
+                let is_key = matches!(token, Token::MapKey(..));
+                if !escape_scalar(&unescaped, is_key).starts_with('"') {
+                    push(
+                        loc.lno,
+                        loc.col,
+                        LintKind::RedundantQuotes,
+                        format!("unnecessary quotes around {}", token.name()),
+                    );
+                }
+            }
+            _ => {}
+        }
+    }
+    lints.sort_by_key(|lint| (lint.lno, lint.col));
+    lints
+}
@@ -0,0 +1,14 @@
+/// conl! parses a CONL document in a string literal into a [Value](crate::Value).
+///
+/// This is convenient for tests and defaults. The document is parsed each time the
+/// macro is evaluated, and invalid CONL panics with the line number and message of the
+/// [SyntaxError](crate::SyntaxError) (the panic location is the call to `conl!`).
+#[macro_export]
+macro_rules! conl {
+    ($input:literal) => {
+        match $crate::parse_value(::core::primitive::str::as_bytes($input)) {
+            ::core::result::Result::Ok(value) => value,
+            ::core::result::Result::Err(err) => ::core::panic!("invalid CONL in conl!: {}", err),
+        }
+    };
+}
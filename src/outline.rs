@@ -0,0 +1,51 @@
+use alloc::string::String;
+
+use crate::{parse, SyntaxError, Token};
+
+/// outline returns the document as a tree with one `-` bullet per key or list item, and
+/// nested sections indented by two spaces, for debugging. Keys are followed by ` = value`,
+/// and list items by their value. Values are unescaped, and only the first line of a
+/// multiline value is shown (followed by `…`).
+pub fn outline(input: &[u8]) -> Result<String, SyntaxError> {
+    let mut output = String::new();
+    let mut depth = 0;
+    let mut separator = "";
+    for token in parse(input) {
+        let token = token?;
+        match token {
+            Token::MapKey(..) | Token::ListItem(..) => {
+                for _ in 0..depth {
+                    output.push_str("  ");
+                }
+                output.push('-');
+                if let Token::MapKey(..) = token {
+                    output.push(' ');
+                    output.push_str(&token.unescape()?);
+                    separator = " = ";
+                } else {
+                    separator = " ";
+                }
+            }
+            Token::Value(..) | Token::MultilineValue(..) => {
+                let value = token.unescape()?;
+                output.push_str(separator);
+                match value.split_once('\n') {
+                    Some((first, _)) => {
+                        output.push_str(first);
+                        output.push('…');
+                    }
+                    None => output.push_str(&value),
+                }
+                output.push('\n');
+            }
+            Token::NoValue(..) => output.push('\n'),
+            Token::Indent(..) => {
+                output.push('\n');
+                depth += 1;
+            }
+            Token::Outdent(..) => depth -= 1,
+            _ => {}
+        }
+    }
+    Ok(output)
+}
@@ -0,0 +1,78 @@
+use alloc::borrow::Cow;
+use alloc::format;
+
+use crate::{parse, Parser, SyntaxError, Token};
+
+/// pairs iterates over the keys and values of a document that is a single map of
+/// scalar values (like a .env file). Keys and values are unescaped, and keys with
+/// no value have an empty value.
+/// If the document contains a list or a nested section a [SyntaxError] is returned.
+pub fn pairs(input: &[u8]) -> Pairs<'_> {
+    Pairs {
+        parser: parse(input),
+        errored: false,
+    }
+}
+
+type Pair<'tok> = (Cow<'tok, str>, Cow<'tok, str>);
+
+/// See [pairs]
+pub struct Pairs<'tok> {
+    parser: Parser<'tok>,
+    errored: bool,
+}
+
+impl<'tok> Pairs<'tok> {
+    // returns the next token that is not a newline, comment or multiline hint.
+    fn next_token(&mut self) -> Result<Option<Token<'tok>>, SyntaxError> {
+        for token in self.parser.by_ref() {
+            match token? {
+                Token::Newline(..) | Token::Comment(..) | Token::MultilineHint(..) => {}
+                token => return Ok(Some(token)),
+            }
+        }
+        Ok(None)
+    }
+
+    fn next_pair(&mut self) -> Result<Option<Pair<'tok>>, SyntaxError> {
+        let key = match self.next_token()? {
+            None => return Ok(None),
+            Some(key @ Token::MapKey(..)) => key.unescape()?,
+            Some(token) => {
+                let loc = token.location();
+                return Err(SyntaxError::new(
+                    loc.lno,
+                    loc.col,
+                    format!("expected map key, got {}", token.name()),
+                ));
+            }
+        };
+        let value = match self.next_token()? {
+            Some(value @ (Token::Value(..) | Token::MultilineValue(..))) => value.unescape()?,
+            Some(Token::NoValue(..)) => Cow::Borrowed(""),
+            Some(token) => {
+                let loc = token.location();
+                return Err(SyntaxError::new(
+                    loc.lno,
+                    loc.col,
+                    format!("expected value, got {}", token.name()),
+                ));
+            }
+            None => unreachable!(),
+        };
+        Ok(Some((key, value)))
+    }
+}
+
+impl<'tok> Iterator for Pairs<'tok> {
+    type Item = Result<Pair<'tok>, SyntaxError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.errored {
+            return None;
+        }
+        let result = self.next_pair().transpose();
+        self.errored = matches!(result, Some(Err(_)));
+        result
+    }
+}
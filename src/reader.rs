@@ -0,0 +1,381 @@
+use std::collections::VecDeque;
+use std::io::{self, BufRead};
+
+#[cfg(feature = "futures")]
+use futures_util::{
+    io::{AsyncBufRead, AsyncBufReadExt},
+    stream::{self, Stream},
+};
+
+use crate::{
+    is_newline, is_whitespace, tokenize, Location, OwnedToken, Parser, ParserLimits, Section,
+    SyntaxError, Token, Tokenizer,
+};
+
+/// parse_reader is like [crate::parse], but reads its input incrementally from a [BufRead]
+/// and yields [OwnedToken]s.
+///
+/// Input is read one line at a time, except that multiline values are read in
+/// their entirety, so memory use is bounded by the longest line (or multiline value)
+/// plus the indentation of each enclosing section. Lines are split on `\n`, so
+/// a file that only uses `\r` as a line separator will be read all at once.
+pub fn parse_reader<R: BufRead>(reader: R) -> ReaderParser<R> {
+    ReaderParser::new(reader)
+}
+
+/// ReadError is returned by [ReaderParser] and [crate::write_json] if either I/O or parsing fails.
+#[derive(Debug)]
+pub enum ReadError {
+    Io(io::Error),
+    Syntax(SyntaxError),
+}
+
+impl std::fmt::Display for ReadError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ReadError::Io(e) => e.fmt(f),
+            ReadError::Syntax(e) => e.fmt(f),
+        }
+    }
+}
+
+impl std::error::Error for ReadError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            ReadError::Io(e) => Some(e),
+            ReadError::Syntax(e) => Some(e),
+        }
+    }
+}
+
+impl From<io::Error> for ReadError {
+    fn from(e: io::Error) -> Self {
+        ReadError::Io(e)
+    }
+}
+
+impl From<SyntaxError> for ReadError {
+    fn from(e: SyntaxError) -> Self {
+        ReadError::Syntax(e)
+    }
+}
+
+/// See [parse_reader]
+pub struct ReaderParser<R> {
+    reader: R,
+    state: State,
+    tokens: VecDeque<Result<OwnedToken, SyntaxError>>,
+    // a line that has been read, but not yet parsed
+    pending: Option<Vec<u8>>,
+    // the offset of the next chunk in the input
+    offset: usize,
+    finished: bool,
+}
+
+// State is the state of the Parser (and its Tokenizer) at the start of a line,
+// which is carried from one chunk of input to the next.
+struct State {
+    indent_stack: Vec<Vec<u8>>,
+    indent_lines: Vec<usize>,
+    expect_indent: bool,
+    expect_value: bool,
+    expect_multiline: bool,
+    lno: usize,
+    stack: Vec<Section>,
+    deny_duplicate_keys: bool,
+    max_depth: usize,
+    limits: ParserLimits,
+    multiline_hint: Option<Location>,
+    needs_value: Option<Location>,
+    has_value: Option<Location>,
+    peek: Option<Option<OwnedToken>>,
+    errored: bool,
+}
+
+impl<R> ReaderParser<R> {
+    fn new(reader: R) -> Self {
+        ReaderParser {
+            reader,
+            state: State {
+                indent_stack: vec![Vec::new()],
+                indent_lines: vec![0],
+                expect_indent: true,
+                expect_value: false,
+                expect_multiline: false,
+                lno: 1,
+                stack: vec![Section::default()],
+                deny_duplicate_keys: false,
+                max_depth: usize::MAX,
+                limits: ParserLimits::default(),
+                multiline_hint: None,
+                needs_value: None,
+                has_value: None,
+                peek: None,
+                errored: false,
+            },
+            tokens: VecDeque::new(),
+            pending: None,
+            offset: 0,
+            finished: false,
+        }
+    }
+
+    /// See [Parser::deny_duplicate_keys]
+    pub fn deny_duplicate_keys(mut self) -> Self {
+        self.state.deny_duplicate_keys = true;
+        self
+    }
+
+    /// See [Parser::max_depth]
+    pub fn max_depth(mut self, n: usize) -> Self {
+        self.state.max_depth = n;
+        self
+    }
+
+    /// See [Parser::limits]
+    pub fn limits(mut self, limits: ParserLimits) -> Self {
+        self.state.limits = limits;
+        self.state.max_depth = limits.max_depth;
+        self
+    }
+
+    // if the line starts a multiline value, returns its indentation: the chunk
+    // is then extended with each following line that is indented by at least that much.
+    fn multiline_indent(&self, line: &[u8]) -> Option<Vec<u8>> {
+        if !self.state.expect_multiline {
+            return None;
+        }
+        let indent_len = line
+            .iter()
+            .position(|c| !is_whitespace(c))
+            .unwrap_or(line.len());
+        let (indent, rest) = line.split_at(indent_len);
+        let current = self.state.indent_stack.last()?;
+        if rest.first().is_none_or(is_newline)
+            || !(indent.len() > current.len() && indent.starts_with(current))
+        {
+            return None;
+        }
+        Some(indent.to_vec())
+    }
+
+    fn extends_multiline(indent: &[u8], line: &[u8]) -> bool {
+        line.starts_with(indent) || line.iter().all(|c| is_whitespace(c) || is_newline(c))
+    }
+
+    // returns the next token that has been parsed, or whether to stop.
+    fn pop_token(&mut self) -> Option<Option<Result<OwnedToken, ReadError>>> {
+        if let Some(token) = self.tokens.pop_front() {
+            return Some(Some(token.map_err(ReadError::Syntax)));
+        }
+        self.finished.then_some(None)
+    }
+
+    // parses the chunk read by read_chunk (or its async equivalent).
+    fn parse_read(&mut self, result: io::Result<(Option<Vec<u8>>, bool)>) -> Option<ReadError> {
+        match result {
+            Err(e) => {
+                self.finished = true;
+                Some(ReadError::Io(e))
+            }
+            Ok((chunk, at_end)) => {
+                self.parse_chunk(chunk.as_deref().unwrap_or_default(), at_end);
+                self.finished = at_end || self.state.errored;
+                None
+            }
+        }
+    }
+
+    // runs the parser over the chunk, resuming from (and then updating) the saved state.
+    fn parse_chunk(&mut self, chunk: &[u8], at_end: bool) {
+        let indent_stack = std::mem::take(&mut self.state.indent_stack);
+        let peek = self.state.peek.take();
+        let mut parser = Parser::new(Tokenizer {
+            indent_stack: indent_stack.iter().map(|indent| &indent[..]).collect(),
+            indent_lines: std::mem::take(&mut self.state.indent_lines),
+            expect_indent: self.state.expect_indent,
+            expect_value: self.state.expect_value,
+            expect_multiline: self.state.expect_multiline,
+            lno: self.state.lno,
+            source_offset: self.offset,
+            limits: self.state.limits,
+            ..tokenize(chunk)
+        });
+        if self.offset > 0 {
+            // only a byte order mark at the start of the input is skipped
+            parser.tokenizer.input = chunk;
+            parser.tokenizer.line_start = 0;
+        }
+        parser.peek = peek
+            .as_ref()
+            .map(|peek| peek.as_ref().map(OwnedToken::as_token));
+        parser.multiline_hint = self.state.multiline_hint;
+        parser.needs_value = self.state.needs_value;
+        parser.has_value = self.state.has_value;
+        parser.errored = self.state.errored;
+        parser.stack = std::mem::take(&mut self.state.stack);
+        parser.deny_duplicate_keys = self.state.deny_duplicate_keys;
+        parser.max_depth = self.state.max_depth;
+
+        while at_end || parser.peek.is_some() || !parser.tokenizer.input.is_empty() {
+            let Some(token) = parser.next() else {
+                break;
+            };
+            self.tokens.push_back(token.map(Token::into_owned));
+        }
+
+        self.state = State {
+            indent_stack: parser
+                .tokenizer
+                .indent_stack
+                .iter()
+                .map(|indent| indent.to_vec())
+                .collect(),
+            indent_lines: parser.tokenizer.indent_lines,
+            expect_indent: parser.tokenizer.expect_indent,
+            expect_value: parser.tokenizer.expect_value,
+            expect_multiline: parser.tokenizer.expect_multiline,
+            lno: parser.tokenizer.lno,
+            stack: parser.stack,
+            deny_duplicate_keys: parser.deny_duplicate_keys,
+            max_depth: parser.max_depth,
+            limits: parser.tokenizer.limits,
+            multiline_hint: parser.multiline_hint,
+            needs_value: parser.needs_value,
+            has_value: parser.has_value,
+            peek: parser.peek.map(|peek| peek.map(|token| token.into_owned())),
+            errored: parser.errored,
+        };
+        self.offset += chunk.len();
+    }
+}
+
+impl<R: BufRead> ReaderParser<R> {
+    fn read_line(&mut self) -> io::Result<Option<Vec<u8>>> {
+        if let Some(line) = self.pending.take() {
+            return Ok(Some(line));
+        }
+        let mut line = Vec::new();
+        if self.reader.read_until(b'\n', &mut line)? == 0 {
+            return Ok(None);
+        }
+        Ok(Some(line))
+    }
+
+    // reads the next line, or if a multiline value is starting, all of its lines.
+    fn read_chunk(&mut self) -> io::Result<Option<Vec<u8>>> {
+        let Some(mut chunk) = self.read_line()? else {
+            return Ok(None);
+        };
+        let Some(indent) = self.multiline_indent(&chunk) else {
+            return Ok(Some(chunk));
+        };
+        while let Some(line) = self.read_line()? {
+            if Self::extends_multiline(&indent, &line) {
+                chunk.extend(line);
+            } else {
+                self.pending = Some(line);
+                break;
+            }
+        }
+        Ok(Some(chunk))
+    }
+
+    fn at_end(&mut self) -> io::Result<bool> {
+        Ok(self.pending.is_none() && self.reader.fill_buf()?.is_empty())
+    }
+}
+
+impl<R: BufRead> Iterator for ReaderParser<R> {
+    type Item = Result<OwnedToken, ReadError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some(token) = self.pop_token() {
+                return token;
+            }
+            let result = self
+                .read_chunk()
+                .and_then(|chunk| Ok((chunk, self.at_end()?)));
+            if let Some(e) = self.parse_read(result) {
+                return Some(Err(e));
+            }
+        }
+    }
+}
+
+/// parse_async is like [parse_reader], but reads its input from a
+/// [futures_util::io::AsyncBufRead] (requires the `futures` feature).
+///
+/// Tokens are returned by [ReaderParser::next_async], or as a
+/// [futures_util::Stream] by [ReaderParser::into_stream]. Input is buffered in the
+/// same way as [parse_reader]: one line at a time, except that a multiline value is
+/// read in its entirety before any of its tokens are yielded.
+#[cfg(feature = "futures")]
+pub fn parse_async<R: AsyncBufRead + Unpin>(reader: R) -> ReaderParser<R> {
+    ReaderParser::new(reader)
+}
+
+#[cfg(feature = "futures")]
+impl<R: AsyncBufRead + Unpin> ReaderParser<R> {
+    async fn read_line_async(&mut self) -> io::Result<Option<Vec<u8>>> {
+        if let Some(line) = self.pending.take() {
+            return Ok(Some(line));
+        }
+        let mut line = Vec::new();
+        if self.reader.read_until(b'\n', &mut line).await? == 0 {
+            return Ok(None);
+        }
+        Ok(Some(line))
+    }
+
+    // see read_chunk
+    async fn read_chunk_async(&mut self) -> io::Result<Option<Vec<u8>>> {
+        let Some(mut chunk) = self.read_line_async().await? else {
+            return Ok(None);
+        };
+        let Some(indent) = self.multiline_indent(&chunk) else {
+            return Ok(Some(chunk));
+        };
+        while let Some(line) = self.read_line_async().await? {
+            if Self::extends_multiline(&indent, &line) {
+                chunk.extend(line);
+            } else {
+                self.pending = Some(line);
+                break;
+            }
+        }
+        Ok(Some(chunk))
+    }
+
+    async fn at_end_async(&mut self) -> io::Result<bool> {
+        if self.pending.is_some() {
+            return Ok(false);
+        }
+        Ok(self.reader.fill_buf().await?.is_empty())
+    }
+
+    /// next_async returns the next token, or None at the end of the input.
+    pub async fn next_async(&mut self) -> Option<Result<OwnedToken, ReadError>> {
+        loop {
+            if let Some(token) = self.pop_token() {
+                return token;
+            }
+            let result = match self.read_chunk_async().await {
+                Ok(chunk) => self.at_end_async().await.map(|at_end| (chunk, at_end)),
+                Err(e) => Err(e),
+            };
+            if let Some(e) = self.parse_read(result) {
+                return Some(Err(e));
+            }
+        }
+    }
+
+    /// into_stream returns a [futures_util::Stream] of the remaining tokens.
+    pub fn into_stream(self) -> impl Stream<Item = Result<OwnedToken, ReadError>> {
+        stream::unfold(self, |mut parser| async move {
+            let token = parser.next_async().await?;
+            Some((token, parser))
+        })
+    }
+}
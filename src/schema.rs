@@ -0,0 +1,222 @@
+use alloc::boxed::Box;
+use alloc::format;
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+use core::fmt;
+
+use crate::value::join_path as join;
+use crate::{infer_scalar, Location, Scalar, Spanned, SpannedValue, Value};
+
+/// Schema describes the expected shape of a [Value]. See [validate].
+///
+/// Types of values are inferred with [infer_scalar]. A key or list item with no value is
+/// an empty map or list, so it matches [Schema::Map] and [Schema::List] but not a scalar type.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Schema {
+    /// any value, section, or no value
+    Any,
+    /// any single-line or multiline value
+    String,
+    /// an integer
+    Int,
+    /// an integer or other number
+    Number,
+    /// `true` or `false`
+    Bool,
+    /// a list whose items all match the schema
+    List(Box<Schema>),
+    /// a map with the given keys
+    Map {
+        fields: Vec<Field>,
+        /// whether keys that are not in fields are allowed
+        allow_extra: bool,
+    },
+}
+
+/// Field is a key in a [Schema::Map].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Field {
+    pub key: String,
+    pub schema: Schema,
+    pub required: bool,
+}
+
+impl Field {
+    /// returns a field that must be present.
+    pub fn required(key: impl Into<String>, schema: Schema) -> Self {
+        Field {
+            key: key.into(),
+            schema,
+            required: true,
+        }
+    }
+
+    /// returns a field that may be omitted.
+    pub fn optional(key: impl Into<String>, schema: Schema) -> Self {
+        Field {
+            key: key.into(),
+            schema,
+            required: false,
+        }
+    }
+}
+
+/// SchemaError is a part of a document that does not match its [Schema].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SchemaError {
+    /// the dotted path to the value (as accepted by [Value::pointer])
+    pub path: String,
+    /// the line of the value, or of the map that is missing a key
+    /// (None if the value was not parsed with [crate::parse_value_spanned])
+    pub lno: Option<usize>,
+    pub msg: String,
+}
+
+impl fmt::Display for SchemaError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some(lno) = self.lno {
+            write!(f, "{}: ", lno)?;
+        }
+        if !self.path.is_empty() {
+            write!(f, "{}: ", self.path)?;
+        }
+        f.write_str(&self.msg)
+    }
+}
+
+/// validate checks a value against a schema, returning every mismatch in document order
+/// (a missing key is reported after the other errors in its map).
+/// Use [validate_spanned] to include line numbers in the errors.
+pub fn validate(value: &Value, schema: &Schema) -> Result<(), Vec<SchemaError>> {
+    validate_spanned(&spanned(value), schema)
+}
+
+/// validate_spanned is like [validate], for a value from [crate::parse_value_spanned].
+pub fn validate_spanned(
+    value: &Spanned<SpannedValue>,
+    schema: &Schema,
+) -> Result<(), Vec<SchemaError>> {
+    let mut errors = Vec::new();
+    check(value, schema, String::new(), &mut errors);
+    if errors.is_empty() {
+        Ok(())
+    } else {
+        Err(errors)
+    }
+}
+
+// converts a value to a SpannedValue with no locations (line 0).
+fn spanned(value: &Value) -> Spanned<SpannedValue> {
+    let value = match value {
+        Value::Scalar(value) => SpannedValue::Scalar(value.clone()),
+        Value::Multiline { value, hint } => SpannedValue::Multiline {
+            value: value.clone(),
+            hint: hint.clone(),
+        },
+        Value::Map(entries) => SpannedValue::Map(
+            entries
+                .iter()
+                .map(|(key, value)| {
+                    let key = Spanned {
+                        value: key.clone(),
+                        location: Location::default(),
+                    };
+                    (key, spanned(value))
+                })
+                .collect(),
+        ),
+        Value::List(items) => SpannedValue::List(items.iter().map(spanned).collect()),
+        Value::Null => SpannedValue::Null,
+    };
+    Spanned {
+        value,
+        location: Location::default(),
+    }
+}
+
+fn check(
+    value: &Spanned<SpannedValue>,
+    schema: &Schema,
+    path: String,
+    errors: &mut Vec<SchemaError>,
+) {
+    let lno = Some(value.location.lno).filter(|&lno| lno > 0);
+    let expected = match (schema, &value.value) {
+        (Schema::Any, _) => return,
+        (Schema::List(_), SpannedValue::Null) | (Schema::Map { .. }, SpannedValue::Null) => return,
+        (Schema::List(schema), SpannedValue::List(items)) => {
+            for (i, item) in items.iter().enumerate() {
+                check(item, schema, join(&path, &i.to_string()), errors);
+            }
+            return;
+        }
+        (
+            Schema::Map {
+                fields,
+                allow_extra,
+            },
+            SpannedValue::Map(entries),
+        ) => {
+            for (key, value) in entries {
+                let path = join(&path, &key.value);
+                match fields.iter().find(|field| field.key == key.value) {
+                    Some(field) => check(value, &field.schema, path, errors),
+                    None if !allow_extra => errors.push(SchemaError {
+                        path,
+                        lno: Some(key.location.lno).filter(|&lno| lno > 0),
+                        msg: "unexpected key".into(),
+                    }),
+                    None => {}
+                }
+            }
+            for field in fields {
+                if field.required && !entries.iter().any(|(key, _)| key.value == field.key) {
+                    errors.push(SchemaError {
+                        path: join(&path, &field.key),
+                        lno,
+                        msg: "missing required key".into(),
+                    });
+                }
+            }
+            return;
+        }
+        (Schema::String, SpannedValue::Scalar(_) | SpannedValue::Multiline { .. }) => return,
+        (Schema::Int, SpannedValue::Scalar(value) | SpannedValue::Multiline { value, .. }) => {
+            match infer_scalar(value) {
+                Scalar::Int(_) => return,
+                _ => "an integer",
+            }
+        }
+        (Schema::Number, SpannedValue::Scalar(value) | SpannedValue::Multiline { value, .. }) => {
+            match infer_scalar(value) {
+                Scalar::Int(_) | Scalar::Float(_) => return,
+                _ => "a number",
+            }
+        }
+        (Schema::Bool, SpannedValue::Scalar(value) | SpannedValue::Multiline { value, .. }) => {
+            match infer_scalar(value) {
+                Scalar::Bool(_) => return,
+                _ => "true or false",
+            }
+        }
+        (Schema::String, _) => "a value",
+        (Schema::Int, _) => "an integer",
+        (Schema::Number, _) => "a number",
+        (Schema::Bool, _) => "true or false",
+        (Schema::List(_), _) => "a list",
+        (Schema::Map { .. }, _) => "a map",
+    };
+    let found = match &value.value {
+        SpannedValue::Scalar(value) | SpannedValue::Multiline { value, .. } => {
+            format!("{:?}", value)
+        }
+        SpannedValue::Map(_) => "a map".into(),
+        SpannedValue::List(_) => "a list".into(),
+        SpannedValue::Null => "no value".into(),
+    };
+    errors.push(SchemaError {
+        path,
+        lno,
+        msg: format!("expected {}, found {}", expected, found),
+    });
+}
@@ -0,0 +1,577 @@
+use alloc::string::{String, ToString};
+
+use serde::ser::{self, Serialize};
+
+/// SerializeError is returned when a value cannot be represented in CONL.
+#[derive(Debug)]
+pub struct SerializeError {
+    pub msg: String,
+}
+
+impl ser::Error for SerializeError {
+    fn custom<T: core::fmt::Display>(msg: T) -> Self {
+        SerializeError {
+            msg: msg.to_string(),
+        }
+    }
+}
+
+impl core::fmt::Display for SerializeError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.write_str(&self.msg)
+    }
+}
+
+impl core::error::Error for SerializeError {}
+
+/// Serialize a value as CONL (with two-space indentation).
+///
+/// Structs and maps become `key = value` pairs, sequences become `= value`
+/// list items, and `None` or `()` become keys or items with no value.
+/// The value must be a map or a list (or unit, for an empty document).
+pub fn to_string<T: Serialize + ?Sized>(value: &T) -> Result<String, SerializeError> {
+    let mut serializer = Serializer::default();
+    value.serialize(&mut serializer)?;
+    Ok(serializer.into_inner())
+}
+
+/// A serde Serializer that writes CONL. See [to_string].
+#[derive(Default)]
+pub struct Serializer {
+    output: String,
+    depth: usize,
+    nested: bool,
+    // what to write between the key or list item and a value on the same line
+    separator: &'static str,
+}
+
+impl Serializer {
+    /// into_inner returns the CONL written by the serializer.
+    pub fn into_inner(self) -> String {
+        self.output
+    }
+
+    fn scalar(&mut self, value: &str) -> Result<(), SerializeError> {
+        if !self.nested {
+            return Err(ser::Error::custom(
+                "can only serialize a map or a list at the top level",
+            ));
+        }
+        self.output.push_str(self.separator);
+        self.output.push_str(&crate::escape_scalar(value, false));
+        self.output.push('\n');
+        Ok(())
+    }
+
+    fn no_value(&mut self) -> Result<(), SerializeError> {
+        if self.nested {
+            self.output.push('\n');
+        }
+        Ok(())
+    }
+
+    // returns whether the section is nested inside another
+    fn start_section(&mut self) -> bool {
+        let nested = self.nested;
+        if nested {
+            self.output.push('\n');
+            self.depth += 1;
+        }
+        self.nested = true;
+        nested
+    }
+
+    fn start_variant(&mut self, variant: &str) -> bool {
+        let nested = self.start_section();
+        self.write_key(variant);
+        nested
+    }
+
+    fn write_indent(&mut self) {
+        for _ in 0..self.depth {
+            self.output.push_str("  ");
+        }
+    }
+
+    fn write_key(&mut self, key: &str) {
+        self.write_indent();
+        self.output.push_str(&crate::escape_scalar(key, true));
+        self.separator = " = ";
+    }
+
+    fn write_list_item(&mut self) {
+        self.write_indent();
+        self.output.push('=');
+        self.separator = " ";
+    }
+}
+
+impl<'a> ser::Serializer for &'a mut Serializer {
+    type Ok = ();
+    type Error = SerializeError;
+    type SerializeSeq = Compound<'a>;
+    type SerializeTuple = Compound<'a>;
+    type SerializeTupleStruct = Compound<'a>;
+    type SerializeTupleVariant = Compound<'a>;
+    type SerializeMap = Compound<'a>;
+    type SerializeStruct = Compound<'a>;
+    type SerializeStructVariant = Compound<'a>;
+
+    fn serialize_bool(self, v: bool) -> Result<(), SerializeError> {
+        self.scalar(if v { "true" } else { "false" })
+    }
+
+    fn serialize_i8(self, v: i8) -> Result<(), SerializeError> {
+        self.scalar(&v.to_string())
+    }
+
+    fn serialize_i16(self, v: i16) -> Result<(), SerializeError> {
+        self.scalar(&v.to_string())
+    }
+
+    fn serialize_i32(self, v: i32) -> Result<(), SerializeError> {
+        self.scalar(&v.to_string())
+    }
+
+    fn serialize_i64(self, v: i64) -> Result<(), SerializeError> {
+        self.scalar(&v.to_string())
+    }
+
+    fn serialize_i128(self, v: i128) -> Result<(), SerializeError> {
+        self.scalar(&v.to_string())
+    }
+
+    fn serialize_u8(self, v: u8) -> Result<(), SerializeError> {
+        self.scalar(&v.to_string())
+    }
+
+    fn serialize_u16(self, v: u16) -> Result<(), SerializeError> {
+        self.scalar(&v.to_string())
+    }
+
+    fn serialize_u32(self, v: u32) -> Result<(), SerializeError> {
+        self.scalar(&v.to_string())
+    }
+
+    fn serialize_u64(self, v: u64) -> Result<(), SerializeError> {
+        self.scalar(&v.to_string())
+    }
+
+    fn serialize_u128(self, v: u128) -> Result<(), SerializeError> {
+        self.scalar(&v.to_string())
+    }
+
+    fn serialize_f32(self, v: f32) -> Result<(), SerializeError> {
+        self.scalar(&v.to_string())
+    }
+
+    fn serialize_f64(self, v: f64) -> Result<(), SerializeError> {
+        self.scalar(&v.to_string())
+    }
+
+    fn serialize_char(self, v: char) -> Result<(), SerializeError> {
+        self.scalar(v.encode_utf8(&mut [0; 4]))
+    }
+
+    fn serialize_str(self, v: &str) -> Result<(), SerializeError> {
+        self.scalar(v)
+    }
+
+    fn serialize_bytes(self, v: &[u8]) -> Result<(), SerializeError> {
+        let v = core::str::from_utf8(v)
+            .map_err(|_| ser::Error::custom("can only serialize bytes that are valid UTF-8"))?;
+        self.scalar(v)
+    }
+
+    fn serialize_none(self) -> Result<(), SerializeError> {
+        self.no_value()
+    }
+
+    fn serialize_some<T: Serialize + ?Sized>(self, value: &T) -> Result<(), SerializeError> {
+        value.serialize(self)
+    }
+
+    fn serialize_unit(self) -> Result<(), SerializeError> {
+        self.no_value()
+    }
+
+    fn serialize_unit_struct(self, _name: &'static str) -> Result<(), SerializeError> {
+        self.no_value()
+    }
+
+    fn serialize_unit_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+    ) -> Result<(), SerializeError> {
+        self.scalar(variant)
+    }
+
+    fn serialize_newtype_struct<T: Serialize + ?Sized>(
+        self,
+        _name: &'static str,
+        value: &T,
+    ) -> Result<(), SerializeError> {
+        value.serialize(self)
+    }
+
+    fn serialize_newtype_variant<T: Serialize + ?Sized>(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+        value: &T,
+    ) -> Result<(), SerializeError> {
+        let nested = self.start_variant(variant);
+        value.serialize(&mut *self)?;
+        Compound { ser: self, nested }.end_section();
+        Ok(())
+    }
+
+    fn serialize_seq(self, _len: Option<usize>) -> Result<Compound<'a>, SerializeError> {
+        Ok(Compound {
+            nested: self.start_section(),
+            ser: self,
+        })
+    }
+
+    fn serialize_tuple(self, _len: usize) -> Result<Compound<'a>, SerializeError> {
+        Ok(Compound {
+            nested: self.start_section(),
+            ser: self,
+        })
+    }
+
+    fn serialize_tuple_struct(
+        self,
+        _name: &'static str,
+        _len: usize,
+    ) -> Result<Compound<'a>, SerializeError> {
+        Ok(Compound {
+            nested: self.start_section(),
+            ser: self,
+        })
+    }
+
+    fn serialize_tuple_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+        _len: usize,
+    ) -> Result<Compound<'a>, SerializeError> {
+        let nested = self.start_variant(variant);
+        self.start_section();
+        Ok(Compound { ser: self, nested })
+    }
+
+    fn serialize_map(self, _len: Option<usize>) -> Result<Compound<'a>, SerializeError> {
+        Ok(Compound {
+            nested: self.start_section(),
+            ser: self,
+        })
+    }
+
+    fn serialize_struct(
+        self,
+        _name: &'static str,
+        _len: usize,
+    ) -> Result<Compound<'a>, SerializeError> {
+        Ok(Compound {
+            nested: self.start_section(),
+            ser: self,
+        })
+    }
+
+    fn serialize_struct_variant(
+        self,
+        name: &'static str,
+        variant_index: u32,
+        variant: &'static str,
+        len: usize,
+    ) -> Result<Compound<'a>, SerializeError> {
+        self.serialize_tuple_variant(name, variant_index, variant, len)
+    }
+}
+
+/// Compound serializes the entries of a map or a list. See [Serializer].
+pub struct Compound<'a> {
+    ser: &'a mut Serializer,
+    nested: bool,
+}
+
+impl Compound<'_> {
+    fn end_section(self) {
+        if self.nested {
+            self.ser.depth -= 1;
+        }
+    }
+
+    fn end_variant(self) {
+        // variants are a list or map nested inside a map with one key.
+        self.ser.depth -= 1;
+        if self.nested {
+            self.ser.depth -= 1;
+        }
+    }
+}
+
+impl ser::SerializeSeq for Compound<'_> {
+    type Ok = ();
+    type Error = SerializeError;
+
+    fn serialize_element<T: Serialize + ?Sized>(
+        &mut self,
+        value: &T,
+    ) -> Result<(), SerializeError> {
+        self.ser.write_list_item();
+        value.serialize(&mut *self.ser)
+    }
+
+    fn end(self) -> Result<(), SerializeError> {
+        self.end_section();
+        Ok(())
+    }
+}
+
+impl ser::SerializeTuple for Compound<'_> {
+    type Ok = ();
+    type Error = SerializeError;
+
+    fn serialize_element<T: Serialize + ?Sized>(
+        &mut self,
+        value: &T,
+    ) -> Result<(), SerializeError> {
+        ser::SerializeSeq::serialize_element(self, value)
+    }
+
+    fn end(self) -> Result<(), SerializeError> {
+        ser::SerializeSeq::end(self)
+    }
+}
+
+impl ser::SerializeTupleStruct for Compound<'_> {
+    type Ok = ();
+    type Error = SerializeError;
+
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), SerializeError> {
+        ser::SerializeSeq::serialize_element(self, value)
+    }
+
+    fn end(self) -> Result<(), SerializeError> {
+        ser::SerializeSeq::end(self)
+    }
+}
+
+impl ser::SerializeTupleVariant for Compound<'_> {
+    type Ok = ();
+    type Error = SerializeError;
+
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), SerializeError> {
+        ser::SerializeSeq::serialize_element(self, value)
+    }
+
+    fn end(self) -> Result<(), SerializeError> {
+        self.end_variant();
+        Ok(())
+    }
+}
+
+impl ser::SerializeMap for Compound<'_> {
+    type Ok = ();
+    type Error = SerializeError;
+
+    fn serialize_key<T: Serialize + ?Sized>(&mut self, key: &T) -> Result<(), SerializeError> {
+        let key = key.serialize(KeySerializer)?;
+        self.ser.write_key(&key);
+        Ok(())
+    }
+
+    fn serialize_value<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), SerializeError> {
+        value.serialize(&mut *self.ser)
+    }
+
+    fn end(self) -> Result<(), SerializeError> {
+        self.end_section();
+        Ok(())
+    }
+}
+
+impl ser::SerializeStruct for Compound<'_> {
+    type Ok = ();
+    type Error = SerializeError;
+
+    fn serialize_field<T: Serialize + ?Sized>(
+        &mut self,
+        key: &'static str,
+        value: &T,
+    ) -> Result<(), SerializeError> {
+        ser::SerializeMap::serialize_entry(self, key, value)
+    }
+
+    fn end(self) -> Result<(), SerializeError> {
+        ser::SerializeMap::end(self)
+    }
+}
+
+impl ser::SerializeStructVariant for Compound<'_> {
+    type Ok = ();
+    type Error = SerializeError;
+
+    fn serialize_field<T: Serialize + ?Sized>(
+        &mut self,
+        key: &'static str,
+        value: &T,
+    ) -> Result<(), SerializeError> {
+        ser::SerializeMap::serialize_entry(self, key, value)
+    }
+
+    fn end(self) -> Result<(), SerializeError> {
+        self.end_variant();
+        Ok(())
+    }
+}
+
+// KeySerializer converts map keys to strings.
+struct KeySerializer;
+
+macro_rules! serialize_key_to_string {
+    ($($method:ident: $ty:ty,)*) => {
+        $(
+            fn $method(self, v: $ty) -> Result<String, SerializeError> {
+                Ok(v.to_string())
+            }
+        )*
+    };
+}
+
+impl ser::Serializer for KeySerializer {
+    type Ok = String;
+    type Error = SerializeError;
+    type SerializeSeq = ser::Impossible<String, SerializeError>;
+    type SerializeTuple = ser::Impossible<String, SerializeError>;
+    type SerializeTupleStruct = ser::Impossible<String, SerializeError>;
+    type SerializeTupleVariant = ser::Impossible<String, SerializeError>;
+    type SerializeMap = ser::Impossible<String, SerializeError>;
+    type SerializeStruct = ser::Impossible<String, SerializeError>;
+    type SerializeStructVariant = ser::Impossible<String, SerializeError>;
+
+    serialize_key_to_string! {
+        serialize_bool: bool,
+        serialize_i8: i8,
+        serialize_i16: i16,
+        serialize_i32: i32,
+        serialize_i64: i64,
+        serialize_i128: i128,
+        serialize_u8: u8,
+        serialize_u16: u16,
+        serialize_u32: u32,
+        serialize_u64: u64,
+        serialize_u128: u128,
+        serialize_f32: f32,
+        serialize_f64: f64,
+        serialize_char: char,
+        serialize_str: &str,
+    }
+
+    fn serialize_bytes(self, v: &[u8]) -> Result<String, SerializeError> {
+        String::from_utf8(v.to_vec())
+            .map_err(|_| ser::Error::custom("can only serialize bytes that are valid UTF-8"))
+    }
+
+    fn serialize_none(self) -> Result<String, SerializeError> {
+        Err(key_must_be_a_string())
+    }
+
+    fn serialize_some<T: Serialize + ?Sized>(self, value: &T) -> Result<String, SerializeError> {
+        value.serialize(self)
+    }
+
+    fn serialize_unit(self) -> Result<String, SerializeError> {
+        Err(key_must_be_a_string())
+    }
+
+    fn serialize_unit_struct(self, _name: &'static str) -> Result<String, SerializeError> {
+        Err(key_must_be_a_string())
+    }
+
+    fn serialize_unit_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+    ) -> Result<String, SerializeError> {
+        Ok(variant.to_string())
+    }
+
+    fn serialize_newtype_struct<T: Serialize + ?Sized>(
+        self,
+        _name: &'static str,
+        value: &T,
+    ) -> Result<String, SerializeError> {
+        value.serialize(self)
+    }
+
+    fn serialize_newtype_variant<T: Serialize + ?Sized>(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        _variant: &'static str,
+        _value: &T,
+    ) -> Result<String, SerializeError> {
+        Err(key_must_be_a_string())
+    }
+
+    fn serialize_seq(self, _len: Option<usize>) -> Result<Self::SerializeSeq, SerializeError> {
+        Err(key_must_be_a_string())
+    }
+
+    fn serialize_tuple(self, _len: usize) -> Result<Self::SerializeTuple, SerializeError> {
+        Err(key_must_be_a_string())
+    }
+
+    fn serialize_tuple_struct(
+        self,
+        _name: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeTupleStruct, SerializeError> {
+        Err(key_must_be_a_string())
+    }
+
+    fn serialize_tuple_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        _variant: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeTupleVariant, SerializeError> {
+        Err(key_must_be_a_string())
+    }
+
+    fn serialize_map(self, _len: Option<usize>) -> Result<Self::SerializeMap, SerializeError> {
+        Err(key_must_be_a_string())
+    }
+
+    fn serialize_struct(
+        self,
+        _name: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeStruct, SerializeError> {
+        Err(key_must_be_a_string())
+    }
+
+    fn serialize_struct_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        _variant: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeStructVariant, SerializeError> {
+        Err(key_must_be_a_string())
+    }
+}
+
+fn key_must_be_a_string() -> SerializeError {
+    ser::Error::custom("map keys must be strings, numbers or booleans")
+}
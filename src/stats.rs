@@ -0,0 +1,39 @@
+use crate::{tokenize, SyntaxError, Token};
+
+/// Stats are counts of the parts of a document. See [stats].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Stats {
+    /// the number of map keys
+    pub keys: usize,
+    /// the number of list items
+    pub list_items: usize,
+    /// the deepest level of nesting (0 if there are no nested sections)
+    pub max_depth: usize,
+    /// the number of multiline values
+    pub multiline_values: usize,
+    /// the number of comments
+    pub comments: usize,
+}
+
+/// stats counts the keys, list items, multiline values and comments in a document
+/// in a single pass over its tokens. As the document is read with [tokenize], its
+/// structure is not validated: the first error from the tokenizer is returned.
+pub fn stats(input: &[u8]) -> Result<Stats, SyntaxError> {
+    let mut stats = Stats::default();
+    let mut depth = 0;
+    for token in tokenize(input) {
+        match token? {
+            Token::MapKey(..) => stats.keys += 1,
+            Token::ListItem(..) => stats.list_items += 1,
+            Token::MultilineValue(..) => stats.multiline_values += 1,
+            Token::Comment(..) => stats.comments += 1,
+            Token::Indent(..) => {
+                depth += 1;
+                stats.max_depth = stats.max_depth.max(depth);
+            }
+            Token::Outdent(..) => depth -= 1,
+            _ => {}
+        }
+    }
+    Ok(stats)
+}
@@ -1,89 +1,7 @@
-use crate::{parse, Parser, SectionType, SyntaxError};
-
-fn string_to_json(input: &str, output: &mut String) {
-    output.push('"');
-    for c in input.chars() {
-        match c {
-            '"' => output.push_str("\\\""),
-            '\\' => output.push_str("\\\\"),
-            '\x08' => output.push_str("\\b"),
-            '\x0c' => output.push_str("\\f"),
-            '\n' => output.push_str("\\n"),
-            '\r' => output.push_str("\\r"),
-            '\t' => output.push_str("\\t"),
-            _ if c.is_ascii_control() => {
-                output.push_str(&format!("\\u{:04x}", c as u32));
-            }
-            _ => output.push(c),
-        }
-    }
-    output.push('"');
-}
+use crate::{parse, SyntaxError};
 
 pub fn to_json(content: &[u8]) -> Result<String, SyntaxError> {
-    let mut output = String::new();
-    let mut parser = parse(content);
-    section_to_json(&mut parser, &mut output, "")?;
-    Ok(output)
-}
-
-fn section_to_json<'tok>(
-    parser: &mut Parser<'tok>,
-    output: &mut String,
-    indent: &str,
-) -> Result<(), SyntaxError> {
-    use crate::Token::*;
-    let mut sect_type: Option<SectionType> = None;
-    while let Some(result) = parser.next() {
-        match result? {
-            Newline(..) | Comment(..) | MultilineHint(..) => {}
-            Indent(..) => {
-                section_to_json(parser, output, &(indent.to_string() + "  "))?;
-            }
-            Outdent(_) => {
-                break;
-            }
-            ListItem(..) => match sect_type {
-                None => {
-                    output.push('[');
-                    sect_type = Some(SectionType::List)
-                }
-                Some(SectionType::List) => {
-                    output.push(',');
-                }
-                Some(SectionType::Map) => {
-                    unreachable!()
-                }
-            },
-            ref tok @ MapKey(..) => {
-                match sect_type {
-                    None => {
-                        output.push('{');
-                        sect_type = Some(SectionType::Map)
-                    }
-                    Some(SectionType::Map) => {
-                        output.push(',');
-                    }
-                    Some(SectionType::List) => {
-                        unreachable!()
-                    }
-                }
-                string_to_json(&tok.unescape()?, output);
-                output.push(':');
-            }
-            ref tok @ Value(..) | ref tok @ MultilineValue(..) => {
-                string_to_json(&tok.unescape()?, output);
-            }
-            NoValue(..) => output.push_str("null"),
-        }
-    }
-
-    match sect_type {
-        None => output.push_str("{}"),
-        Some(SectionType::List) => output.push(']'),
-        Some(SectionType::Map) => output.push('}'),
-    }
-    return Ok(());
+    crate::to_json(content, Default::default())
 }
 
 #[test]
@@ -119,7 +37,7 @@ fn test_errors() {
 
         let input: Vec<u8> = input
             .as_bytes()
-            .into_iter()
+            .iter()
             .map(|c| if *c == b'?' { b'\xff' } else { *c })
             .collect();
 
@@ -142,3 +60,3468 @@ fn test_errors() {
         }
     }
 }
+
+#[test]
+fn test_locations() {
+    let input = "a = b\nmap ; comment\n  ключ = \"значение\"\n  = x\n";
+    let locations: Vec<(&str, usize, usize, usize)> = crate::tokenize(input.as_bytes())
+        .map(|token| {
+            let token = token.unwrap();
+            let loc = token.location();
+            (token.name(), loc.lno, loc.col, loc.char_col)
+        })
+        .collect();
+
+    assert_eq!(
+        locations,
+        vec![
+            ("map key", 1, 1, 1),
+            ("value", 1, 5, 5),
+            ("newline", 1, 6, 6),
+            ("map key", 2, 1, 1),
+            ("comment", 2, 5, 5),
+            ("newline", 2, 14, 14),
+            ("indent", 3, 3, 3),
+            ("map key", 3, 3, 3),
+            ("value", 3, 14, 10),
+            ("newline", 3, 32, 20),
+            ("list item", 4, 3, 3),
+            ("value", 4, 5, 5),
+            ("newline", 4, 6, 6),
+            ("outdent", 5, 1, 1),
+        ]
+    );
+}
+
+#[test]
+fn test_spans() {
+    let input = "a = \"b c\" ;  note \r\nlist\n  = x\n  =\n    key = \"\"\"sh\n      echo hi\n\n      exit\n";
+    let spans: Vec<(&str, &str)> = crate::tokenize(input.as_bytes())
+        .map(|token| {
+            let token = token.unwrap();
+            (token.name(), &input[std::ops::Range::from(token.span())])
+        })
+        .collect();
+
+    assert_eq!(
+        spans,
+        vec![
+            ("map key", "a"),
+            ("value", "\"b c\""),
+            ("comment", ";  note"),
+            ("newline", "\r\n"),
+            ("map key", "list"),
+            ("newline", "\n"),
+            ("indent", "  "),
+            ("list item", "="),
+            ("value", "x"),
+            ("newline", "\n"),
+            ("list item", "="),
+            ("newline", "\n"),
+            ("indent", "    "),
+            ("map key", "key"),
+            ("multiline hint", "\"\"\"sh"),
+            ("newline", "\n"),
+            ("multiline value", "echo hi\n\n      exit"),
+            ("outdent", ""),
+            ("outdent", ""),
+        ]
+    );
+}
+
+#[test]
+fn test_error_columns() {
+    let input = "a = b\n  c = \"x\\q\"\n";
+    let err = to_json(input.as_bytes()).unwrap_err();
+    assert_eq!((err.lno, err.col), (2, 3));
+
+    let input = "a\n\tb = \"x\\q\"\n";
+    let err = to_json(input.as_bytes()).unwrap_err();
+    assert_eq!(err.to_string(), "2: invalid escape code: \\q");
+    assert_eq!(
+        err.with_caret(input.as_bytes()),
+        "2:8: invalid escape code: \\q\n\tb = \"x\\q\"\n\t      ^"
+    );
+}
+
+#[cfg(feature = "serde")]
+#[test]
+fn test_deserialize() {
+    use serde::Deserialize;
+    use std::collections::HashMap;
+
+    #[derive(Deserialize, Debug, PartialEq)]
+    #[serde(rename_all = "lowercase")]
+    enum Mode {
+        Fast,
+        Slow { delay: u32 },
+    }
+
+    #[derive(Deserialize, Debug, PartialEq)]
+    struct Config {
+        name: String,
+        port: u16,
+        verbose: bool,
+        ratio: f64,
+        tags: Vec<String>,
+        missing: Option<String>,
+        empty: String,
+        none: Vec<u8>,
+        env: HashMap<String, String>,
+        mode: Mode,
+        other: Mode,
+        script: String,
+    }
+
+    let config: Config = crate::from_slice(
+        br#"
+name = "hello world"
+port = 8080 ; comment
+verbose = true
+ratio = 0.5
+tags
+  = a
+  = "b"
+missing
+empty =
+none
+env
+  PATH = /bin
+mode = fast
+other
+  slow
+    delay = 10
+script = """sh
+  echo hi
+"#,
+    )
+    .unwrap();
+
+    assert_eq!(
+        config,
+        Config {
+            name: "hello world".into(),
+            port: 8080,
+            verbose: true,
+            ratio: 0.5,
+            tags: vec!["a".into(), "b".into()],
+            missing: None,
+            empty: "".into(),
+            none: vec![],
+            env: HashMap::from([("PATH".into(), "/bin".into())]),
+            mode: Mode::Fast,
+            other: Mode::Slow { delay: 10 },
+            script: "echo hi".into(),
+        }
+    );
+
+    let err = crate::from_slice::<HashMap<String, u16>>(b"a = 1\nb = x\n").unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "2: invalid value: string \"x\", expected u16"
+    );
+
+    let err = crate::from_slice::<Config>(b"name = a\n").unwrap_err();
+    assert_eq!(err.to_string(), "1: missing field `port`");
+
+    let err = crate::from_slice::<Vec<u8>>(b"= 1\n= 2\n  = 3\n").unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "3: unexpected indent (list item on line 2 already has a value)"
+    );
+}
+
+#[cfg(feature = "serde")]
+#[test]
+fn test_serialize_round_trip() {
+    use serde::{Deserialize, Serialize};
+    use std::collections::BTreeMap;
+
+    #[derive(Serialize, Deserialize, Debug, PartialEq)]
+    enum Shape {
+        Circle(f64),
+        Rect { w: u32, h: u32 },
+        Point,
+        Line(Vec<u32>),
+    }
+
+    #[derive(Serialize, Deserialize, Debug, PartialEq)]
+    struct Config {
+        name: String,
+        tricky: Vec<String>,
+        nothing: Option<u32>,
+        empty: Vec<u32>,
+        nested: Vec<Vec<i32>>,
+        map: BTreeMap<String, bool>,
+        shapes: Vec<Shape>,
+    }
+
+    let config = Config {
+        name: "hello".into(),
+        tricky: vec![
+            "".into(),
+            " padded ".into(),
+            "a ; b".into(),
+            "a = b".into(),
+            "\"quoted\"".into(),
+            "multi\nline\r\n".into(),
+            "tab\there".into(),
+            "\u{0}\u{1b}".into(),
+            "🐱".into(),
+        ],
+        nothing: None,
+        empty: vec![],
+        nested: vec![vec![1, 2], vec![], vec![-3]],
+        map: BTreeMap::from([("a = b".into(), true), ("".into(), false)]),
+        shapes: vec![
+            Shape::Circle(1.5),
+            Shape::Rect { w: 1, h: 2 },
+            Shape::Point,
+            Shape::Line(vec![1, 2]),
+        ],
+    };
+
+    let output = crate::to_string(&config).unwrap();
+    assert_eq!(
+        output,
+        r#"name = hello
+tricky
+  = ""
+  = " padded "
+  = "a ; b"
+  = a = b
+  = "\"quoted\""
+  = "multi\nline\r\n"
+  = tab	here
+  = "\{0}\{1b}"
+  = 🐱
+nothing
+empty
+nested
+  =
+    = 1
+    = 2
+  =
+  =
+    = -3
+map
+  "" = false
+  "a = b" = true
+shapes
+  =
+    Circle = 1.5
+  =
+    Rect
+      w = 1
+      h = 2
+  = Point
+  =
+    Line
+      = 1
+      = 2
+"#
+    );
+    assert_eq!(crate::from_str::<Config>(&output).unwrap(), config);
+
+    assert!(crate::to_string(&1).is_err());
+    assert_eq!(crate::to_string(&()).unwrap(), "");
+
+    let mut serializer = crate::Serializer::default();
+    config.serialize(&mut serializer).unwrap();
+    assert_eq!(serializer.into_inner(), output);
+}
+
+#[test]
+fn test_escape() {
+    assert_eq!(crate::escape("simple value"), "simple value");
+    assert_eq!(crate::escape("a\"b"), "a\"b");
+    assert_eq!(crate::escape(""), "\"\"");
+    assert_eq!(crate::escape("a = b"), "\"a = b\"");
+    assert_eq!(crate::escape("\"a"), "\"\\\"a\"");
+    assert_eq!(crate::escape("\u{7f}"), "\"\\{7f}\"");
+
+    let alphabet = [
+        "a", " ", "\t", "\n", "\r", "\"", "\\", ";", "=", "{", "}", "🐱", "é", "\u{0}", "\u{1b}",
+        "\u{200b}",
+    ];
+    let mut seed = 0x2545f4914f6cdd1du64;
+    for _ in 0..2000 {
+        let mut value = String::new();
+        seed ^= seed << 13;
+        seed ^= seed >> 7;
+        seed ^= seed << 17;
+        for i in 0..(seed % 8) {
+            value.push_str(alphabet[((seed >> (i * 4 + 3)) % alphabet.len() as u64) as usize]);
+        }
+
+        let escaped = crate::escape(&value);
+        let input = format!("{} = {}\n= {}", escaped, escaped, escaped);
+        let tokens: Vec<_> = crate::tokenize(input.as_bytes())
+            .map(|token| token.unwrap())
+            .filter(|token| {
+                matches!(
+                    token,
+                    crate::Token::MapKey(..) | crate::Token::Value(..) | crate::Token::ListItem(..)
+                )
+            })
+            .collect();
+        assert_eq!(tokens.len(), 4, "input: {:?}", input);
+        for token in [&tokens[0], &tokens[1], &tokens[3]] {
+            assert_eq!(token.unescape().unwrap(), value, "input: {:?}", input);
+        }
+    }
+}
+
+#[test]
+fn test_parse_value() {
+    use crate::Value;
+
+    let value = crate::parse_value(
+        b"name = test ; comment\nservers\n  =\n    port = 80\n  = \"b\"\nscript = \"\"\"\n  a\n  b\nempty\n",
+    )
+    .unwrap();
+    assert_eq!(
+        value,
+        Value::Map(vec![
+            ("name".into(), Value::Scalar("test".into())),
+            (
+                "servers".into(),
+                Value::List(vec![
+                    Value::Map(vec![("port".into(), Value::Scalar("80".into()))]),
+                    Value::Scalar("b".into()),
+                ])
+            ),
+            (
+                "script".into(),
+                Value::Multiline {
+                    value: "a\nb".into(),
+                    hint: "".into()
+                }
+            ),
+            ("empty".into(), Value::Null),
+        ])
+    );
+    assert_eq!(
+        value
+            .get("servers")
+            .and_then(|s| s.index(0))
+            .and_then(|s| s.get("port"))
+            .and_then(Value::as_str),
+        Some("80")
+    );
+    assert_eq!(value.get("missing"), None);
+    assert_eq!(value.index(0), None);
+    assert_eq!(crate::parse_value(b"; nothing\n").unwrap(), Value::Null);
+    assert_eq!(
+        crate::parse_value(b"a = 1\n = b").unwrap_err().to_string(),
+        "2: unexpected indent (key on line 1 already has a value)"
+    );
+}
+
+#[test]
+fn test_parse_value_spanned() {
+    use crate::SpannedValue;
+
+    let input = b"; ports\nservers\n  = \n    port = 80\n  = b\nempty\n";
+    let value = crate::parse_value_spanned(input).unwrap();
+    let lines = |value: &crate::Spanned<SpannedValue>| match &value.value {
+        SpannedValue::Map(entries) => entries
+            .iter()
+            .map(|(k, v)| {
+                (
+                    k.location.lno,
+                    k.location.col,
+                    v.location.lno,
+                    v.location.col,
+                )
+            })
+            .collect(),
+        SpannedValue::List(items) => items
+            .iter()
+            .map(|v| (0, 0, v.location.lno, v.location.col))
+            .collect(),
+        _ => vec![],
+    };
+    assert_eq!((value.location.lno, value.location.col), (2, 1));
+    assert_eq!(lines(&value), vec![(2, 1, 3, 3), (6, 1, 6, 1)]);
+    let SpannedValue::Map(entries) = &value.value else {
+        panic!("expected map")
+    };
+    assert_eq!(lines(&entries[0].1), vec![(0, 0, 4, 5), (0, 0, 5, 5)]);
+    assert_eq!(
+        crate::Value::from(value.value),
+        crate::parse_value(input).unwrap()
+    );
+    let empty = crate::parse_value_spanned(b"").unwrap();
+    assert_eq!((empty.value, empty.location.lno), (SpannedValue::Null, 1));
+}
+
+#[cfg(feature = "serde")]
+#[test]
+fn test_deserialize_duplicate_keys() {
+    use crate::{from_slice_with, DeserializeOptions, DuplicateKeys};
+    use serde::Deserialize;
+    use std::collections::HashMap;
+
+    #[derive(Deserialize, Debug, PartialEq)]
+    struct Config {
+        a: String,
+        b: HashMap<String, String>,
+    }
+
+    let input = b"a = 1\nb\n  x = 1\n  \"x\" = 2\n  y\n    = z\n  y = 3\na = 2\n";
+    let options = |duplicate_keys| DeserializeOptions { duplicate_keys };
+    let map = |entries: &[(&str, &str)]| {
+        entries
+            .iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect::<HashMap<_, _>>()
+    };
+
+    assert_eq!(
+        from_slice_with::<Config>(input, options(DuplicateKeys::LastWins)).unwrap(),
+        Config {
+            a: "2".into(),
+            b: map(&[("x", "2"), ("y", "3")]),
+        }
+    );
+    let input_no_lists = b"a = 1\nb\n  x = 1\n  x = 2\na = 2\n";
+    let err = crate::from_slice::<Config>(input_no_lists).unwrap_err();
+    assert_eq!(err.to_string(), "5: duplicate field `a`");
+    let keys = from_slice_with::<HashMap<String, serde::de::IgnoredAny>>(
+        input,
+        options(DuplicateKeys::FirstWins),
+    )
+    .unwrap();
+    assert_eq!(keys.len(), 2);
+    let err = from_slice_with::<Config>(input, options(DuplicateKeys::FirstWins)).unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "6: invalid type: sequence, expected a string"
+    );
+    assert_eq!(
+        from_slice_with::<Config>(input_no_lists, options(DuplicateKeys::FirstWins)).unwrap(),
+        Config {
+            a: "1".into(),
+            b: map(&[("x", "1")]),
+        }
+    );
+    let err = from_slice_with::<Config>(input, options(DuplicateKeys::Error)).unwrap_err();
+    assert_eq!(err.to_string(), "4: duplicate key");
+}
+
+#[test]
+fn test_deny_duplicate_keys() {
+    fn check(input: &str) -> Result<(), String> {
+        for token in parse(input.as_bytes()).deny_duplicate_keys() {
+            token.map_err(|e| e.with_caret(input.as_bytes()))?;
+        }
+        Ok(())
+    }
+
+    assert_eq!(check("a = 1\nb = 2\nc\n  a = 1\n"), Ok(()));
+    assert_eq!(check("a\n  = 1\n  = 1\n"), Ok(()));
+    assert_eq!(check("a\n  b = 1\nc\n  b = 1\n"), Ok(()));
+    assert_eq!(
+        check("a = 1\nb = 2\na = 3\n"),
+        Err("3:1: duplicate key\na = 3\n^".into())
+    );
+    assert_eq!(
+        check("a\n  foo = 1\n  \"foo\" = 2\n"),
+        Err("3:3: duplicate key\n  \"foo\" = 2\n  ^".into())
+    );
+    assert_eq!(
+        check("a\n  b = 1\n  b\n"),
+        Err("3:3: duplicate key\n  b\n  ^".into())
+    );
+    assert!(parse(b"a = 1\na = 2").all(|token| token.is_ok()));
+}
+
+#[test]
+fn test_format() {
+    for (input, expected) in load_examples("test_data/format.txt") {
+        let output = crate::format(&input).unwrap();
+        assert_eq!(
+            output.trim_end(),
+            expected,
+            "input: {:?}",
+            String::from_utf8_lossy(&input)
+        );
+        assert_eq!(crate::format(output.as_bytes()).unwrap(), output);
+    }
+
+    let mut examples = load_examples("test_data/examples.txt");
+    examples.extend(load_examples("test_data/errors.txt"));
+    for (input, _) in examples {
+        let input_str = String::from_utf8_lossy(&input);
+        match to_json(&input) {
+            Ok(expected) => {
+                let output = crate::format(&input).unwrap();
+                assert_eq!(
+                    to_json(output.as_bytes()).unwrap(),
+                    expected,
+                    "input: {:?}",
+                    input_str
+                );
+                assert_eq!(
+                    crate::format(output.as_bytes()).unwrap(),
+                    output,
+                    "input: {:?}",
+                    input_str
+                );
+            }
+            Err(e) => {
+                let err = crate::format(&input).unwrap_err();
+                assert_eq!(err.to_string(), e.to_string(), "input: {:?}", input_str)
+            }
+        }
+    }
+    assert_eq!(crate::format(b"").unwrap(), "");
+
+    use crate::{format_with, IndentStyle};
+    let input = b"a\n b\n \t= \"\"\"\n \t\t\tx\n \t\t\t  y\n \t= 2\nc\n    = 1\n";
+    assert_eq!(
+        format_with(input, IndentStyle::Tabs).unwrap(),
+        "a\n\tb\n\t\t= \"\"\"\n\t\t\tx\n\t\t\t  y\n\t\t= 2\nc\n\t= 1\n"
+    );
+    assert_eq!(
+        format_with(input, IndentStyle::Spaces(4)).unwrap(),
+        "a\n    b\n        = \"\"\"\n            x\n              y\n        = 2\nc\n    = 1\n"
+    );
+    for style in [
+        IndentStyle::Tabs,
+        IndentStyle::Spaces(0),
+        IndentStyle::Spaces(3),
+    ] {
+        let output = format_with(input, style).unwrap();
+        assert_eq!(to_json(output.as_bytes()).unwrap(), to_json(input).unwrap());
+    }
+    assert_eq!(crate::format(b"\n; only\n\n").unwrap(), "; only\n");
+}
+
+#[test]
+fn test_recover() {
+    fn recover(input: &[u8]) -> (Vec<String>, Vec<String>) {
+        let mut parser = parse(input).recover();
+        let mut tokens = Vec::new();
+        for token in parser.by_ref() {
+            match token {
+                Ok(crate::Token::Newline(..) | crate::Token::Comment(..)) => {}
+                Ok(token) => tokens.push(format!("{}:{}", token.line_number(), token.name())),
+                Err(e) => tokens.push(format!("{}:error", e.lno)),
+            }
+        }
+        let errors = parser.errors().iter().map(|e| e.to_string()).collect();
+        (tokens, errors)
+    }
+    fn strings(strs: &[&str]) -> Vec<String> {
+        strs.iter().map(|s| s.to_string()).collect()
+    }
+
+    assert_eq!(
+        recover(b"a = 1\n  b = 2\n    c = 3\nd = 4\n"),
+        (
+            strings(&["1:map key", "1:value", "2:error", "4:map key", "4:value"]),
+            strings(&["2: unexpected indent (key on line 1 already has a value)"])
+        )
+    );
+    assert_eq!(
+        recover(b"a\n  = 1\n  b = 2\n    c = 3\n  = 4\n  = \"\"\"\nd = 5\n"),
+        (
+            strings(&[
+                "1:map key",
+                "2:indent",
+                "2:list item",
+                "2:value",
+                "3:error",
+                "5:list item",
+                "5:value",
+                "6:list item",
+                "6:multiline hint",
+                "6:error",
+                "6:no value",
+                "7:outdent",
+                "7:map key",
+                "7:value",
+            ]),
+            strings(&[
+                "3: expected list item (section started as list on line 2)",
+                "6: missing value"
+            ])
+        )
+    );
+    assert_eq!(
+        recover(b"a = \xff\nb = \xff\nc\n"),
+        (
+            strings(&[
+                "1:map key",
+                "1:error",
+                "1:no value",
+                "2:map key",
+                "2:error",
+                "2:no value",
+                "3:map key",
+                "3:no value"
+            ]),
+            strings(&["1: invalid UTF-8 at byte 4", "2: invalid UTF-8 at byte 10"])
+        )
+    );
+
+    // with no errors, recovery mode produces the same tokens
+    for (input, _) in load_examples("test_data/examples.txt") {
+        if parse(&input).any(|token| token.is_err()) {
+            continue;
+        }
+        assert!(parse(&input)
+            .map(Result::ok)
+            .eq(parse(&input).recover().map(Result::ok)));
+    }
+    // otherwise the first error is the same, and the structure stays balanced
+    for (input, _) in load_examples("test_data/errors.txt") {
+        // (some errors are only found by Token::unescape)
+        let Some(expected) = parse(&input).find_map(Result::err) else {
+            continue;
+        };
+        let mut parser = parse(&input).recover();
+        let mut depth = 0;
+        for token in parser.by_ref() {
+            match token {
+                Ok(crate::Token::Indent(..)) => depth += 1,
+                Ok(crate::Token::Outdent(..)) => depth -= 1,
+                _ => {}
+            }
+        }
+        assert_eq!(depth, 0, "input: {:?}", String::from_utf8_lossy(&input));
+        assert_eq!(parser.errors()[0].to_string(), expected.to_string());
+    }
+}
+
+#[test]
+fn test_invalid_utf8_offsets() {
+    let mut state = 0x2545f4914f6cdd1du64;
+    let mut random = move || {
+        state ^= state << 13;
+        state ^= state >> 7;
+        state ^= state << 17;
+        state
+    };
+    let alphabet: &[&[u8]] = &[
+        b"a",
+        b" ",
+        b"=",
+        b"\n",
+        b"\r",
+        b";",
+        b"\"",
+        b"\"\"\"",
+        b"  ",
+        b"\xff",
+        b"\xc3",
+        b"\xa9",
+        "\u{e9}".as_bytes(),
+        "\u{1f600}".as_bytes(),
+    ];
+    for _ in 0..2000 {
+        let mut input = Vec::new();
+        for _ in 0..random() % 30 {
+            input.extend(alphabet[random() as usize % alphabet.len()]);
+        }
+        for token in crate::tokenize(&input) {
+            let Err(e) = token else { continue };
+            let Some(offset) = e.msg.strip_prefix("invalid UTF-8 at byte ") else {
+                continue;
+            };
+            let offset: usize = offset.parse().unwrap();
+            let rest = std::str::from_utf8(&input[offset..]);
+            assert_eq!(
+                rest.map_err(|e| e.valid_up_to()),
+                Err(0),
+                "input: {:?}, error: {}",
+                input,
+                e.with_caret(&input)
+            );
+            // the line and column point at the same byte
+            let line_start = input[..offset]
+                .iter()
+                .rposition(|&c| c == b'\n' || c == b'\r')
+                .map_or(0, |i| i + 1);
+            assert_eq!(e.col, offset - line_start + 1, "input: {:?}", input);
+        }
+    }
+}
+
+#[test]
+fn test_parse_str() {
+    let mut examples = load_examples("test_data/examples.txt");
+    examples.extend(load_examples("test_data/errors.txt"));
+    for (input, _) in examples {
+        let Ok(input) = std::str::from_utf8(&input) else {
+            continue;
+        };
+        let expected: Vec<_> = parse(input.as_bytes())
+            .map(|token| token.map_err(|e| e.to_string()))
+            .collect();
+        let actual: Vec<_> = crate::parse_str(input)
+            .map(|token| token.map_err(|e| e.to_string()))
+            .collect();
+        assert_eq!(actual, expected, "input: {:?}", input);
+        assert!(crate::tokenize(input.as_bytes())
+            .map(Result::ok)
+            .eq(crate::tokenize_str(input).map(Result::ok)));
+    }
+}
+
+#[test]
+fn test_pairs() {
+    fn pairs(input: &[u8]) -> Vec<Result<(String, String), String>> {
+        crate::pairs(input)
+            .map(|pair| {
+                pair.map(|(k, v)| (k.into_owned(), v.into_owned()))
+                    .map_err(|e| e.to_string())
+            })
+            .collect()
+    }
+    fn ok(k: &str, v: &str) -> Result<(String, String), String> {
+        Ok((k.to_string(), v.to_string()))
+    }
+
+    assert_eq!(
+        pairs(
+            b"; config\nA = 1\n\"B C\" = \"x\\ty\" ; comment\nEMPTY\nD = \"\"\"sh\n  echo\n  hi\n"
+        ),
+        vec![
+            ok("A", "1"),
+            ok("B C", "x\ty"),
+            ok("EMPTY", ""),
+            ok("D", "echo\nhi")
+        ]
+    );
+    assert_eq!(pairs(b""), vec![]);
+    assert_eq!(
+        pairs(b"A = 1\nB\n  C = 2\nD = 3\n"),
+        vec![ok("A", "1"), Err("3: expected value, got indent".into())]
+    );
+    assert_eq!(
+        pairs(b"= 1\n= 2\n"),
+        vec![Err("1: expected map key, got list item".into())]
+    );
+    assert_eq!(
+        pairs(b"A = \"\\q\"\nB = 1"),
+        vec![Err("1: invalid escape code: \\q".into())]
+    );
+}
+
+#[test]
+fn test_hint_before_value() {
+    let input = b"a = \"\"\"sh ; comment\n  echo\nb = \"\"\"\n\n  x\n";
+    let tokens: Vec<_> = parse(input)
+        .hint_before_value()
+        .map(|token| {
+            let token = token.unwrap();
+            format!("{}:{}", token.name(), token.unescape().unwrap())
+        })
+        .collect();
+    assert_eq!(
+        tokens,
+        vec![
+            "map key:a",
+            "comment:comment",
+            "newline:",
+            "multiline hint:sh",
+            "multiline value:echo",
+            "map key:b",
+            "newline:",
+            "newline:",
+            "multiline hint:",
+            "multiline value:x",
+        ]
+    );
+
+    for (input, _) in load_examples("test_data/examples.txt") {
+        let tokens: Vec<_> = parse(&input).hint_before_value().collect();
+        for (i, token) in tokens.iter().enumerate() {
+            if let Ok(crate::Token::MultilineHint(..)) = token {
+                assert!(matches!(
+                    tokens[i + 1],
+                    Ok(crate::Token::MultilineValue(..))
+                ));
+            }
+        }
+        let is_hint = |token: &Result<crate::Token, SyntaxError>| {
+            matches!(token, Ok(crate::Token::MultilineHint(..)))
+        };
+        assert!(parse(&input)
+            .filter(|token| !is_hint(token))
+            .map(Result::ok)
+            .eq(tokens
+                .into_iter()
+                .filter(|token| !is_hint(token))
+                .map(Result::ok)));
+    }
+}
+
+#[test]
+fn test_verbatim_multiline() {
+    fn values(input: &[u8]) -> Vec<String> {
+        parse(input)
+            .verbatim_multiline()
+            .filter_map(|token| match token.unwrap() {
+                token @ crate::Token::MultilineValue(..) => Some(token.unescape().unwrap().into()),
+                _ => None,
+            })
+            .collect()
+    }
+
+    assert_eq!(
+        values(b"a = \"\"\"\n\n  \n  #!/bin/sh\n\n    echo\n\n\nb = 1\n"),
+        vec!["\n\n#!/bin/sh\n\n  echo\n\n\n"]
+    );
+    assert_eq!(
+        values(b"a\n  = \"\"\"\n      x\n     \n      y\n\n  = \"\"\"\n    z"),
+        vec!["x\n\ny\n\n", "z"]
+    );
+    assert!(parse(b"a = \"\"\"\n\n\nb = 1\n")
+        .verbatim_multiline()
+        .any(|token| token.is_err()));
+
+    // the structure is unchanged
+    let input = b"a\n  b = \"\"\"\n\n    x\n\n  c = \"\"\"\n    y\n\nd = 1\n";
+    assert!(parse(input)
+        .filter(|t| !matches!(
+            t,
+            Ok(crate::Token::Newline(..) | crate::Token::MultilineValue(..))
+        ))
+        .map(Result::ok)
+        .eq(parse(input)
+            .verbatim_multiline()
+            .filter(|t| !matches!(
+                t,
+                Ok(crate::Token::Newline(..) | crate::Token::MultilineValue(..))
+            ))
+            .map(Result::ok)));
+}
+
+#[test]
+fn test_unescape_raw() {
+    fn values(parser: crate::Parser) -> Vec<(String, String)> {
+        parser
+            .filter_map(|token| match token.unwrap() {
+                token @ crate::Token::MultilineValue(..) => Some((
+                    token.unescape().unwrap().into(),
+                    token.unescape_raw().unwrap().into(),
+                )),
+                _ => None,
+            })
+            .collect()
+    }
+    let input = b"a = \"\"\"\n  one\r\n  two\r  three\n\r\n    four\r\n\r\nb = 1\n";
+    assert_eq!(
+        values(parse(input)),
+        vec![(
+            "one\ntwo\nthree\n\n  four".into(),
+            "one\r\ntwo\rthree\n\r\n  four".into()
+        )]
+    );
+    assert_eq!(
+        values(parse(input).verbatim_multiline()),
+        vec![(
+            "one\ntwo\nthree\n\n  four\n\n".into(),
+            "one\r\ntwo\rthree\n\r\n  four\r\n\r\n".into()
+        )]
+    );
+    assert_eq!(
+        values(parse(b"a = \"\"\"\n  one")),
+        vec![("one".into(), "one".into())]
+    );
+    assert_eq!(
+        parse(b"a = \"\\tb\"")
+            .nth(1)
+            .unwrap()
+            .unwrap()
+            .unescape_raw()
+            .unwrap(),
+        "\tb"
+    );
+}
+
+#[test]
+fn test_to_source() {
+    fn to_source(input: &[u8]) -> Result<String, SyntaxError> {
+        let mut output = String::new();
+        let mut level = 0;
+        for token in parse(input) {
+            let token = token?;
+            match token {
+                crate::Token::Indent(..) => level += 1,
+                crate::Token::Outdent(..) => level -= 1,
+                _ => {}
+            }
+            output.push_str(&token.to_source(level)?);
+        }
+        Ok(output)
+    }
+
+    assert_eq!(
+        to_source(
+            b"; start\na=b;c\n\"d e\"\n  = \"\"\"sh\n      x\n\n       y\n  =\n    \"=\" = \"\"\n"
+        )
+        .unwrap(),
+        " ; start\na = b ; c\nd e =\n  = \"\"\"sh\n    x\n\n     y\n  =\n    \"=\" = \"\"\n"
+    );
+
+    for (input, _) in load_examples("test_data/examples.txt") {
+        let Ok(expected) = to_json(&input) else {
+            continue;
+        };
+        let output = to_source(&input).unwrap();
+        assert_eq!(
+            to_json(output.as_bytes()).unwrap(),
+            expected,
+            "input: {:?}\noutput: {:?}",
+            String::from_utf8_lossy(&input),
+            output
+        );
+    }
+}
+
+#[test]
+fn test_comment_char() {
+    use crate::{parse_with, tokenize_with, TokenizerOptions};
+    let options = TokenizerOptions { comment_char: b'#' };
+
+    let tokens: Vec<_> = parse_with(
+        b"# header\na = b # c\nd = x;y\n\"e#\" = \"\"\"sh # hint\n  # not a comment\n",
+        options,
+    )
+    .map(|token| {
+        let token = token.unwrap();
+        format!("{}:{}", token.name(), token.unescape().unwrap())
+    })
+    .collect();
+    assert_eq!(
+        tokens,
+        vec![
+            "comment:header",
+            "newline:",
+            "map key:a",
+            "value:b",
+            "comment:c",
+            "newline:",
+            "map key:d",
+            "value:x;y",
+            "newline:",
+            "map key:e#",
+            "multiline hint:sh",
+            "comment:hint",
+            "newline:",
+            "multiline value:# not a comment",
+        ]
+    );
+
+    for (input, _) in load_examples("test_data/examples.txt") {
+        assert!(crate::tokenize(&input).map(Result::ok).eq(tokenize_with(
+            &input,
+            TokenizerOptions::default()
+        )
+        .map(Result::ok)));
+    }
+    assert!(std::panic::catch_unwind(|| tokenize_with(
+        b"",
+        TokenizerOptions { comment_char: b'=' }
+    ))
+    .is_err());
+}
+
+#[test]
+fn test_is_quoted() {
+    let quoted: Vec<_> =
+        parse(b"a = 123\n\"b\" = \"123\"\nc = \"\"\nd = a\"b\"\nf = \"\"\"\n  x\ng\n")
+            .filter_map(|token| {
+                let token = token.unwrap();
+                match token {
+                    crate::Token::Newline(..) | crate::Token::MultilineHint(..) => None,
+                    _ => Some(format!(
+                        "{}:{}",
+                        token.unescape().unwrap(),
+                        token.is_quoted()
+                    )),
+                }
+            })
+            .collect();
+    assert_eq!(
+        quoted,
+        vec![
+            "a:false",
+            "123:false",
+            "b:true",
+            "123:true",
+            "c:false",
+            ":true",
+            "d:false",
+            "a\"b\":false",
+            "f:false",
+            "x:true",
+            "g:false",
+            ":false",
+        ]
+    );
+}
+
+#[test]
+fn test_typed_scalars() {
+    let tokens: Vec<_> = parse(b"a = true\nb = \"-12\"\nc = 1.5e3\nd = foo\ne\n")
+        .filter(|token| !matches!(token, Ok(crate::Token::Newline(..))))
+        .map(Result::unwrap)
+        .collect();
+    let values = |f: fn(&crate::Token) -> Result<String, SyntaxError>| -> Vec<String> {
+        tokens
+            .iter()
+            .skip(1)
+            .step_by(2)
+            .map(|token| f(token).unwrap_or_else(|e| e.to_string()))
+            .collect()
+    };
+
+    assert_eq!(
+        values(|t| t.as_bool().map(|v| v.to_string())),
+        vec![
+            "true",
+            "2: expected bool, got '-12'",
+            "3: expected bool, got '1.5e3'",
+            "4: expected bool, got 'foo'",
+            "5: expected bool, got no value",
+        ]
+    );
+    assert_eq!(
+        values(|t| t.as_i64().map(|v| v.to_string())),
+        vec![
+            "1: expected integer, got 'true'",
+            "-12",
+            "3: expected integer, got '1.5e3'",
+            "4: expected integer, got 'foo'",
+            "5: expected integer, got no value",
+        ]
+    );
+    assert_eq!(
+        values(|t| t.as_f64().map(|v| v.to_string())),
+        vec![
+            "1: expected number, got 'true'",
+            "-12",
+            "1500",
+            "4: expected number, got 'foo'",
+            "5: expected number, got no value",
+        ]
+    );
+}
+
+#[test]
+fn test_unescape_into() {
+    let mut examples = load_examples("test_data/examples.txt");
+    examples.push((
+        b"a = \"\\t\\{1F600}\\\"\"\nb = \"\"\n".to_vec(),
+        String::new(),
+    ));
+    let mut buf = String::new();
+    for (input, _) in examples {
+        for token in parse(&input).flatten() {
+            buf.clear();
+            match (token.unescape(), token.unescape_into(&mut buf)) {
+                (Ok(expected), Ok(())) => assert_eq!(buf, expected, "token: {:?}", token),
+                (Err(expected), Err(actual)) => {
+                    assert_eq!(actual.to_string(), expected.to_string())
+                }
+                (expected, actual) => panic!("{:?} != {:?}", expected, actual),
+            }
+        }
+    }
+
+    buf.clear();
+    for token in parse(b"= a\n= \"b\\n\"\n").flatten() {
+        token.unescape_into(&mut buf).unwrap();
+    }
+    assert_eq!(buf, "ab\n");
+}
+
+#[test]
+fn test_events() {
+    use crate::Event::*;
+    let scalar = |value: &str, quoted| Scalar {
+        value: value.to_string().into(),
+        quoted,
+    };
+    let mut events = crate::events(b"a\nb\n  = 1\n  =\n    c = \"\"\"\n      2\n");
+    let mut actual = vec![];
+    while let Some(event) = events.next() {
+        actual.push((event.unwrap(), events.location().lno));
+    }
+    assert_eq!(
+        actual,
+        vec![
+            (StartMap, 1),
+            (Key("a".into()), 1),
+            (Null, 1),
+            (Key("b".into()), 2),
+            (StartList, 3),
+            (scalar("1", false), 3),
+            (StartMap, 5),
+            (Key("c".into()), 5),
+            (scalar("2", true), 6),
+            (EndMap, 7),
+            (EndList, 7),
+            (EndMap, 7),
+        ]
+    );
+    assert_eq!(
+        crate::events(b"").collect::<Result<Vec<_>, _>>().unwrap(),
+        vec![]
+    );
+    assert_eq!(
+        crate::events(b"a = 1\n= 2\nb = 3")
+            .map(|event| event.map_err(|e| e.to_string()))
+            .collect::<Vec<_>>(),
+        vec![
+            Ok(StartMap),
+            Ok(Key("a".into())),
+            Ok(scalar("1", false)),
+            Err("2: expected map key (section started as map on line 1)".into()),
+        ]
+    );
+}
+
+#[test]
+fn test_json() {
+    use crate::{json_to_conl, JsonOptions};
+    let input = b"a = 1\nb\n  = true\n  = -1.5e3\n  = 01\n  =\n  = \"\"\"\n    x\n";
+    assert_eq!(
+        crate::to_json(
+            input,
+            JsonOptions {
+                pretty: true,
+                infer_types: true,
+                ..Default::default()
+            }
+        )
+        .unwrap(),
+        "{\n  \"a\": 1,\n  \"b\": [\n    true,\n    -1.5e3,\n    \"01\",\n    null,\n    \"x\"\n  ]\n}"
+    );
+    assert_eq!(
+        crate::to_json(
+            b"",
+            JsonOptions {
+                pretty: true,
+                ..Default::default()
+            }
+        )
+        .unwrap(),
+        "{}"
+    );
+    let infer = JsonOptions {
+        infer_types: true,
+        ..Default::default()
+    };
+    assert_eq!(
+        crate::to_json(b"a = \"1\"\nb = \"true\"\nc = \"\"\"\n  2\nd = 3\n", infer).unwrap(),
+        "{\"a\":\"1\",\"b\":\"true\",\"c\":\"2\",\"d\":3}"
+    );
+
+    for (input, _) in load_examples("test_data/examples.txt") {
+        let Ok(json) = to_json(&input) else {
+            continue;
+        };
+        let conl = json_to_conl(&json).unwrap();
+        assert_eq!(to_json(conl.as_bytes()).unwrap(), json, "conl: {:?}", conl);
+    }
+
+    assert_eq!(
+        json_to_conl("{\"a b\": [1, {\"c\": null}, [], \"x\\u00e9\\ud83d\\ude00\\n\"], \"=\": {}}")
+            .unwrap(),
+        "a b\n  = 1\n  =\n    c\n  =\n  = \"x\u{e9}\u{1F600}\\n\"\n\"=\"\n"
+    );
+    for (input, error) in [
+        ("1", "1: expected object or array"),
+        ("[1,]", "1: expected value"),
+        ("{\"a\" 1}", "1: expected ':'"),
+        ("{\n  \"a\": [01]\n}", "2: expected value"),
+        ("[\"\\x\"]", "1: invalid escape code"),
+        ("[\"\\ud83d\"]", "1: invalid surrogate pair"),
+        ("[\"a", "1: unclosed string"),
+        ("[] []", "1: unexpected characters after JSON value"),
+    ] {
+        let err = json_to_conl(input).unwrap_err();
+        assert_eq!(err.to_string(), error, "input: {:?}", input);
+    }
+
+    let nested = format!("{}{}", "[".repeat(128), "]".repeat(128));
+    assert!(json_to_conl(&nested).is_ok());
+    let err = json_to_conl(&"[".repeat(100_000)).unwrap_err();
+    assert_eq!(err.to_string(), "1: too deeply nested");
+}
+
+#[test]
+#[cfg(feature = "serde_json")]
+fn test_serde_json() {
+    let input = b"name = ann\nage = 42\nratio = 1.5\nadmin = yes\nnone\ntags\n  = a\n  = true\n";
+    let options = crate::JsonOptions {
+        infer_types: true,
+        ..Default::default()
+    };
+    let value = crate::to_serde_json(input, options).unwrap();
+    assert_eq!(
+        value,
+        serde_json::json!({"name": "ann", "age": 42, "ratio": 1.5, "admin": "yes", "none": null, "tags": ["a", true]})
+    );
+    assert_eq!(
+        crate::to_serde_json(input, Default::default()).unwrap()["age"],
+        serde_json::json!("42")
+    );
+    assert_eq!(
+        crate::to_serde_json(b"", Default::default()).unwrap(),
+        serde_json::json!({})
+    );
+    assert!(crate::to_serde_json(b"a = 1\n= 2", Default::default()).is_err());
+    let empty_string = crate::JsonOptions {
+        null: crate::NullStyle::EmptyString,
+        ..options
+    };
+    assert_eq!(
+        crate::to_serde_json(b"a", empty_string).unwrap(),
+        serde_json::json!({"a": ""})
+    );
+
+    assert_eq!(
+        crate::to_serde_json(b"a = \"1\"\nb\n  = 2\n  = \"\"\"\n    3\na = 4\n", options).unwrap(),
+        serde_json::json!({"a": 4, "b": [2, "3"]})
+    );
+
+    let conl = crate::from_serde_json(&value).to_string();
+    assert_eq!(
+        crate::to_serde_json(conl.as_bytes(), options).unwrap(),
+        value
+    );
+    for (input, _) in load_examples("test_data/examples.txt") {
+        let Ok(json) = crate::to_json(&input, options) else {
+            continue;
+        };
+        let expected: serde_json::Value = serde_json::from_str(&json).unwrap();
+        assert_eq!(crate::to_serde_json(&input, options).unwrap(), expected);
+    }
+}
+
+#[test]
+#[cfg(feature = "toml")]
+fn test_toml() {
+    let input = r#"
+title = "a = \"b\""
+port = 8080
+
+[server.tls]
+enabled = true
+date = 1979-05-27
+
+[[users]]
+name = "ann"
+scores = [1.5, 2]
+
+[[users]]
+name = " bob"
+"#;
+    let value = crate::from_toml(input).unwrap();
+    assert_eq!(
+        value.to_string(),
+        concat!(
+            "title = a = \"b\"\n",
+            "port = 8080\n",
+            "server\n",
+            "  tls\n",
+            "    enabled = true\n",
+            "    date = 1979-05-27\n",
+            "users\n",
+            "  =\n",
+            "    name = ann\n",
+            "    scores\n",
+            "      = 1.5\n",
+            "      = 2\n",
+            "  =\n",
+            "    name = \" bob\"\n",
+        )
+    );
+    let output = crate::to_toml(&value).unwrap();
+    assert_eq!(crate::from_toml(&output).unwrap(), value);
+    assert_eq!(
+        output.parse::<toml::Table>().unwrap(),
+        input.parse::<toml::Table>().unwrap()
+    );
+
+    let value = crate::parse_value(b"a = 01\nb = true\nc\n").unwrap();
+    assert_eq!(
+        crate::to_toml(&value).unwrap_err().to_string(),
+        "TOML has no null value"
+    );
+    assert!(crate::to_toml(&crate::parse_value(b"= 1").unwrap()).is_err());
+}
+
+#[test]
+fn test_events_with_comments() {
+    use crate::Event::*;
+    let input = b"; top\na = 1 ; one\n; before b\n\nb ; section\n  ; item\n  = x ; two\n  =\n    ; c\n    c\n; end\n";
+    let actual: Vec<_> = crate::events(input)
+        .with_comments()
+        .map(|event| {
+            let event = event.unwrap();
+            (event.event, event.comments_before, event.comment_after)
+        })
+        .collect();
+    let s = |s: &str| s.to_string();
+    let scalar = |value: &str| Scalar {
+        value: s(value).into(),
+        quoted: false,
+    };
+    assert_eq!(
+        actual,
+        vec![
+            (StartMap, vec![], None),
+            (Key("a".into()), vec![s("top")], Some(s("one"))),
+            (scalar("1"), vec![], None),
+            (Key("b".into()), vec![s("before b")], Some(s("section"))),
+            (StartList, vec![], None),
+            (scalar("x"), vec![s("item")], Some(s("two"))),
+            (StartMap, vec![], None),
+            (Key("c".into()), vec![s("c")], None),
+            (Null, vec![], None),
+            (EndMap, vec![], None),
+            (EndList, vec![], None),
+            (EndMap, vec![s("end")], None),
+        ]
+    );
+    assert!(crate::events(input)
+        .map(Result::unwrap)
+        .eq(crate::events(input)
+            .with_comments()
+            .map(|e| e.unwrap().event)));
+}
+
+#[test]
+fn test_consistent_indent() {
+    let errors = |input: &str| -> Vec<String> {
+        parse(input.as_bytes())
+            .consistent_indent()
+            .filter_map(|token| token.err().map(|e| format!("{}:{}", e, e.col)))
+            .collect()
+    };
+    for (input, expected) in [
+        ("a\n    b = 1\n  \tc = 2\n", "3: inconsistent indentation:4"),
+        ("a\n  \tb = 1\n    c = 2\n", "2: inconsistent indentation:4"),
+        (
+            "a\n    b\n      c = 1\n  \td = 2\n",
+            "4: inconsistent indentation:4",
+        ),
+        (
+            "a\n  b\n    c = 1\n\td = 2\n",
+            "4: inconsistent indentation:2",
+        ),
+        (
+            "a\n  b\n  \tc = 1\n",
+            "3: inconsistent indentation (section opened on line 2):4",
+        ),
+    ] {
+        assert_eq!(errors(input), vec![expected], "input: {:?}", input);
+    }
+    assert_eq!(
+        errors("a\n  b\n    c = 1\n  d = 2\ne\n\tf\n\t\tg\n"),
+        Vec::<String>::new()
+    );
+    // without consistent_indent, only the nesting is checked
+    assert!(parse(b"a\n  b\n  \tc = 1\n").all(|token| token.is_ok()));
+}
+
+#[test]
+#[cfg(feature = "std")]
+fn test_write_json() {
+    use crate::{write_json, JsonOptions, ReadError};
+    let options = JsonOptions {
+        pretty: true,
+        infer_types: true,
+        ..Default::default()
+    };
+    for (input, _) in load_examples("test_data/examples.txt") {
+        let mut output = Vec::new();
+        let result = write_json(&input, &mut output, options);
+        match crate::to_json(&input, options) {
+            Ok(expected) => {
+                result.unwrap();
+                assert_eq!(String::from_utf8(output).unwrap(), expected);
+            }
+            Err(e) => assert!(
+                matches!(result, Err(ReadError::Syntax(err)) if err.to_string() == e.to_string())
+            ),
+        }
+    }
+
+    struct Full;
+    impl std::io::Write for Full {
+        fn write(&mut self, _: &[u8]) -> std::io::Result<usize> {
+            Err(std::io::ErrorKind::StorageFull.into())
+        }
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+    assert!(matches!(
+        write_json(b"a = 1", &mut Full, options),
+        Err(ReadError::Io(e)) if e.kind() == std::io::ErrorKind::StorageFull
+    ));
+    let mut output = Vec::new();
+    assert!(matches!(
+        write_json(b"a = 1\n= 2", &mut output, JsonOptions::default()),
+        Err(ReadError::Syntax(e)) if e.lno == 2
+    ));
+    assert_eq!(output, b"{\"a\":\"1\"");
+}
+
+#[test]
+fn test_depth() {
+    let mut parser = parse(b"a\n  b\n    c = 1\n  d\ne = 2\n");
+    let mut depths = vec![];
+    while let Some(token) = parser.next() {
+        match token.unwrap() {
+            crate::Token::MapKey(_, key) => depths.push((key, parser.depth())),
+            crate::Token::Indent(..) | crate::Token::Outdent(..) => {
+                depths.push(("", parser.depth()))
+            }
+            _ => {}
+        }
+    }
+    assert_eq!(
+        depths,
+        vec![
+            ("a", 0),
+            ("", 1),
+            ("b", 1),
+            ("", 2),
+            ("c", 2),
+            ("", 1),
+            ("d", 1),
+            ("", 0),
+            ("e", 0)
+        ]
+    );
+    assert_eq!(parser.depth(), 0);
+}
+
+#[test]
+fn test_max_depth() {
+    let input = b"a\n  b\n    c = 1\n  d = 2\ne = 3\n";
+    let result = |parser: crate::Parser| -> Vec<String> {
+        parser
+            .filter(|token| !matches!(token, Ok(crate::Token::Newline(..))))
+            .map(|token| match token {
+                Ok(token) => token.name().to_string(),
+                Err(e) => e.to_string(),
+            })
+            .collect()
+    };
+    assert!(result(parse(input).max_depth(2))
+        .iter()
+        .all(|t| !t.contains("maximum")));
+    assert_eq!(
+        result(parse(input).max_depth(1)).last().unwrap(),
+        "3: maximum nesting depth exceeded"
+    );
+    assert_eq!(
+        result(parse(input).max_depth(0)),
+        vec!["map key", "2: maximum nesting depth exceeded"]
+    );
+    assert_eq!(
+        result(parse(input).max_depth(1).recover()),
+        vec![
+            "map key",
+            "indent",
+            "map key",
+            "3: maximum nesting depth exceeded",
+            "no value",
+            "map key",
+            "value",
+            "outdent",
+            "map key",
+            "value"
+        ]
+    );
+}
+
+#[test]
+fn test_owned() {
+    let tokens: Vec<_> = {
+        let input = b"a = \"b\"\nc\n  = d\n".to_vec();
+        parse(&input).owned().collect::<Result<_, _>>().unwrap()
+    };
+    let expected: Vec<_> = parse(b"a = \"b\"\nc\n  = d\n")
+        .map(Result::unwrap)
+        .collect();
+    assert!(tokens.iter().map(crate::OwnedToken::as_token).eq(expected));
+    assert_eq!(tokens[1].as_token().unescape().unwrap(), "b");
+}
+
+#[test]
+fn test_raw() {
+    let input = b"C:\\new = \"C:\\\\new\" ; c\nb = \"\"\"sh\n  x\\n\n";
+    let raw: Vec<_> = parse(input)
+        .map(Result::unwrap)
+        .map(|token| (token.raw(), token.unescape().unwrap()))
+        .filter(|(raw, _)| !raw.is_empty())
+        .collect();
+    assert_eq!(
+        raw,
+        vec![
+            ("C:\\new", "C:\\new".into()),
+            ("\"C:\\\\new\"", "C:\\new".into()),
+            ("c", "c".into()),
+            ("b", "b".into()),
+            ("sh", "sh".into()),
+            ("x\\n", "x\\n".into()),
+        ]
+    );
+}
+
+#[test]
+fn test_value_whitespace() {
+    let input = b"key =\t\tvalue\t\n";
+    let value = parse(input).nth(1).unwrap().unwrap();
+    assert_eq!(value.unescape().unwrap(), "value");
+    assert_eq!(&input[core::ops::Range::from(value.span())], b"value");
+
+    let value = parse(b"key = \" \tvalue\t\"").nth(1).unwrap().unwrap();
+    assert_eq!(value.unescape().unwrap(), " \tvalue\t");
+}
+
+#[test]
+fn test_parse_documents() {
+    use crate::Value;
+    let input = b"a = 1\n---\n= 2\n---\nb = \"\"\"\n  ---\n---\nc = \"\n---\n---";
+    let documents: Vec<_> = crate::parse_documents(input, "---")
+        .map(|doc| doc.map_err(|e| e.to_string()))
+        .collect();
+    assert_eq!(
+        documents,
+        vec![
+            Ok(Value::Map(vec![("a".into(), Value::Scalar("1".into()))])),
+            Ok(Value::List(vec![Value::Scalar("2".into())])),
+            Ok(Value::Map(vec![(
+                "b".into(),
+                Value::Multiline {
+                    value: "---".into(),
+                    hint: "".into()
+                }
+            )])),
+            Err("8: unclosed quotes".into()),
+            Ok(Value::Null),
+            Ok(Value::Null),
+        ]
+    );
+    assert_eq!(crate::parse_documents(b"", "===").count(), 1);
+}
+
+#[test]
+fn test_drive() {
+    use core::ops::ControlFlow;
+
+    #[derive(Default)]
+    struct Recorder {
+        calls: Vec<String>,
+        stop_at: Option<&'static str>,
+    }
+    impl crate::Visitor for Recorder {
+        fn map_key(&mut self, key: &str, loc: crate::Location) -> ControlFlow<()> {
+            self.calls.push(format!("{}:key {}", loc.lno, key));
+            match self.stop_at == Some(key) {
+                true => ControlFlow::Break(()),
+                false => ControlFlow::Continue(()),
+            }
+        }
+        fn list_item(&mut self, _: crate::Location) -> ControlFlow<()> {
+            self.calls.push("item".into());
+            ControlFlow::Continue(())
+        }
+        fn scalar(&mut self, value: &str, _: crate::Location) -> ControlFlow<()> {
+            self.calls.push(format!("scalar {}", value));
+            ControlFlow::Continue(())
+        }
+        fn no_value(&mut self, _: crate::Location) -> ControlFlow<()> {
+            self.calls.push("no value".into());
+            ControlFlow::Continue(())
+        }
+        fn start_section(&mut self, _: crate::Location) -> ControlFlow<()> {
+            self.calls.push("start".into());
+            ControlFlow::Continue(())
+        }
+        fn end_section(&mut self, _: crate::Location) -> ControlFlow<()> {
+            self.calls.push("end".into());
+            ControlFlow::Continue(())
+        }
+        fn comment(&mut self, comment: &str, _: crate::Location) -> ControlFlow<()> {
+            self.calls.push(format!("comment {}", comment));
+            ControlFlow::Continue(())
+        }
+    }
+
+    let input = b"; hi\na = \"1\"\nb\n  = x\n  =\nc = 2\n";
+    let mut visitor = Recorder::default();
+    assert_eq!(
+        crate::drive(input, &mut visitor).unwrap(),
+        ControlFlow::Continue(())
+    );
+    assert_eq!(
+        visitor.calls,
+        vec![
+            "comment hi",
+            "2:key a",
+            "scalar 1",
+            "3:key b",
+            "start",
+            "item",
+            "scalar x",
+            "item",
+            "no value",
+            "end",
+            "6:key c",
+            "scalar 2"
+        ]
+    );
+
+    let mut visitor = Recorder {
+        stop_at: Some("b"),
+        ..Default::default()
+    };
+    assert_eq!(
+        crate::drive(input, &mut visitor).unwrap(),
+        ControlFlow::Break(())
+    );
+    assert_eq!(visitor.calls.last().unwrap(), "3:key b");
+
+    let mut visitor = Recorder::default();
+    let err = crate::drive(b"a = 1\n= 2\n", &mut visitor).unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "2: expected map key (section started as map on line 1)"
+    );
+    assert_eq!(visitor.calls, vec!["1:key a", "scalar 1"]);
+}
+
+#[test]
+fn test_indent_text() {
+    let indents: Vec<_> = crate::tokenize(b"a\n\t\tb\n\t\t  c = 1\nd\n    = 2\n")
+        .filter_map(|token| match token.unwrap() {
+            crate::Token::Indent(_, indent) => Some(indent),
+            _ => None,
+        })
+        .collect();
+    assert_eq!(indents, vec!["\t\t", "\t\t  ", "    "]);
+}
+
+#[test]
+fn test_bom() {
+    let value = crate::parse_value(b"\xEF\xBB\xBFa = 1\nb = \xEF\xBB\xBF2\n").unwrap();
+    assert_eq!(
+        value,
+        crate::Value::Map(vec![
+            ("a".into(), crate::Value::Scalar("1".into())),
+            ("b".into(), crate::Value::Scalar("\u{feff}2".into())),
+        ])
+    );
+
+    let token = crate::tokenize(b"\xEF\xBB\xBFa = 1")
+        .next()
+        .unwrap()
+        .unwrap();
+    let location = token.location();
+    assert_eq!((location.col, location.span.start), (1, 3));
+    #[cfg(feature = "std")]
+    {
+        let tokens: Vec<_> = crate::parse_reader(&b"\xEF\xBB\xBFa = 1\n"[..])
+            .map(|token| token.unwrap())
+            .collect();
+        assert_eq!(tokens[0], crate::OwnedToken::MapKey(location, "a".into()));
+    }
+
+    let documents: Vec<_> = crate::parse_documents(b"\xEF\xBB\xBF---\na = 1\n", "---")
+        .map(|document| document.unwrap())
+        .collect();
+    assert_eq!(
+        documents,
+        vec![
+            crate::Value::Null,
+            crate::Value::Map(vec![("a".into(), crate::Value::Scalar("1".into()))]),
+        ]
+    );
+    let documents: Vec<_> = crate::parse_documents(b"\xEF\xBB\xBFa = 1\n---\nb = 2\n", "---")
+        .map(|document| document.unwrap())
+        .collect();
+    assert_eq!(
+        documents[0],
+        crate::Value::Map(vec![("a".into(), crate::Value::Scalar("1".into()))])
+    );
+}
+
+#[cfg(feature = "macros")]
+#[test]
+fn test_conl_macro() {
+    let value = crate::conl!(
+        "
+name = conl
+tags
+  = config
+"
+    );
+    assert_eq!(
+        value,
+        crate::Value::Map(vec![
+            ("name".into(), crate::Value::Scalar("conl".into())),
+            (
+                "tags".into(),
+                crate::Value::List(vec![crate::Value::Scalar("config".into())])
+            ),
+        ])
+    );
+
+    let err = std::panic::catch_unwind(|| crate::conl!("a\n    b\n  c")).unwrap_err();
+    assert_eq!(
+        err.downcast_ref::<String>().unwrap(),
+        "invalid CONL in conl!: 3: unexpected indent"
+    );
+}
+
+#[test]
+fn test_multiline_hint_comment() {
+    let input = b"key = \"\"\"rust ; highlight this\n  fn main() {}\n";
+    let tokens: Vec<_> = crate::tokenize(input)
+        .map(|token| token.unwrap())
+        .filter_map(|token| match token {
+            crate::Token::MultilineHint(_, hint) => Some(("hint", hint)),
+            crate::Token::Comment(_, comment) => Some(("comment", comment)),
+            _ => None,
+        })
+        .collect();
+    assert_eq!(
+        tokens,
+        vec![("hint", "rust"), ("comment", "highlight this")]
+    );
+
+    let tokens: Vec<_> = crate::parse(input).map(|token| token.unwrap()).collect();
+    assert_eq!(
+        tokens[2],
+        crate::Token::Comment(tokens[2].location(), "highlight this")
+    );
+    assert_eq!(tokens[2].location().col, 15);
+    assert_eq!(
+        crate::format(input).unwrap(),
+        "key = \"\"\"rust ; highlight this\n  fn main() {}\n"
+    );
+}
+
+#[test]
+fn test_skip_value() {
+    let input = b"a = 1\nb\n  c = 2\n  d\n    = 3\ne = \"\"\"\n  x\nf\ng ; comment\n  = 4\nh = 5\n";
+    let mut parser = crate::parse(input);
+    let mut keys = vec![];
+    while let Some(token) = parser.next() {
+        if let crate::Token::MapKey(_, key) = token.unwrap() {
+            keys.push(key);
+            if key != "h" {
+                parser.skip_value().unwrap();
+                assert_eq!(parser.depth(), 0);
+            }
+        }
+    }
+    assert_eq!(keys, vec!["a", "b", "e", "f", "g", "h"]);
+
+    let mut parser = crate::parse(b"a\n  b = 1\n    c = 2\n");
+    parser.next().unwrap().unwrap();
+    assert_eq!(
+        parser.skip_value().unwrap_err().to_string(),
+        "3: unexpected indent (key on line 2 already has a value)"
+    );
+
+    // the value has already been read, so the Outdent that follows is left alone
+    let mut parser = crate::parse(b"a\n  b = 1\nc = 2\n");
+    let tokens = parser
+        .by_ref()
+        .take(5)
+        .collect::<Result<Vec<_>, _>>()
+        .unwrap();
+    assert!(matches!(tokens[4], crate::Token::Value(_, "1")));
+    parser.skip_value().unwrap();
+    assert_eq!(parser.depth(), 1);
+    assert!(matches!(parser.next(), Some(Ok(crate::Token::Newline(..)))));
+    assert!(matches!(parser.next(), Some(Ok(crate::Token::Outdent(..)))));
+    assert_eq!(parser.depth(), 0);
+}
+
+#[test]
+fn test_no_panics() {
+    // a cheap stand-in for the fuzz target (see fuzz/): random combinations of
+    // syntactically interesting fragments must only ever produce errors.
+    let fragments: &[&[u8]] = &[
+        b" ",
+        b"\t",
+        b"\n",
+        b"\r",
+        b"=",
+        b";",
+        b"\"",
+        b"\"\"\"",
+        b"a",
+        b"\\",
+        b"\\u{",
+        b"}",
+        b"\xff",
+        b"\xc3",
+        b"\xEF\xBB\xBF",
+        b"---",
+        b"[",
+        b"{",
+        b":",
+        b",",
+        b"1",
+    ];
+    let mut seed: u64 = 0x2545f4914f6cdd1d;
+    let mut random = move |n: usize| {
+        seed ^= seed << 13;
+        seed ^= seed >> 7;
+        seed ^= seed << 17;
+        seed as usize % n
+    };
+    for _ in 0..20_000 {
+        let mut input = vec![];
+        for _ in 0..random(16) {
+            input.extend_from_slice(fragments[random(fragments.len())]);
+        }
+        for token in crate::tokenize(&input) {
+            let _ = token.map(|token| token.unescape().is_ok());
+        }
+        for token in crate::parse(&input).recover().verbatim_multiline() {
+            let _ = token;
+        }
+        let _ = crate::to_json(&input, Default::default());
+        let _ = crate::format(&input);
+        let _ = crate::parse_value(&input);
+        let _ = crate::parse_documents(&input, "---").count();
+        if let Ok(input) = std::str::from_utf8(&input) {
+            let _ = crate::json_to_conl(input);
+        }
+    }
+}
+
+#[test]
+fn test_multiline_invalid_utf8() {
+    for (input, expected, col) in [
+        (
+            &b"a = \"\"\"\n  \xff\n"[..],
+            "2: invalid UTF-8 at byte 10",
+            3,
+        ),
+        (
+            &b"a = \"\"\"\n \xc3\x28\n b\n"[..],
+            "2: invalid UTF-8 at byte 9",
+            2,
+        ),
+        (
+            &b"a = \"\"\"\n\n  x\xff\n"[..],
+            "3: invalid UTF-8 at byte 12",
+            4,
+        ),
+    ] {
+        let err = crate::parse(input).find_map(|token| token.err()).unwrap();
+        assert_eq!((err.to_string(), err.col), (expected.to_string(), col));
+    }
+}
+
+#[test]
+fn test_syntax_tree() {
+    let input = "; settings\nname = \"old\" ; the name\n\nserver\n    port=80\n    hosts\n      = a\n      =\n      = c\nscript = \"\"\"sh ; run it\n    echo hi\n\n    exit\nempty\n";
+    let mut tree = crate::SyntaxTree::parse(input).unwrap();
+    assert_eq!(tree.to_string(), input);
+
+    tree.set_value(&["name"], "new").unwrap();
+    tree.set_value(&["server", "port"], "8080").unwrap();
+    tree.set_value(&["server", "hosts", "1"], "b ; c").unwrap();
+    tree.set_value(&["server", "hosts", "2"], "").unwrap();
+    tree.set_value(&["script"], "echo bye\n  exit 1").unwrap();
+    tree.set_value(&["empty"], "full").unwrap();
+    assert_eq!(
+        tree.as_str(),
+        "; settings\nname = new ; the name\n\nserver\n    port=8080\n    hosts\n      = a\n      = \"b ; c\"\n      = \"\"\nscript = \"\"\"sh ; run it\n    echo bye\n      exit 1\nempty = full\n"
+    );
+
+    tree.set_value(&["script"], " spaced").unwrap();
+    assert_eq!(
+        crate::parse_value(tree.as_str().as_bytes())
+            .unwrap()
+            .get("script"),
+        Some(&crate::Value::Scalar(" spaced".into()))
+    );
+    assert!(tree
+        .as_str()
+        .contains("script = \" spaced\" ; run it\nempty"));
+
+    assert_eq!(
+        tree.set_value(&["server"], "x"),
+        Err(crate::EditError::Section)
+    );
+    assert_eq!(
+        tree.set_value(&["missing"], "x"),
+        Err(crate::EditError::NotFound)
+    );
+    assert_eq!(
+        tree.set_value(&["server", "hosts", "3"], "x"),
+        Err(crate::EditError::NotFound)
+    );
+
+    // the spans of the tree are kept up to date, so it matches a fresh parse
+    assert_eq!(tree, crate::SyntaxTree::parse(tree.as_str()).unwrap());
+
+    let mut tree = crate::SyntaxTree::parse("a =\n").unwrap();
+    tree.set_value(&["a"], "1").unwrap();
+    assert_eq!(tree.as_str(), "a = 1\n");
+
+    let input = "a = \"\"\"\r\n  x\r\nb\r\n  = 1\r\n";
+    let mut tree = crate::SyntaxTree::parse(input).unwrap();
+    tree.set_value(&["a"], "y\nz").unwrap();
+    tree.set_value(&["b", "0"], "two words").unwrap();
+    tree.set_value(&["a"], "y\n\nzz").unwrap();
+    assert_eq!(
+        tree.as_str(),
+        "a = \"\"\"\r\n  y\r\n\r\n  zz\r\nb\r\n  = two words\r\n"
+    );
+    assert_eq!(tree, crate::SyntaxTree::parse(tree.as_str()).unwrap());
+}
+
+#[test]
+fn test_peek_section_type() {
+    use crate::SectionType::{List, Map};
+    let mut parser = crate::parse(b"; comment\na\n  ; first\n\n  = 1\n  = 2\nb\n  c = 1\n");
+    assert_eq!(parser.peek_section_type(), Some(Map));
+    let mut seen = vec![];
+    while let Some(token) = parser.next() {
+        if let crate::Token::Indent(..) = token.unwrap() {
+            seen.push(parser.peek_section_type());
+            // peeking does not consume anything
+            assert_eq!(parser.peek_section_type(), seen.last().copied().flatten());
+        }
+    }
+    assert_eq!(seen, vec![Some(List), Some(Map)]);
+
+    assert_eq!(crate::parse(b"= a").peek_section_type(), Some(List));
+    assert_eq!(crate::parse(b"; nothing\n").peek_section_type(), None);
+}
+
+#[test]
+fn test_section_type() {
+    use crate::SectionType::{List, Map};
+    let mut parser = crate::parse(b"a\n  = 1\n  = 2\nb = 3\n");
+    let mut seen = vec![parser.section_type()];
+    while let Some(token) = parser.next() {
+        match token.unwrap() {
+            crate::Token::Indent(..) | crate::Token::Outdent(..) | crate::Token::ListItem(..) => {
+                seen.push(parser.section_type())
+            }
+            _ => {}
+        }
+    }
+    assert_eq!(seen, vec![None, None, Some(List), Some(List), Some(Map)]);
+}
+
+#[test]
+fn test_infer_scalar() {
+    use crate::{infer_scalar, Scalar};
+    for (input, expected) in [
+        ("true", Scalar::Bool(true)),
+        ("false", Scalar::Bool(false)),
+        ("null", Scalar::Null),
+        ("0", Scalar::Int(0)),
+        ("-12", Scalar::Int(-12)),
+        ("1.5", Scalar::Float(1.5)),
+        ("-2e3", Scalar::Float(-2000.0)),
+        ("9223372036854775808", Scalar::Float(9223372036854775808.0)),
+        ("True", Scalar::Str),
+        ("+1", Scalar::Str),
+        ("01", Scalar::Str),
+        (".5", Scalar::Str),
+        ("0x10", Scalar::Str),
+        ("", Scalar::Str),
+        ("hello", Scalar::Str),
+    ] {
+        assert_eq!(infer_scalar(input), expected, "input: {:?}", input);
+    }
+
+    let types: Vec<_> = crate::parse(b"a = 1\nb = \"1\"\nc\nd = \"\"\"\n  true\n")
+        .map(|token| token.unwrap())
+        .filter(|token| {
+            matches!(
+                token,
+                crate::Token::Value(..)
+                    | crate::Token::NoValue(..)
+                    | crate::Token::MultilineValue(..)
+            )
+        })
+        .map(|token| crate::infer_token(&token))
+        .collect();
+    assert_eq!(
+        types,
+        vec![Scalar::Int(1), Scalar::Str, Scalar::Null, Scalar::Str]
+    );
+}
+
+#[test]
+fn test_empty_values() {
+    for input in ["key", "key =", "key = ", "key =\t; comment", "=", "= "] {
+        let tokens: Vec<_> = parse(input.as_bytes())
+            .map(|token| token.unwrap().name())
+            .filter(|name| *name != "comment")
+            .collect();
+        assert_eq!(tokens[1], "no value", "input: {:?}", input);
+    }
+    let tokens: Vec<_> = parse(b"key = \"\"").map(|token| token.unwrap()).collect();
+    assert_eq!(tokens[1].unescape().unwrap(), "");
+
+    #[cfg(feature = "serde")]
+    {
+        #[derive(serde::Deserialize, Debug, PartialEq)]
+        struct Config {
+            a: Option<String>,
+            b: Option<String>,
+            c: Option<String>,
+        }
+        assert_eq!(
+            crate::from_slice::<Config>(b"a\nb =\nc = \"\"\n").unwrap(),
+            Config {
+                a: None,
+                b: None,
+                c: Some("".into())
+            }
+        );
+    }
+}
+
+#[test]
+fn test_leaves() {
+    let value = crate::parse_value(
+        b"server\n  ports\n    = 80\n    =\n    = 443\n  a.b = dotted\n  a\\b = slashed\nname = x\n",
+    )
+    .unwrap();
+    let leaves: Vec<_> = value.leaves().collect();
+    assert_eq!(
+        leaves,
+        vec![
+            ("server.ports.0".to_string(), "80"),
+            ("server.ports.2".to_string(), "443"),
+            ("server.a\\.b".to_string(), "dotted"),
+            ("server.a\\\\b".to_string(), "slashed"),
+            ("name".to_string(), "x"),
+        ]
+    );
+    for (path, scalar) in leaves {
+        assert_eq!(
+            value.pointer(&path).and_then(crate::Value::as_str),
+            Some(scalar)
+        );
+    }
+    assert_eq!(
+        crate::Value::Scalar("a".into())
+            .leaves()
+            .collect::<Vec<_>>(),
+        vec![(String::new(), "a")]
+    );
+}
+
+#[test]
+fn test_pointer() {
+    let value = crate::parse_value(
+        b"server\n  ports\n    = 80\n    = 443\n  a.b = dotted\n  a\\b = slashed\n  1 = one\n",
+    )
+    .unwrap();
+    let scalar = |s: &str| Some(crate::Value::Scalar(s.into()));
+    assert_eq!(value.pointer("server.ports.0").cloned(), scalar("80"));
+    assert_eq!(value.pointer("server.ports.1").cloned(), scalar("443"));
+    assert_eq!(value.pointer("server.a\\.b").cloned(), scalar("dotted"));
+    assert_eq!(value.pointer("server.a\\\\b").cloned(), scalar("slashed"));
+    assert_eq!(value.pointer("server.1").cloned(), scalar("one"));
+    assert_eq!(value.pointer(""), Some(&value));
+    assert!(matches!(
+        value.pointer("server.ports"),
+        Some(crate::Value::List(_))
+    ));
+
+    for missing in [
+        "client",
+        "server.ports.2",
+        "server.ports.x",
+        "server.ports.0.a",
+        "server.a",
+        "server.",
+        "server\\",
+    ] {
+        assert_eq!(value.pointer(missing), None, "path: {:?}", missing);
+    }
+}
+
+#[test]
+fn test_value_multiline() {
+    use crate::Value::{self, List, Map, Scalar};
+    let value = Map(vec![
+        ("script".into(), Scalar("echo hi\n\n  exit 1".into())),
+        (
+            "items".into(),
+            List(vec![
+                Scalar("a\nb".into()),
+                Scalar(" padded\n".into()),
+                Scalar("bell\u{7}\n".into()),
+            ]),
+        ),
+    ]);
+    let output = value.to_string();
+    assert_eq!(
+        output,
+        "script = \"\"\"\n  echo hi\n\n    exit 1\nitems\n  = \"\"\"\n    a\n    b\n  = \" padded\\n\"\n  = \"bell\\{7}\\n\"\n"
+    );
+    assert_eq!(
+        crate::parse_value(output.as_bytes()).unwrap().to_string(),
+        output
+    );
+
+    let input = "a = \"\"\"bash\n  echo hi\nb = \"\"\"\n  x\n";
+    let mut value = crate::parse_value(input.as_bytes()).unwrap();
+    assert_eq!(
+        value.get("a"),
+        Some(&Value::Multiline {
+            value: "echo hi".into(),
+            hint: "bash".into()
+        })
+    );
+    assert_eq!(value.get("b").and_then(Value::as_str), Some("x"));
+    assert_eq!(value.to_string(), input);
+    assert_eq!(value.to_conl_string(), input);
+    if let Value::Map(entries) = &mut value {
+        entries[1].1 = Value::Multiline {
+            value: " padded".into(),
+            hint: "".into(),
+        };
+    }
+    assert_eq!(
+        value.to_string(),
+        "a = \"\"\"bash\n  echo hi\nb = \" padded\"\n"
+    );
+}
+
+#[test]
+fn test_trim_values() {
+    let input = b"a =  x  ; comment\nb = \"  y \"  \nc =\t\nd\n  = z\t\n";
+    let values = |parser: crate::Parser| -> Vec<String> {
+        parser
+            .map(|token| token.unwrap())
+            .filter(|token| matches!(token, crate::Token::Value(..)))
+            .map(|token| token.unescape().unwrap().into_owned())
+            .collect()
+    };
+    assert_eq!(values(parse(input)), vec!["x", "  y ", "z"]);
+    assert_eq!(
+        values(parse(input).trim_values(false)),
+        vec!["  x  ", "  y ", " z\t"]
+    );
+    assert_eq!(
+        crate::tokenize(input)
+            .trim_values(false)
+            .nth(1)
+            .unwrap()
+            .unwrap()
+            .location()
+            .span,
+        crate::Span { start: 3, end: 8 }
+    );
+}
+
+#[test]
+fn test_raw_multiline_hint() {
+    let input = b"a = \"\"\" Rust Script\t; comment\n  x\nb = \"\"\"\n  y\n";
+    let hints = |parser: crate::Parser| -> Vec<String> {
+        parser
+            .map(|token| token.unwrap())
+            .filter(|token| matches!(token, crate::Token::MultilineHint(..)))
+            .map(|token| token.unescape().unwrap().into_owned())
+            .collect()
+    };
+    assert_eq!(hints(parse(input)), vec!["Rust Script", ""]);
+    assert_eq!(
+        hints(parse(input).raw_multiline_hint()),
+        vec![" Rust Script\t", ""]
+    );
+    assert_eq!(
+        crate::tokenize(input)
+            .raw_multiline_hint()
+            .nth(1)
+            .unwrap()
+            .unwrap()
+            .location()
+            .span,
+        crate::Span { start: 4, end: 20 }
+    );
+}
+
+#[test]
+fn test_validate() {
+    assert!(parse(b"a = \"\\x\"\nb\n  = 1\n").validate().is_ok());
+    assert_eq!(
+        parse(b"a = 1\n  b = 2\n")
+            .validate()
+            .unwrap_err()
+            .to_string(),
+        "2: unexpected indent (key on line 1 already has a value)"
+    );
+    assert_eq!(
+        parse(b"a = 1\na = 2\n")
+            .deny_duplicate_keys()
+            .validate()
+            .unwrap_err()
+            .to_string(),
+        "2: duplicate key"
+    );
+}
+
+#[test]
+fn test_detect_newline() {
+    use crate::{detect_newline, NewlineStyle};
+    for (input, expected) in [
+        (&b"a = 1"[..], NewlineStyle::Lf),
+        (b"a = 1\nb = 2\n", NewlineStyle::Lf),
+        (b"a = 1\r\nb = 2\r\n", NewlineStyle::CrLf),
+        (b"a = 1\rb = 2\r", NewlineStyle::Cr),
+        (b"a\r\n  b = 1\r\nc = 2\nd = 3\r\n", NewlineStyle::CrLf),
+        (b"a = 1\r\nb = 2\n", NewlineStyle::Lf),
+        (b"a = 1\rb = 2\r\n\r\n", NewlineStyle::CrLf),
+    ] {
+        assert_eq!(detect_newline(input), expected, "input: {:?}", input);
+    }
+
+    let input = b"a = \"\"\"\r\n  x\r\n\r\n  y\r\nb  =  c\n";
+    assert_eq!(
+        crate::format(input).unwrap(),
+        "a = \"\"\"\r\n  x\r\n\r\n  y\r\nb = c\r\n"
+    );
+    let options = crate::FormatOptions {
+        newline: Some(NewlineStyle::Lf),
+        ..Default::default()
+    };
+    assert_eq!(
+        crate::format_with(input, options).unwrap(),
+        "a = \"\"\"\n  x\n\n  y\nb = c\n"
+    );
+}
+
+#[test]
+fn test_empty_documents() {
+    for input in [
+        &b""[..],
+        b"\n\n",
+        b"  \n\t\n",
+        b"; just a comment\n",
+        b"\r\n; a\n\n  ; b",
+    ] {
+        assert_eq!(crate::parse_value(input).unwrap(), crate::Value::Null);
+        assert_eq!(to_json(input).unwrap(), "{}");
+        assert_eq!(crate::parse(input).peek_section_type(), None);
+        #[cfg(feature = "serde")]
+        {
+            use std::collections::BTreeMap;
+            assert_eq!(
+                crate::from_slice::<BTreeMap<String, String>>(input).unwrap(),
+                BTreeMap::new()
+            );
+            assert_eq!(
+                crate::from_slice::<Vec<String>>(input).unwrap(),
+                Vec::<String>::new()
+            );
+            assert_eq!(
+                crate::from_slice::<Option<Vec<String>>>(input).unwrap(),
+                None
+            );
+        }
+    }
+}
+
+#[test]
+fn test_key_line() {
+    let value = crate::parse_value_spanned(
+        b"; config\nname = app\n\nserver\n  port = 80\nhosts\n  = a\n  = b\n",
+    )
+    .unwrap()
+    .value;
+    assert_eq!(value.key_line("name"), Some(2));
+    assert_eq!(value.key_line("server"), Some(4));
+    assert_eq!(value.key_line("missing"), None);
+    let server = &value.get("server").unwrap().value;
+    assert_eq!(server.key_line("port"), Some(5));
+    assert_eq!(server.get("port").unwrap().location.col, 10);
+    let hosts = &value.get("hosts").unwrap().value;
+    assert_eq!(hosts.index(1).unwrap().location.lno, 8);
+    assert_eq!(hosts.key_line("a"), None);
+}
+
+#[test]
+fn test_require_content() {
+    for input in [&b""[..], b"\n", b"; nothing here\n; yet\n"] {
+        let tokens: Vec<_> = parse(input).require_content().collect();
+        let err = tokens.last().unwrap().as_ref().unwrap_err();
+        assert_eq!(err.to_string(), "1: empty document");
+        assert!(parse(input)
+            .require_content()
+            .recover()
+            .last()
+            .unwrap()
+            .is_err());
+    }
+    assert!(parse(b"a").require_content().validate().is_ok());
+    assert!(parse(b"; list\n=").require_content().validate().is_ok());
+}
+
+#[test]
+fn test_cr_line_endings() {
+    let lf = "; config\nname = app ; inline\n\nserver\n  port = 80\n  hosts\n    = a\n    = \"b\"\n\n  script = \"\"\"sh\n    echo 1\n\n    echo 2\n  empty\nlast = 1\n";
+    let summary = |input: &[u8], verbatim: bool| -> Vec<(String, usize, usize)> {
+        let mut parser = parse(input);
+        if verbatim {
+            parser = parser.verbatim_multiline();
+        }
+        parser
+            .map(|token| token.unwrap())
+            .map(|token| {
+                let loc = token.location();
+                (
+                    format!("{} {:?}", token.name(), token.unescape().ok()),
+                    loc.lno,
+                    loc.col,
+                )
+            })
+            .collect()
+    };
+    for newline in ["\r", "\r\n"] {
+        let input = lf.replace('\n', newline);
+        assert_eq!(
+            crate::parse_value(input.as_bytes()).unwrap(),
+            crate::parse_value(lf.as_bytes()).unwrap()
+        );
+        let normalize = |tokens: Vec<(String, usize, usize)>| -> Vec<(String, usize, usize)> {
+            tokens
+                .into_iter()
+                .map(|(token, lno, col)| (token.replace(newline, "\n"), lno, col))
+                .collect()
+        };
+        assert_eq!(
+            normalize(summary(input.as_bytes(), false)),
+            summary(lf.as_bytes(), false)
+        );
+        assert_eq!(
+            normalize(summary(input.as_bytes(), true)),
+            summary(lf.as_bytes(), true)
+        );
+        #[cfg(feature = "std")]
+        {
+            let tokens: Vec<_> = crate::parse_reader(input.as_bytes())
+                .map(|token| token.unwrap().as_token().location().lno)
+                .collect();
+            let expected: Vec<_> = summary(lf.as_bytes(), false).iter().map(|t| t.1).collect();
+            assert_eq!(tokens, expected);
+        }
+    }
+}
+
+#[test]
+fn test_lint() {
+    use crate::LintKind::*;
+    let input = b"\"a\" = \"b\" \r\nc\r\n  d = \"x;y\"\r\n  e\r\n      f = 1\t\r\ng\r\n\t= \"\"\nh\n \t i = \"=\"\n\"=\" = 1\n";
+    let lints: Vec<_> = crate::lint(input)
+        .into_iter()
+        .map(|lint| (lint.lno, lint.col, lint.kind))
+        .collect();
+    assert_eq!(
+        lints,
+        vec![
+            (1, 1, RedundantQuotes),
+            (1, 7, RedundantQuotes),
+            (1, 10, TrailingWhitespace),
+            (5, 1, InconsistentIndentWidth),
+            (5, 12, TrailingWhitespace),
+            (7, 1, MixedIndentation),
+            (9, 1, MixedIndentation),
+            (9, 8, RedundantQuotes),
+        ]
+    );
+    assert_eq!(
+        crate::lint(b"a\n  b\n      c = 1\n")[0].message,
+        "section is indented by 4, but the first section was indented by 2"
+    );
+    assert_eq!(crate::lint(b"a\n  b = 1\n  c\n    d = \"\"\n"), vec![]);
+    // syntax errors are ignored
+    assert_eq!(crate::lint(b"a = \"b\n  = \"c\"")[0].kind, RedundantQuotes);
+}
+
+#[test]
+fn test_raw_requoting() {
+    let input = b"a = \"b\"\n\"c d\" = \"e;f\"\ng = \"\\{68}\"\n";
+    let minimal: Vec<_> = parse(input)
+        .map(|token| token.unwrap())
+        .filter(|token| matches!(token, crate::Token::MapKey(..) | crate::Token::Value(..)))
+        .map(|token| {
+            let escaped = crate::escape(&token.unescape().unwrap()).into_owned();
+            (token.raw(), escaped == token.raw())
+        })
+        .collect();
+    assert_eq!(
+        minimal,
+        vec![
+            ("a", true),
+            ("\"b\"", false),
+            ("\"c d\"", false),
+            ("\"e;f\"", true),
+            ("g", true),
+            ("\"\\{68}\"", false),
+        ]
+    );
+}
+
+#[test]
+fn test_limits() {
+    let limits = crate::ParserLimits {
+        max_key_len: 5,
+        max_value_len: 5,
+        max_multiline_bytes: 10,
+        max_depth: 1,
+    };
+    let first_error = |input: &[u8]| {
+        parse(input)
+            .limits(limits)
+            .validate()
+            .err()
+            .map(|e| e.to_string())
+    };
+    assert_eq!(first_error(b"abcde = 12345\nb\n  c = 1\n"), None);
+    assert_eq!(first_error(b"abcdef = 1\n"), Some("1: key too long".into()));
+    assert_eq!(
+        first_error(b"a = \"1234\"\n"),
+        Some("1: value too long".into())
+    );
+    assert_eq!(first_error(b"a = \"\"\"\n  1234567\n"), None);
+    assert_eq!(
+        first_error(b"a = \"\"\"\n  1234\n  5678\n"),
+        Some("2: multiline value too long".into())
+    );
+    assert_eq!(
+        first_error(b"a\n  b\n    c = 1\n"),
+        Some("3: maximum nesting depth exceeded".into())
+    );
+    assert!(parse(b"abcdef = 1").validate().is_ok());
+
+    #[cfg(feature = "std")]
+    {
+        let err = crate::parse_reader(&b"a = 1\nabcdef = 1\n"[..])
+            .limits(limits)
+            .find_map(Result::err)
+            .unwrap();
+        assert_eq!(err.to_string(), "2: key too long");
+    }
+}
+
+#[cfg(feature = "futures")]
+#[test]
+fn test_parse_async() {
+    use futures_util::{FutureExt, StreamExt};
+
+    let mut examples = load_examples("test_data/examples.txt");
+    examples.extend(load_examples("test_data/errors.txt"));
+
+    for (input, _) in examples {
+        let expected: Vec<_> = crate::parse_reader(&input[..])
+            .map(|token| token.map_err(|e| e.to_string()))
+            .collect();
+        let reader = futures_util::io::BufReader::with_capacity(3, &input[..]);
+        let actual: Vec<_> = crate::parse_async(reader)
+            .into_stream()
+            .map(|token| token.map_err(|e| e.to_string()))
+            .collect::<Vec<_>>()
+            .now_or_never()
+            .unwrap();
+        assert_eq!(
+            actual,
+            expected,
+            "input: {:?}",
+            String::from_utf8_lossy(&input)
+        );
+    }
+
+    let mut parser = crate::parse_async(&b"a = 1\na = 2"[..]).deny_duplicate_keys();
+    let mut errors = Vec::new();
+    while let Some(token) = parser.next_async().now_or_never().unwrap() {
+        if let Err(e) = token {
+            errors.push(e.to_string());
+        }
+    }
+    assert_eq!(errors, vec!["2: duplicate key".to_string()]);
+}
+
+#[test]
+fn test_writer() {
+    use crate::{WriteError, Writer};
+
+    let mut writer = Writer::new(String::new());
+    writer
+        .map_key("a")
+        .unwrap()
+        .scalar("1")
+        .unwrap()
+        .start_list("items")
+        .unwrap()
+        .item("x")
+        .unwrap()
+        .null_item()
+        .unwrap()
+        .start_map_item()
+        .unwrap()
+        .map_key("k = v")
+        .unwrap()
+        .scalar(" padded")
+        .unwrap()
+        .map_key("lines")
+        .unwrap()
+        .scalar("one\ntwo")
+        .unwrap()
+        .end()
+        .unwrap()
+        .end()
+        .unwrap()
+        .map_key("b")
+        .unwrap()
+        .null()
+        .unwrap()
+        .start_map("empty")
+        .unwrap()
+        .end()
+        .unwrap();
+    let output = writer.finish().unwrap();
+    assert_eq!(
+        output,
+        "a = 1\nitems\n  = x\n  =\n  =\n    \"k = v\" = \" padded\"\n    lines = \"\"\"\n      one\n      two\nb\nempty\n"
+    );
+    let value = crate::parse_value(output.as_bytes()).unwrap();
+    assert_eq!(
+        value.pointer("items.2.k = v").unwrap().as_str(),
+        Some(" padded")
+    );
+    assert_eq!(
+        value.pointer("items.2.lines").unwrap().as_str(),
+        Some("one\ntwo")
+    );
+
+    let mut writer = Writer::new(String::new());
+    assert_eq!(writer.scalar("1").err(), Some(WriteError::ValueWithoutKey));
+    writer.map_key("a").unwrap();
+    assert_eq!(writer.map_key("b").err(), Some(WriteError::MissingValue));
+    assert_eq!(writer.end().err(), Some(WriteError::MissingValue));
+    writer.null().unwrap();
+    assert_eq!(writer.item("x").err(), Some(WriteError::WrongSectionType));
+    assert_eq!(writer.end().err(), Some(WriteError::NoSectionOpen));
+    writer.start_list("l").unwrap();
+    assert_eq!(
+        writer.map_key("x").err(),
+        Some(WriteError::WrongSectionType)
+    );
+    assert_eq!(writer.finish().err(), Some(WriteError::Unfinished));
+}
+
+#[test]
+fn test_quoted_comment_char() {
+    let input = br#"key = "a ; b" ; real comment
+"k;=" = "\" ; " ; c
+g = "a\\" ; d
+"#;
+    let tokens: Vec<_> = parse(input)
+        .map(|token| token.unwrap())
+        .filter(|token| !matches!(token, crate::Token::Newline(..)))
+        .map(|token| (token.name(), token.unescape().unwrap().into_owned()))
+        .collect();
+    let expected = [
+        ("map key", "key"),
+        ("value", "a ; b"),
+        ("comment", "real comment"),
+        ("map key", "k;="),
+        ("value", "\" ; "),
+        ("comment", "c"),
+        ("map key", "g"),
+        ("value", "a\\"),
+        ("comment", "d"),
+    ];
+    assert_eq!(
+        tokens,
+        expected.map(|(name, value)| (name, value.to_string()))
+    );
+
+    // an unclosed quote runs to the end of the line
+    let value = parse(b"e = \"a ; b").nth(1).unwrap().unwrap();
+    assert_eq!(value.raw(), "\"a ; b");
+}
+
+#[test]
+fn test_tokenize_after_error() {
+    let tokens = |input: &[u8]| {
+        crate::tokenize(input)
+            .map(|token| {
+                token
+                    .map(|t| format!("{}:{}", t.location().lno, t.name()))
+                    .map_err(|e| e.to_string())
+            })
+            .collect::<Vec<_>>()
+    };
+    let ok = |s: &str| Ok(s.to_string());
+    let err = |s: &str| Err(s.to_string());
+
+    assert_eq!(
+        tokens(b"a = 1 ; \xff\nb = 2\n\xfe = 3\n"),
+        vec![
+            ok("1:map key"),
+            ok("1:value"),
+            err("1: invalid UTF-8 at byte 8"),
+            ok("1:newline"),
+            ok("2:map key"),
+            ok("2:value"),
+            ok("2:newline"),
+            err("3: invalid UTF-8 at byte 16"),
+            ok("3:value"),
+            ok("3:newline"),
+        ]
+    );
+    assert_eq!(
+        tokens(b"a = \"\"\"\n  \xff\nb = 2\nc = \xfe"),
+        vec![
+            ok("1:map key"),
+            ok("1:multiline hint"),
+            ok("1:newline"),
+            err("2: invalid UTF-8 at byte 10"),
+            ok("3:map key"),
+            ok("3:value"),
+            ok("3:newline"),
+            ok("4:map key"),
+            err("4: invalid UTF-8 at byte 22"),
+        ]
+    );
+}
+
+#[test]
+fn test_bare_key_backslash() {
+    // backslashes in bare keys are literal, so they cannot escape = or ;
+    assert_eq!(
+        to_json(b"a\\=b = 1\nc\\;d\ne\\\\f = 2\n").unwrap(),
+        r#"{"a\\":"b = 1","c\\":null,"e\\\\f":"2"}"#
+    );
+    assert_eq!(crate::escape("a=b"), "\"a=b\"");
+    assert_eq!(crate::escape("c;d"), "\"c;d\"");
+    assert_eq!(
+        to_json(b"\"a=b\" = 1\n\"c;d\" = 2\n\"e\\\\f\" = 3\n").unwrap(),
+        r#"{"a=b":"1","c;d":"2","e\\f":"3"}"#
+    );
+}
+
+#[test]
+fn test_bare_value_backslash() {
+    // a backslash in a bare value is literal, so a value cannot start with \; unquoted
+    assert_eq!(
+        to_json(b"a = \\;b\nc = d\\;e\n").unwrap(),
+        r#"{"a":"\\","c":"d\\"}"#
+    );
+    assert_eq!(crate::escape(";b"), "\";b\"");
+    assert_eq!(to_json(b"a = \";b\"\n").unwrap(), r#"{"a":";b"}"#);
+}
+
+#[test]
+fn test_stats() {
+    let input = std::fs::read("example.conl").unwrap();
+    assert_eq!(
+        crate::stats(&input).unwrap(),
+        crate::Stats {
+            keys: 19,
+            list_items: 12,
+            max_depth: 3,
+            multiline_values: 2,
+            comments: 19,
+        }
+    );
+    assert_eq!(crate::stats(b"").unwrap(), crate::Stats::default());
+    assert_eq!(
+        crate::stats(b"a = \"\xff\"").unwrap_err().to_string(),
+        "1: invalid UTF-8 at byte 5"
+    );
+}
+
+#[test]
+fn test_with_trivia() {
+    use crate::{TriviaItem, TriviaPiece};
+
+    let items: Vec<_> = crate::tokenize(b"; a\n\na = 1 ; b\nc = \"\xff\"\n; d\n")
+        .with_trivia()
+        .map(|item| match item {
+            Ok(TriviaItem::Token(token)) => token.name().to_string(),
+            Ok(TriviaItem::Trivia(pieces)) => pieces
+                .iter()
+                .map(|piece| match piece {
+                    TriviaPiece::Newline(loc) => format!("{}:newline", loc.lno),
+                    TriviaPiece::Comment(loc, comment) => format!("{}:{}", loc.lno, comment),
+                })
+                .collect::<Vec<_>>()
+                .join(" "),
+            Err(e) => e.to_string(),
+        })
+        .collect();
+    assert_eq!(
+        items,
+        vec![
+            "1:a 1:newline 2:newline",
+            "map key",
+            "value",
+            "3:b 3:newline",
+            "map key",
+            "4: invalid UTF-8 at byte 20",
+            "4:newline 5:d 5:newline",
+        ]
+    );
+    assert_eq!(crate::tokenize(b"").with_trivia().count(), 0);
+}
+
+#[test]
+fn test_tab_width() {
+    let columns = |tokens: &mut dyn Iterator<Item = Result<crate::Token, crate::SyntaxError>>| {
+        tokens
+            .map(|token| token.unwrap())
+            .filter(|token| matches!(token, crate::Token::MapKey(..) | crate::Token::Value(..)))
+            .map(|token| token.location().char_col)
+            .collect::<Vec<_>>()
+    };
+    let input = "a\n\tb =\tx\n\tкл\t= y\n".as_bytes();
+    assert_eq!(columns(&mut crate::tokenize(input)), vec![1, 2, 6, 2, 7]);
+    assert_eq!(
+        columns(&mut crate::tokenize(input).tab_width(4)),
+        vec![1, 5, 9, 5, 11]
+    );
+    assert_eq!(
+        columns(&mut crate::parse(input).tab_width(8)),
+        vec![1, 9, 17, 9, 19]
+    );
+}
+
+#[test]
+fn test_value_round_trip() {
+    let mut examples = load_examples("test_data/examples.txt");
+    examples.extend(load_examples("test_data/errors.txt"));
+    examples.push((std::fs::read("example.conl").unwrap(), String::new()));
+    for (input, _) in examples {
+        // (some examples are only valid as tokens, or are errors)
+        let Ok(value) = crate::parse_value(&input) else {
+            continue;
+        };
+        let output = value.to_string();
+        assert_eq!(
+            crate::parse_value(output.as_bytes()).unwrap(),
+            value,
+            "input: {:?}\noutput: {:?}",
+            String::from_utf8_lossy(&input),
+            output
+        );
+    }
+}
+
+#[test]
+fn test_is_complete() {
+    // whether the document is complete after each line
+    let complete = |input: &str| {
+        let mut parser = parse(input.as_bytes());
+        let mut lines = Vec::new();
+        while let Some(token) = parser.next() {
+            if let crate::Token::Newline(..) = token.unwrap() {
+                lines.push(parser.is_complete());
+            }
+        }
+        assert!(parser.is_complete());
+        lines
+    };
+    assert_eq!(complete("a = 1\nb = 2\n"), vec![true, true]);
+    assert_eq!(complete("a\n  b = 1\n"), vec![false, false]);
+    assert_eq!(complete("a\n  b = 1\nc = 2\n"), vec![false, false, true]);
+    assert_eq!(complete("a = \"\"\"\n  x\n\nb = 1\n"), vec![false, true]);
+    assert!(parse(b"").is_complete());
+}
+
+#[test]
+fn test_no_inline_comments() {
+    let errors = |input: &str| {
+        parse(input.as_bytes())
+            .no_inline_comments()
+            .recover()
+            .filter_map(Result::err)
+            .map(|e| e.to_string())
+            .collect::<Vec<_>>()
+    };
+    assert_eq!(
+        errors("; a\na = 1\n  ; b\nc\n  ; d\n  = 1\ne = \"\"\"\n  ; not a comment\n"),
+        Vec::<String>::new()
+    );
+    assert_eq!(
+        errors("a = 1 ; x\nb ; y\nc\n  = ; z\nd = \"\"\"sh ; w\n  e\nf = \"; not a comment\"\n"),
+        vec![
+            "1: inline comment",
+            "2: inline comment",
+            "4: inline comment",
+            "5: inline comment",
+        ]
+    );
+    assert!(parse(b"a = 1 ; x").all(|token| token.is_ok()));
+    assert_eq!(parse(b"a = 1 ; x\nb = 2").no_inline_comments().count(), 3);
+}
+
+#[test]
+fn test_unescape_or() {
+    let values: Vec<_> = parse(b"a\nb =\nc = \"\"\nd = x\ne = \"\\ty\"\n")
+        .map(|token| token.unwrap())
+        .filter(|token| matches!(token, crate::Token::Value(..) | crate::Token::NoValue(..)))
+        .map(|token| token.unescape_or("default").unwrap())
+        .collect();
+    assert_eq!(values, vec!["default", "default", "", "x", "\ty"]);
+}
+
+#[test]
+fn test_consistent_sibling_indent() {
+    let check = |input: &str| {
+        parse(input.as_bytes())
+            .consistent_sibling_indent()
+            .find_map(Result::err)
+            .map(|e| e.with_caret(input.as_bytes()))
+    };
+    assert_eq!(
+        check("a\n  b = 1\nc\n  d\n    e = 1\n  f\n    g = 2\n"),
+        None
+    );
+    assert_eq!(check("a\n  b\n    c = 1\nd\n  e\n      f = 1\n"), None);
+    assert_eq!(
+        check("a\n  b = 1\nc\n    d = 2\n"),
+        Some(
+            "4:5: sibling indentation mismatch (section opened on line 2)\n    d = 2\n    ^".into()
+        )
+    );
+    assert_eq!(
+        check("a\n  =\n    x = 1\n  =\n      y = 2\n"),
+        Some(
+            "5:7: sibling indentation mismatch (section opened on line 3)\n      y = 2\n      ^"
+                .into()
+        )
+    );
+    assert!(parse(b"a\n  b = 1\nc\n    d = 2\n").all(|token| token.is_ok()));
+}
+
+#[test]
+fn test_checkpoint() {
+    let input = std::fs::read("example.conl").unwrap();
+    let mut tokenizer = crate::tokenize(&input);
+    let mut checkpoints = Vec::new();
+    let mut tokens = Vec::new();
+    loop {
+        if let Some(checkpoint) = tokenizer.checkpoint() {
+            checkpoints.push((checkpoint, tokens.len()));
+        }
+        let Some(token) = tokenizer.next() else {
+            break;
+        };
+        tokens.push(token.unwrap());
+    }
+    assert!(checkpoints.len() > 40);
+    assert_eq!(checkpoints[0].0.line_number(), 1);
+    for (checkpoint, seen) in &checkpoints {
+        let resumed: Vec<_> = crate::tokenize(&input)
+            .resume(checkpoint)
+            .map(Result::unwrap)
+            .collect();
+        assert_eq!(
+            resumed,
+            tokens[*seen..],
+            "line {}",
+            checkpoint.line_number()
+        );
+    }
+
+    // a checkpoint cannot be used if the input before it has changed
+    let mut tokenizer = crate::tokenize_str("é = 1\nb = 2\n");
+    tokenizer.by_ref().take(3).for_each(drop);
+    let checkpoint = tokenizer.checkpoint().unwrap();
+    assert_eq!(checkpoint.offset(), 7);
+    let resumed = std::panic::catch_unwind(|| {
+        crate::tokenize_str("aaaaa é = 2\n")
+            .resume(&checkpoint)
+            .count()
+    });
+    assert!(resumed.is_err());
+    let resumed =
+        std::panic::catch_unwind(|| crate::tokenize_str("a = 1").resume(&checkpoint).count());
+    assert!(resumed.is_err());
+    assert_eq!(
+        crate::tokenize_str("ab = 1\nc = 2\n")
+            .resume(&checkpoint)
+            .count(),
+        3
+    );
+
+    // checkpoints are only available at the start of a line
+    let mut tokenizer = crate::tokenize(b"a\n  b = 1\nc = 2\n");
+    let available: Vec<_> = core::iter::from_fn(|| {
+        let available = tokenizer.checkpoint().is_some();
+        tokenizer
+            .next()
+            .map(|token| (token.unwrap().name(), available))
+    })
+    .collect();
+    assert_eq!(
+        available,
+        vec![
+            ("map key", true),
+            ("newline", false),
+            ("indent", true),
+            ("map key", false),
+            ("value", false),
+            ("newline", false),
+            ("outdent", true),
+            ("map key", false),
+            ("value", false),
+            ("newline", false),
+        ]
+    );
+
+    // after an edit, only the lines after the checkpoint need to be tokenized
+    let before = b"a\n  b = \"\"\"\n    x\n  c = 1\n";
+    let after = b"a\n  b = \"\"\"\n    x\n  c\n    = 2\n";
+    let mut tokenizer = crate::tokenize(before);
+    tokenizer.by_ref().take(6).for_each(drop);
+    let checkpoint = tokenizer.checkpoint().unwrap();
+    assert_eq!((checkpoint.line_number(), checkpoint.offset()), (3, 12));
+    assert!(crate::tokenize(after)
+        .resume(&checkpoint)
+        .map(Result::unwrap)
+        .eq(crate::tokenize(after).skip(6).map(Result::unwrap)));
+}
+
+#[test]
+fn test_lone_quotes() {
+    assert_eq!(to_json(b"a = \"\"").unwrap(), "{\"a\":\"\"}");
+    assert_eq!(to_json(b"\"\" = 1").unwrap(), "{\"\":\"1\"}");
+    for input in ["a = \"", "= \"", "\" = 1", "a = \" ; c"] {
+        assert_eq!(
+            to_json(input.as_bytes()).unwrap_err().to_string(),
+            "1: unclosed quotes",
+            "input: {:?}",
+            input
+        );
+    }
+    assert_eq!(
+        to_json(b"a = \"\"\"").unwrap_err().to_string(),
+        "1: missing value"
+    );
+    assert_eq!(to_json(b"a = \"\"\"\n  \"").unwrap(), "{\"a\":\"\\\"\"}");
+    assert_eq!(
+        to_json(b"\"\"\" = 1").unwrap_err().to_string(),
+        "1: extra characters after quotes"
+    );
+}
+
+#[test]
+fn test_take_section() {
+    use crate::Value;
+    let mut parser = parse(b"; config\na = 1\nb\n  c = \"\"\"\n    x\n  d\ne = 3\n");
+    assert_eq!(
+        parser.take_section().unwrap().unwrap(),
+        (Some("a".into()), Value::Scalar("1".into()))
+    );
+    assert_eq!(
+        parser.take_section().unwrap().unwrap(),
+        (
+            Some("b".into()),
+            Value::Map(vec![
+                (
+                    "c".into(),
+                    Value::Multiline {
+                        value: "x".into(),
+                        hint: "".into()
+                    }
+                ),
+                ("d".into(), Value::Null),
+            ])
+        )
+    );
+    assert_eq!(parser.next().unwrap().unwrap().unescape().unwrap(), "e");
+
+    let mut parser = parse(b"= 1\n=\n  = 2\n");
+    let items: Vec<_> = core::iter::from_fn(|| parser.take_section())
+        .map(|item| item.unwrap())
+        .collect();
+    assert_eq!(
+        items,
+        vec![
+            (None, Value::Scalar("1".into())),
+            (None, Value::List(vec![Value::Scalar("2".into())])),
+        ]
+    );
+    assert!(parser.take_section().is_none());
+
+    let mut parser = parse(b"a = 1\nb\n  c = \"\\x\"\n");
+    assert!(parser.take_section().unwrap().is_ok());
+    assert_eq!(
+        parser.take_section().unwrap().unwrap_err().to_string(),
+        "3: invalid escape code: \\x"
+    );
+}
+
+#[test]
+fn test_syntax_error_is_error() {
+    fn parse_config(input: &[u8]) -> Result<crate::Value, Box<dyn std::error::Error>> {
+        Ok(crate::parse_value(input)?)
+    }
+    assert!(parse_config(b"a = 1").is_ok());
+    let err = parse_config(b"a = \"").unwrap_err();
+    assert_eq!(err.to_string(), "1: unclosed quotes");
+    assert!(err.source().is_none());
+    assert!(err.downcast_ref::<SyntaxError>().is_some());
+}
+
+#[test]
+fn test_syntax_error_eq() {
+    let error = |lno, col, msg: &str| SyntaxError {
+        lno,
+        col,
+        msg: msg.into(),
+    };
+    let errors: Vec<SyntaxError> = crate::tokenize(b"a = 1 ; \xff\nb = 2\n\xfe = 3\n")
+        .filter_map(Result::err)
+        .collect();
+    assert_eq!(
+        errors,
+        vec![
+            error(1, 9, "invalid UTF-8 at byte 8"),
+            error(3, 1, "invalid UTF-8 at byte 16"),
+        ]
+    );
+
+    let mut parser = parse(b"a\n  = 1\n  b = 2\nc = 3\n  d = 4\n").recover();
+    parser.by_ref().for_each(drop);
+    assert_eq!(
+        parser.errors(),
+        [
+            error(
+                3,
+                3,
+                "expected list item (section started as list on line 2)"
+            ),
+            error(
+                5,
+                3,
+                "unexpected indent (key on line 4 already has a value)"
+            ),
+        ]
+    );
+}
+
+#[test]
+fn test_to_conl_inline() {
+    let value = crate::parse_value(
+        b"name = ann\nnone\ntags\n  = a, b\n  =\n  = [c]\nserver\n  \"a=b\" = \"\"\n  script = \"\"\"\n    x\n    y\n",
+    )
+    .unwrap();
+    assert_eq!(
+        value.to_conl_inline(),
+        r#"{name = ann, none, tags = ["a, b", , "[c]"], server = {"a=b" = "", script = "x\ny"}}"#
+    );
+    assert_eq!(crate::Value::Null.to_conl_inline(), "");
+    assert_eq!(crate::Value::Scalar("a b".into()).to_conl_inline(), "a b");
+}
+
+#[test]
+fn test_consumed() {
+    let mut parser = parse(b"a = 1\nb\nc = 2\n");
+    let mut consumed = vec![parser.consumed()];
+    while let Some(Ok(_)) = parser.next() {
+        consumed.push(parser.consumed());
+    }
+    assert_eq!(consumed, vec![0, 3, 5, 6, 7, 8, 8, 11, 13, 14]);
+
+    let input = b"a = 1\nb = 2\n  c = 3\nd = 4\n";
+    let mut parser = parse(input);
+    let err = parser.find_map(Result::err).unwrap();
+    assert_eq!(
+        err.to_string(),
+        "3: unexpected indent (key on line 2 already has a value)"
+    );
+    assert_eq!(&input[..parser.consumed()], b"a = 1\nb = 2\n  ");
+}
+
+#[test]
+fn test_outline() {
+    let input = b"name = ann ; a comment\nnone\nserver\n  \"a b\" = \"c\\td\"\n  ports\n    = 80\n    =\n    =\n      x = 1\nscript = \"\"\"bash\n  echo a\n  echo b\n";
+    assert_eq!(
+        crate::outline(input).unwrap(),
+        "- name = ann\n- none\n- server\n  - a b = c\td\n  - ports\n    - 80\n    -\n    -\n      - x = 1\n- script = echo a…\n"
+    );
+    assert_eq!(crate::outline(b"").unwrap(), "");
+    assert_eq!(
+        crate::outline(b"a = 1\n= 2").unwrap_err().to_string(),
+        "2: expected map key (section started as map on line 1)"
+    );
+}
+
+#[test]
+fn test_json_null() {
+    use crate::{JsonOptions, NullStyle};
+    let input = b"a\nb\n  =\n  = \n  = 1\n";
+    assert_eq!(to_json(input).unwrap(), r#"{"a":null,"b":[null,null,"1"]}"#);
+    let with = |null| {
+        crate::to_json(
+            input,
+            JsonOptions {
+                null,
+                ..Default::default()
+            },
+        )
+        .unwrap()
+    };
+    assert_eq!(with(NullStyle::EmptyString), r#"{"a":"","b":["","","1"]}"#);
+    assert_eq!(with(NullStyle::EmptyObject), r#"{"a":{},"b":[{},{},"1"]}"#);
+    for style in [
+        NullStyle::Null,
+        NullStyle::EmptyString,
+        NullStyle::EmptyObject,
+    ] {
+        let json: serde_json::Value = serde_json::from_str(&with(style)).unwrap();
+        assert_eq!(json["b"][2], "1");
+    }
+}
+
+#[test]
+fn test_schema() {
+    use crate::{validate, validate_spanned, Field, Schema};
+    let schema = Schema::Map {
+        fields: vec![
+            Field::required("name", Schema::String),
+            Field::required("port", Schema::Int),
+            Field::optional("ratio", Schema::Number),
+            Field::optional("debug", Schema::Bool),
+            Field::optional("tags", Schema::List(Box::new(Schema::String))),
+            Field::optional(
+                "server",
+                Schema::Map {
+                    fields: vec![Field::required("host", Schema::String)],
+                    allow_extra: true,
+                },
+            ),
+        ],
+        allow_extra: false,
+    };
+
+    let input =
+        b"name = ann\nport = 80\nratio = 1.5\ntags\n  = a\nserver\n  host = x\n  extra = 1\n";
+    assert!(validate(&crate::parse_value(input).unwrap(), &schema).is_ok());
+    assert!(validate(
+        &crate::parse_value(b"name = a\nport = 1\ntags\n").unwrap(),
+        &schema
+    )
+    .is_ok());
+
+    let input = b"name\nport = eighty\ndebug = yes\ntags\n  = a\n  =\n    = b\nserver\n  \"a.b\" = 1\nother = 1\n";
+    let errors =
+        |errors: Vec<crate::SchemaError>| errors.iter().map(|e| e.to_string()).collect::<Vec<_>>();
+    assert_eq!(
+        errors(validate_spanned(&crate::parse_value_spanned(input).unwrap(), &schema).unwrap_err()),
+        vec![
+            "1: name: expected a value, found no value",
+            "2: port: expected an integer, found \"eighty\"",
+            "3: debug: expected true or false, found \"yes\"",
+            "7: tags.1: expected a value, found a list",
+            "9: server.host: missing required key",
+            "10: other: unexpected key",
+        ]
+    );
+    assert_eq!(
+        errors(validate(&crate::parse_value(b"= 1").unwrap(), &schema).unwrap_err()),
+        vec!["expected a map, found a list"]
+    );
+    let error = &validate(&crate::parse_value(b"port = 1").unwrap(), &schema).unwrap_err()[0];
+    assert_eq!((error.path.as_str(), error.lno), ("name", None));
+}
+
+#[test]
+fn test_parse_value_sorted() {
+    let input =
+        b"b = 1\na\n  z = 2\n  y\n    = d = 1\n    =\n      c = 3\n      b = 4\nB = 5\nb = 6\n";
+    assert_eq!(
+        crate::parse_value(input).unwrap().to_string(),
+        "b = 1\na\n  z = 2\n  y\n    = d = 1\n    =\n      c = 3\n      b = 4\nB = 5\nb = 6\n"
+    );
+    assert_eq!(
+        crate::parse_value_sorted(input).unwrap().to_string(),
+        "B = 5\na\n  y\n    = d = 1\n    =\n      b = 4\n      c = 3\n  z = 2\nb = 1\nb = 6\n"
+    );
+    assert_eq!(
+        crate::parse_value_sorted(b"x = 1\ny = 2").unwrap(),
+        crate::parse_value_sorted(b"y = 2\nx = 1").unwrap()
+    );
+}
+
+#[test]
+fn test_canonicalize() {
+    let a = b"; settings\r\nname = \"ann\"\r\nserver\r\n\tport = 80 ; http\r\n\thost = x\r\nscript = \"\"\"bash\r\n    echo a\r\n    echo b\r\nempty\r\n";
+    let b = b"empty\nscript = \"echo a\\necho b\"\nserver\n    host = \"x\"\n    port = 80\nname = ann\n";
+    let canonical = crate::canonicalize(a).unwrap();
+    assert_eq!(
+        String::from_utf8(canonical.clone()).unwrap(),
+        "empty\nname = ann\nscript = \"\"\"\n  echo a\n  echo b\nserver\n  host = x\n  port = 80\n"
+    );
+    assert_eq!(crate::canonicalize(b).unwrap(), canonical);
+    assert_eq!(crate::canonicalize(&canonical).unwrap(), canonical);
+    assert_ne!(crate::canonicalize(b"port = 81").unwrap(), canonical);
+    assert_eq!(crate::canonicalize(b"; nothing\n").unwrap(), b"");
+
+    for (input, _) in load_examples("test_data/examples.txt") {
+        if let Ok(canonical) = crate::canonicalize(&input) {
+            assert_eq!(crate::canonicalize(&canonical).unwrap(), canonical);
+        }
+    }
+}
+
+#[test]
+fn test_deny_nul() {
+    let errors = |input: &[u8]| {
+        parse(input)
+            .deny_nul()
+            .validate()
+            .map_err(|e| e.to_string())
+    };
+    assert_eq!(errors(b"a = b\0c\n"), Err("1: NUL byte in value".into()));
+    assert_eq!(
+        errors(b"x = 1\na\0 = b\n"),
+        Err("2: NUL byte in key".into())
+    );
+    assert_eq!(
+        errors(b"a = \"\"\"\n  x\n  y\0\n"),
+        Err("3: NUL byte in value".into())
+    );
+    assert_eq!(errors(b"a = \"\\{0}\" ; \0\n"), Ok(()));
+    assert!(parse(b"a = b\0c\n").validate().is_ok());
+
+    let err = parse(b"list\n  = \"\0\"\n")
+        .deny_nul()
+        .find_map(Result::err)
+        .unwrap();
+    assert_eq!((err.lno, err.col), (2, 6));
+}
+
+#[test]
+fn test_diff() {
+    use crate::{diff, Change, Value};
+    let old = crate::parse_value(
+        b"name = ann\nport = 80\nold = 1\nserver\n  a.b = 1\n  tags\n    = x\n    = y\nscript = \"\"\"\n  x\nnone\n",
+    )
+    .unwrap();
+    let new = crate::parse_value(
+        b"script = x\nport = 81\nname = ann\nserver\n  tags\n    = x\n  a.b = 2\nnone\n  = 1\nnew = 1\nnew = 2\n",
+    )
+    .unwrap();
+    assert_eq!(
+        diff(&old, &new),
+        vec![
+            Change::Changed(
+                "port".into(),
+                &Value::Scalar("80".into()),
+                &Value::Scalar("81".into())
+            ),
+            Change::Removed("old".into()),
+            Change::Changed(
+                "server.a\\.b".into(),
+                &Value::Scalar("1".into()),
+                &Value::Scalar("2".into())
+            ),
+            Change::Removed("server.tags.1".into()),
+            Change::Changed("none".into(), &Value::Null, new.get("none").unwrap()),
+            Change::Added("new".into()),
+        ]
+    );
+    assert_eq!(diff(&old, &old), vec![]);
+    let reverse = diff(&new, &old);
+    assert_eq!(reverse.len(), 6);
+    assert_eq!(reverse[1], Change::Added("server.tags.1".into()));
+    assert_eq!(reverse[5], Change::Added("old".into()));
+    let list = Value::List(vec![]);
+    assert_eq!(
+        diff(&Value::Null, &list),
+        vec![Change::Changed("".into(), &Value::Null, &list)]
+    );
+}
+
+#[cfg(feature = "serde")]
+#[test]
+fn test_deserialize_lengths() {
+    use serde::Deserialize;
+    use std::collections::HashMap;
+
+    #[derive(Deserialize, Debug, PartialEq)]
+    struct Config {
+        point: (u8, u8),
+        after: u8,
+    }
+
+    let err = crate::from_slice::<(u8, u8)>(b"= 1\n= 2\n= 3\n").unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "3: invalid length 3, expected 2 list items"
+    );
+    let err =
+        crate::from_slice::<Config>(b"point\n  = 1\n  = 2\n  = 3\n  = 4\nafter = 5\n").unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "4: invalid length 4, expected 2 list items"
+    );
+    assert_eq!(
+        crate::from_slice::<Config>(b"point\n  = 1\n  = 2\nafter = 5\n").unwrap(),
+        Config {
+            point: (1, 2),
+            after: 5
+        }
+    );
+    let err = crate::from_slice::<HashMap<String, u8>>(b"= 1\n").unwrap_err();
+    assert_eq!(err.to_string(), "1: invalid type: sequence, expected a map");
+    let err = crate::from_slice::<HashMap<String, HashMap<String, u8>>>(b"a\n  = 1\n").unwrap_err();
+    assert_eq!(err.to_string(), "2: invalid type: sequence, expected a map");
+}
+
+#[test]
+fn test_events_match_parse_value() {
+    use crate::{Event, Value};
+    // builds a Value from the events of a document
+    fn build(input: &[u8]) -> Value {
+        let mut stack: Vec<(Option<String>, Value)> = vec![];
+        let mut key = None;
+        let mut result = Value::Null;
+        for event in crate::events(input) {
+            let value = match event.unwrap() {
+                Event::StartMap => {
+                    stack.push((key.take(), Value::Map(vec![])));
+                    continue;
+                }
+                Event::StartList => {
+                    stack.push((key.take(), Value::List(vec![])));
+                    continue;
+                }
+                Event::Key(k) => {
+                    key = Some(k.into_owned());
+                    continue;
+                }
+                Event::Scalar { value, .. } => Value::Scalar(value.into_owned()),
+                Event::Null => Value::Null,
+                Event::EndMap | Event::EndList => {
+                    let (k, value) = stack.pop().unwrap();
+                    key = k;
+                    value
+                }
+            };
+            match stack.last_mut() {
+                Some((_, Value::Map(entries))) => entries.push((key.take().unwrap(), value)),
+                Some((_, Value::List(items))) => items.push(value),
+                Some(_) => unreachable!(),
+                None => result = value,
+            }
+        }
+        result
+    }
+
+    for input in [
+        &b""[..],
+        b"\n\n",
+        b"; only a comment\n",
+        b"a = 1\nb\n  = 2\n  =\n    c\n",
+        b"= 1\n=\n  d = 3\n",
+    ] {
+        assert_eq!(
+            build(input),
+            crate::parse_value(input).unwrap(),
+            "input: {:?}",
+            input
+        );
+    }
+}
+
+#[test]
+fn test_parse_trusted() {
+    let input = std::fs::read("example.conl").unwrap();
+    let expected: Vec<_> = crate::parse(&input).collect();
+    // SAFETY: example.conl is valid UTF-8 (as the parse above checks)
+    let actual: Vec<_> = unsafe { crate::parse_trusted(&input) }.collect();
+    assert!(expected.iter().all(|token| token.is_ok()));
+    assert_eq!(actual, expected);
+}
+
+fn load_examples(path: &str) -> Vec<(Vec<u8>, String)> {
+    std::fs::read_to_string(path)
+        .unwrap()
+        .replace("␉", "\t")
+        .replace("␊", "\r")
+        .split("\n===\n")
+        .map(|example| {
+            let (input, expected) = example.split_once("\n---\n").unwrap();
+            let input = input
+                .bytes()
+                .map(|c| if c == b'?' { b'\xff' } else { c })
+                .collect();
+            (input, expected.trim().to_string())
+        })
+        .collect()
+}
+
+#[cfg(feature = "std")]
+#[test]
+fn test_parse_reader() {
+    let mut examples = load_examples("test_data/examples.txt");
+    examples.extend(load_examples("test_data/errors.txt"));
+    examples.push((
+        b"a\n  b = \"\"\"\n    x\r\n\n    y\n  c\n\nd = \"\"\"\n\n  z\r\n".to_vec(),
+        String::new(),
+    ));
+
+    for (input, _) in examples {
+        let expected: Vec<_> = parse(&input)
+            .map(|token| token.map(|t| t.into_owned()).map_err(|e| e.to_string()))
+            .collect();
+        let actual: Vec<_> = crate::parse_reader(std::io::BufReader::with_capacity(3, &input[..]))
+            .map(|token| token.map_err(|e| e.to_string()))
+            .collect();
+        assert_eq!(
+            actual,
+            expected,
+            "input: {:?}",
+            String::from_utf8_lossy(&input)
+        );
+    }
+
+    let reader = crate::parse_reader(std::io::BufReader::with_capacity(3, &b"a = 1\na = 2"[..]));
+    let errors = reader
+        .deny_duplicate_keys()
+        .filter_map(Result::err)
+        .map(|e| e.to_string())
+        .collect::<Vec<_>>();
+    assert_eq!(errors, vec!["2: duplicate key".to_string()]);
+
+    let reader = crate::parse_reader(&b"a\n  b\n    c = 1\n"[..]);
+    let errors = reader
+        .max_depth(1)
+        .filter_map(Result::err)
+        .map(|e| e.to_string())
+        .collect::<Vec<_>>();
+    assert_eq!(
+        errors,
+        vec!["3: maximum nesting depth exceeded".to_string()]
+    );
+}
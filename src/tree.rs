@@ -0,0 +1,275 @@
+use alloc::string::{String, ToString};
+use alloc::vec;
+use alloc::vec::Vec;
+use core::fmt;
+use core::ops::Range;
+
+use crate::value::is_multiline_content;
+use crate::{detect_newline, escape_scalar, parse, NewlineStyle, Parser, SyntaxError, Token};
+
+/// SyntaxTree is a lossless CONL document that can be edited in place (for example
+/// by a settings UI). Unlike [crate::format], editing a value only changes the bytes
+/// of that value: comments, blank lines, indentation and the quoting of everything
+/// else are preserved exactly.
+///
+/// Values are addressed by a path of map keys (unescaped) and list indices (as
+/// decimal strings, e.g. `"0"`). If a key appears more than once, the first is used.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SyntaxTree {
+    source: String,
+    newline: NewlineStyle,
+    // the keys or list items at the top level
+    entries: Vec<Entry>,
+}
+
+/// EditError is returned by [SyntaxTree::set_value] if the value cannot be changed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum EditError {
+    /// there is no key or list item at the given path
+    NotFound,
+    /// the value at the given path is a nested section, not a scalar
+    Section,
+}
+
+impl fmt::Display for EditError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            EditError::NotFound => f.write_str("no key or list item at path"),
+            EditError::Section => f.write_str("cannot replace a section with a value"),
+        }
+    }
+}
+
+impl core::error::Error for EditError {}
+
+// a key or list item, with the spans of its tokens in the source.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Entry {
+    // the unescaped key (None for a list item)
+    key: Option<String>,
+    // the key, or the `=` of a list item
+    span: Range<usize>,
+    value: Node,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Node {
+    // no value: an edit inserts one after the key or list item
+    NoValue,
+    Value(Range<usize>),
+    Multiline {
+        hint: Range<usize>,
+        // from the end of the line with the hint to the end of the content
+        block: Range<usize>,
+        content: Range<usize>,
+        indent: String,
+    },
+    Section(Vec<Entry>),
+}
+
+impl SyntaxTree {
+    /// parse validates the input and returns a tree that reproduces it exactly.
+    /// The tree records the span of each key, list item and value, so edits only
+    /// change the bytes of the value being edited.
+    pub fn parse(input: &str) -> Result<SyntaxTree, SyntaxError> {
+        // the entries of each open section
+        let mut stack: Vec<Vec<Entry>> = vec![Vec::new()];
+        let mut hint = None;
+        let mut line_end = None;
+        for token in parse(input.as_bytes()) {
+            let token = token?;
+            let entries = stack.last_mut().unwrap();
+            let value = match token {
+                Token::MapKey(loc, _) => {
+                    entries.push(Entry {
+                        key: Some(token.unescape()?.into_owned()),
+                        span: loc.span.into(),
+                        value: Node::NoValue,
+                    });
+                    continue;
+                }
+                Token::ListItem(loc) => {
+                    entries.push(Entry {
+                        key: None,
+                        span: loc.span.into(),
+                        value: Node::NoValue,
+                    });
+                    continue;
+                }
+                Token::Value(loc, _) => Node::Value(loc.span.into()),
+                Token::MultilineHint(loc, _) => {
+                    hint = Some(Range::from(loc.span));
+                    continue;
+                }
+                Token::Newline(loc) if hint.is_some() => {
+                    line_end.get_or_insert(loc.span.start);
+                    continue;
+                }
+                Token::MultilineValue(loc, indent, _) => {
+                    let content = Range::from(loc.span);
+                    Node::Multiline {
+                        hint: hint.take().unwrap_or(content.start..content.start),
+                        block: line_end.take().unwrap_or(content.start)..content.end,
+                        content,
+                        indent: indent.to_string(),
+                    }
+                }
+                Token::Indent(..) => {
+                    stack.push(Vec::new());
+                    continue;
+                }
+                Token::Outdent(..) if stack.len() > 1 => Node::Section(stack.pop().unwrap()),
+                _ => continue,
+            };
+            if let Some(entry) = stack.last_mut().unwrap().last_mut() {
+                entry.value = value;
+            }
+        }
+        Ok(SyntaxTree {
+            source: input.to_string(),
+            newline: detect_newline(input.as_bytes()),
+            entries: stack.swap_remove(0),
+        })
+    }
+
+    /// tokens returns the tokens of the document (with spans into [SyntaxTree::as_str]).
+    /// The document is always valid, so the parser will not return errors.
+    pub fn tokens(&self) -> Parser<'_> {
+        parse(self.source.as_bytes())
+    }
+
+    /// returns the document as CONL.
+    pub fn as_str(&self) -> &str {
+        &self.source
+    }
+
+    /// set_value replaces the value of the key or list item at `path`.
+    ///
+    /// A single-line value is replaced (and quoted if necessary); a multiline value
+    /// stays multiline (with the same hint, indentation and line endings) if the new
+    /// value can be written that way; and a key or list item with no value gains one.
+    pub fn set_value(&mut self, path: &[&str], value: &str) -> Result<(), EditError> {
+        let escaped = escape_scalar(value, false);
+        let entry = find(&mut self.entries, path).ok_or(EditError::NotFound)?;
+        let (is_key, end) = (entry.key.is_some(), entry.span.end);
+        let node = match entry.value.clone() {
+            Node::Section(_) => return Err(EditError::Section),
+            Node::Value(span) => {
+                self.splice(span.clone(), &escaped);
+                Node::Value(span.start..span.start + escaped.len())
+            }
+            Node::NoValue => {
+                let rest = self.source[end..].trim_start_matches([' ', '\t']);
+                let (at, text) = if is_key && rest.starts_with('=') {
+                    (
+                        self.source.len() - rest.len() + 1,
+                        alloc::format!(" {}", escaped),
+                    )
+                } else if is_key {
+                    (end, alloc::format!(" = {}", escaped))
+                } else {
+                    (end, alloc::format!(" {}", escaped))
+                };
+                self.splice(at..at, &text);
+                Node::Value(at + text.len() - escaped.len()..at + text.len())
+            }
+            Node::Multiline {
+                hint,
+                block,
+                content,
+                indent,
+            } => {
+                if is_multiline_content(value) {
+                    let mut replacement = String::new();
+                    for (i, line) in value.split('\n').enumerate() {
+                        if i > 0 {
+                            replacement.push_str(self.newline.as_str());
+                            if !line.is_empty() {
+                                replacement.push_str(&indent);
+                            }
+                        }
+                        replacement.push_str(line);
+                    }
+                    self.splice(content.clone(), &replacement);
+                    let end = content.start + replacement.len();
+                    Node::Multiline {
+                        hint,
+                        block: block.start..end,
+                        content: content.start..end,
+                        indent,
+                    }
+                } else {
+                    // remove the block first, as it comes after the hint
+                    self.splice(block, "");
+                    self.splice(hint.clone(), &escaped);
+                    Node::Value(hint.start..hint.start + escaped.len())
+                }
+            }
+        };
+        find(&mut self.entries, path).unwrap().value = node;
+        Ok(())
+    }
+
+    // replaces the range of the source, and moves the spans that follow it.
+    fn splice(&mut self, range: Range<usize>, text: &str) {
+        self.source.replace_range(range.clone(), text);
+        shift(&mut self.entries, &range, text.len());
+    }
+}
+
+// moves the spans after an edit that replaced range with len bytes. Spans inside
+// the range belong to the edited value, which is replaced by the caller.
+fn shift(entries: &mut [Entry], range: &Range<usize>, len: usize) {
+    let moved = |offset: &mut usize| {
+        if *offset > range.start && *offset >= range.end {
+            *offset = *offset - range.len() + len;
+        }
+    };
+    for entry in entries {
+        moved(&mut entry.span.start);
+        moved(&mut entry.span.end);
+        match &mut entry.value {
+            Node::NoValue => {}
+            Node::Value(span) => {
+                moved(&mut span.start);
+                moved(&mut span.end);
+            }
+            Node::Multiline {
+                hint,
+                block,
+                content,
+                ..
+            } => {
+                for span in [hint, block, content] {
+                    moved(&mut span.start);
+                    moved(&mut span.end);
+                }
+            }
+            Node::Section(entries) => shift(entries, range, len),
+        }
+    }
+}
+
+// finds the first key or list item at path.
+fn find<'a>(entries: &'a mut [Entry], path: &[&str]) -> Option<&'a mut Entry> {
+    let (first, rest) = path.split_first()?;
+    let entry = match first.parse::<usize>() {
+        Ok(index) if entries.first().is_some_and(|e| e.key.is_none()) => entries.get_mut(index)?,
+        _ => entries
+            .iter_mut()
+            .find(|e| e.key.as_deref() == Some(first))?,
+    };
+    if rest.is_empty() {
+        return Some(entry);
+    }
+    match &mut entry.value {
+        Node::Section(entries) => find(entries, rest),
+        _ => None,
+    }
+}
+
+impl fmt::Display for SyntaxTree {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.source)
+    }
+}
@@ -0,0 +1,67 @@
+use alloc::vec::Vec;
+
+use crate::{Location, SyntaxError, Token, Tokenizer};
+
+/// TriviaPiece is a comment or newline. See [Tokenizer::with_trivia].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TriviaPiece<'tok> {
+    /// see [Token::Newline]
+    Newline(Location),
+    /// see [Token::Comment]
+    Comment(Location, &'tok str),
+}
+
+/// TriviaItem is returned by [Tokenizer::with_trivia].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TriviaItem<'tok> {
+    /// the comments and newlines before the next token (or the end of the input)
+    Trivia(Vec<TriviaPiece<'tok>>),
+    /// any other token
+    Token(Token<'tok>),
+}
+
+/// See [Tokenizer::with_trivia]
+pub struct WithTrivia<'tok> {
+    tokenizer: Tokenizer<'tok>,
+    // the token (or error) that ended the current run of trivia
+    pending: Option<Result<Token<'tok>, SyntaxError>>,
+}
+
+impl<'tok> Tokenizer<'tok> {
+    /// with_trivia groups each run of consecutive [Token::Newline] and [Token::Comment]
+    /// tokens into a single [TriviaItem::Trivia], which is returned before the token that
+    /// follows it. This lets formatters treat comments and blank lines as leading trivia
+    /// of the next significant token. Errors are returned in order, after any trivia before them.
+    pub fn with_trivia(self) -> WithTrivia<'tok> {
+        WithTrivia {
+            tokenizer: self,
+            pending: None,
+        }
+    }
+}
+
+impl<'tok> Iterator for WithTrivia<'tok> {
+    type Item = Result<TriviaItem<'tok>, SyntaxError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if let Some(token) = self.pending.take() {
+            return Some(token.map(TriviaItem::Token));
+        }
+        let mut trivia = Vec::new();
+        for token in self.tokenizer.by_ref() {
+            match token {
+                Ok(Token::Newline(loc)) => trivia.push(TriviaPiece::Newline(loc)),
+                Ok(Token::Comment(loc, comment)) => trivia.push(TriviaPiece::Comment(loc, comment)),
+                token if trivia.is_empty() => return Some(token.map(TriviaItem::Token)),
+                token => {
+                    self.pending = Some(token);
+                    break;
+                }
+            }
+        }
+        if trivia.is_empty() {
+            return None;
+        }
+        Some(Ok(TriviaItem::Trivia(trivia)))
+    }
+}
@@ -0,0 +1,605 @@
+use alloc::borrow::Cow;
+use alloc::string::{String, ToString};
+use alloc::{vec, vec::Vec};
+use core::fmt;
+
+use crate::{
+    escape_scalar, is_newline, newline_size, parse, quote, tokenize, Location, Parser, SyntaxError,
+    Token, Tokenizer, BOM,
+};
+
+/// Value is a parsed CONL document (or part of one). See [parse_value].
+/// It implements [fmt::Display] to write it back out as CONL with two-space indentation
+/// (values that contain newlines, or came from a multiline value, are written as multiline values).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Value {
+    /// A single-line value (unescaped)
+    Scalar(String),
+    /// A multiline value, and its hint (empty if there was none). This records how the value
+    /// was written so that it can be written the same way; use [Value::as_str] to read
+    /// either kind of value.
+    Multiline { value: String, hint: String },
+    /// A map, with keys in the order they appeared in the document
+    Map(Vec<(String, Value)>),
+    /// A list
+    List(Vec<Value>),
+    /// A key or list item with no value, or an empty document
+    Null,
+}
+
+impl Value {
+    /// returns the value of the first entry with the given key if this is a map.
+    pub fn get(&self, key: &str) -> Option<&Value> {
+        match self {
+            Value::Map(entries) => entries.iter().find(|(k, _)| k == key).map(|(_, v)| v),
+            _ => None,
+        }
+    }
+
+    /// returns the item at the given position if this is a list.
+    pub fn index(&self, index: usize) -> Option<&Value> {
+        match self {
+            Value::List(items) => items.get(index),
+            _ => None,
+        }
+    }
+
+    /// pointer returns the value at a dotted path like `server.ports.0`. Each segment
+    /// is a key in a map, or an index in a list. A `.` or `\` in a key can be escaped
+    /// with a backslash (so `a\.b` is the key `a.b`). The empty path is this value.
+    pub fn pointer(&self, path: &str) -> Option<&Value> {
+        if path.is_empty() {
+            return Some(self);
+        }
+        let mut value = self;
+        let mut segment = String::new();
+        let mut chars = path.chars();
+        loop {
+            let c = chars.next();
+            match c {
+                Some('\\') => segment.push(chars.next()?),
+                Some('.') | None => {
+                    value = match value {
+                        Value::List(_) => value.index(segment.parse().ok()?)?,
+                        _ => value.get(&segment)?,
+                    };
+                    if c.is_none() {
+                        return Some(value);
+                    }
+                    segment.clear();
+                }
+                Some(c) => segment.push(c),
+            }
+        }
+    }
+
+    /// leaves returns the dotted path (as accepted by [Value::pointer]) and contents of
+    /// every scalar in this value, in document order. List indices are numeric segments,
+    /// and `.` or `\` in keys are escaped. Keys or list items with no value are skipped.
+    pub fn leaves(&self) -> impl Iterator<Item = (String, &str)> {
+        let mut leaves = Vec::new();
+        self.collect_leaves(String::new(), &mut leaves);
+        leaves.into_iter()
+    }
+
+    fn collect_leaves<'a>(&'a self, path: String, leaves: &mut Vec<(String, &'a str)>) {
+        let join = |segment: &str| join_path(&path, segment);
+        match self {
+            Value::Scalar(value) | Value::Multiline { value, .. } => leaves.push((path, value)),
+            Value::Map(entries) => {
+                for (key, value) in entries {
+                    value.collect_leaves(join(key), leaves);
+                }
+            }
+            Value::List(items) => {
+                for (i, value) in items.iter().enumerate() {
+                    value.collect_leaves(join(&i.to_string()), leaves);
+                }
+            }
+            Value::Null => {}
+        }
+    }
+
+    /// returns the contents if this is a single-line or multiline value.
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            Value::Scalar(value) | Value::Multiline { value, .. } => Some(value),
+            _ => None,
+        }
+    }
+    /// sort_keys sorts the keys of every map in this value (recursively) by their bytes.
+    /// Duplicate keys keep their relative order. The original order of the keys is lost.
+    pub fn sort_keys(&mut self) {
+        match self {
+            Value::Map(entries) => {
+                entries.sort_by(|(a, _), (b, _)| a.cmp(b));
+                for (_, value) in entries {
+                    value.sort_keys();
+                }
+            }
+            Value::List(items) => items.iter_mut().for_each(Value::sort_keys),
+            Value::Scalar(_) | Value::Multiline { .. } | Value::Null => {}
+        }
+    }
+
+    /// to_conl_string returns the value as CONL. It is the same as `to_string()` (see
+    /// the [fmt::Display] impl), and exists for symmetry with [parse_value].
+    pub fn to_conl_string(&self) -> String {
+        self.to_string()
+    }
+
+    /// to_conl_inline returns the value on a single line, for log messages. CONL has no
+    /// inline syntax, so this uses `{key = value, ...}` for maps and `[item, ...]` for lists.
+    /// A key with no value is written without ` = `, and a list item with no value is empty.
+    /// Keys and values are escaped as for [crate::escape], and are also quoted if they contain
+    /// `,`, `{`, `}`, `[` or `]`. The output is not valid CONL (use [Value::to_conl_string] for that).
+    pub fn to_conl_inline(&self) -> String {
+        let mut output = String::new();
+        self.write_inline(&mut output);
+        output
+    }
+
+    fn write_inline(&self, output: &mut String) {
+        match self {
+            Value::Scalar(value) | Value::Multiline { value, .. } => {
+                output.push_str(&escape_inline(value, false))
+            }
+            Value::Map(entries) => {
+                output.push('{');
+                for (i, (key, value)) in entries.iter().enumerate() {
+                    if i > 0 {
+                        output.push_str(", ");
+                    }
+                    output.push_str(&escape_inline(key, true));
+                    if *value != Value::Null {
+                        output.push_str(" = ");
+                        value.write_inline(output);
+                    }
+                }
+                output.push('}');
+            }
+            Value::List(items) => {
+                output.push('[');
+                for (i, value) in items.iter().enumerate() {
+                    if i > 0 {
+                        output.push_str(", ");
+                    }
+                    value.write_inline(output);
+                }
+                output.push(']');
+            }
+            Value::Null => {}
+        }
+    }
+}
+
+// escapes a key or value for Value::to_conl_inline, quoting the delimiters it uses.
+fn escape_inline(value: &str, is_key: bool) -> Cow<'_, str> {
+    let escaped = escape_scalar(value, is_key);
+    if escaped.starts_with('"') || !value.contains([',', '{', '}', '[', ']']) {
+        return escaped;
+    }
+    Cow::Owned(quote(value))
+}
+
+// appends a segment to a dotted path (as accepted by Value::pointer), escaping `.` and `\`.
+pub(crate) fn join_path(path: &str, segment: &str) -> String {
+    let mut child = String::from(path);
+    if !child.is_empty() {
+        child.push('.');
+    }
+    for c in segment.chars() {
+        if c == '.' || c == '\\' {
+            child.push('\\');
+        }
+        child.push(c);
+    }
+    child
+}
+
+/// parse_value parses a CONL document into a [Value].
+/// A document with no keys or list items (e.g. an empty file, or one with only comments
+/// and blank lines) is [Value::Null].
+pub fn parse_value(input: &[u8]) -> Result<Value, SyntaxError> {
+    Ok(parse_value_spanned(input)?.value.into())
+}
+
+/// parse_value_sorted is like [parse_value], but the keys of every map are sorted (see
+/// [Value::sort_keys]), so documents that differ only in the order of their keys produce the
+/// same value. Unlike [parse_value] this loses the order of the keys in the document.
+pub fn parse_value_sorted(input: &[u8]) -> Result<Value, SyntaxError> {
+    let mut value = parse_value(input)?;
+    value.sort_keys();
+    Ok(value)
+}
+
+/// canonicalize returns a canonical form of a CONL document, for hashing or comparing
+/// documents: two documents that parse to the same [Value] (after sorting keys, and ignoring
+/// whether values were written as multiline values) produce identical bytes, and
+/// canonicalizing the output again returns it unchanged.
+///
+/// The output has the keys of every map sorted (see [Value::sort_keys]), two-space
+/// indentation, `\n` line endings, and no comments. Keys and values are only quoted if
+/// necessary (see [crate::escape]), and values with newlines are written as multiline values
+/// (without a hint) where possible.
+pub fn canonicalize(input: &[u8]) -> Result<Vec<u8>, SyntaxError> {
+    let mut value = parse_value_sorted(input)?;
+    forget_multiline(&mut value);
+    Ok(value.to_string().into_bytes())
+}
+
+// replaces multiline values with single-line ones, so that they are written the same way
+// as other values.
+fn forget_multiline(value: &mut Value) {
+    match value {
+        Value::Multiline { value: content, .. } => *value = Value::Scalar(core::mem::take(content)),
+        Value::Map(entries) => entries
+            .iter_mut()
+            .for_each(|(_, value)| forget_multiline(value)),
+        Value::List(items) => items.iter_mut().for_each(forget_multiline),
+        Value::Scalar(_) | Value::Null => {}
+    }
+}
+
+/// Spanned is a value with the [Location] in the input it came from.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Spanned<T> {
+    pub value: T,
+    pub location: Location,
+}
+
+/// SpannedValue is like [Value], but each key and value records its [Location]. See [parse_value_spanned].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SpannedValue {
+    /// A single-line value (unescaped)
+    Scalar(String),
+    /// A multiline value, and its hint (empty if there was none)
+    Multiline { value: String, hint: String },
+    /// A map, with keys in the order they appeared in the document
+    Map(Vec<(Spanned<String>, Spanned<SpannedValue>)>),
+    /// A list
+    List(Vec<Spanned<SpannedValue>>),
+    /// A key or list item with no value, or an empty document
+    Null,
+}
+
+impl SpannedValue {
+    /// returns the value of the first entry with the given key if this is a map.
+    pub fn get(&self, key: &str) -> Option<&Spanned<SpannedValue>> {
+        self.entry(key).map(|(_, v)| v)
+    }
+
+    /// returns the line number of the first entry with the given key if this is a map.
+    /// This is useful for reporting errors in the value of a key.
+    pub fn key_line(&self, key: &str) -> Option<usize> {
+        self.entry(key).map(|(k, _)| k.location.lno)
+    }
+
+    /// returns the item at the given position if this is a list.
+    pub fn index(&self, index: usize) -> Option<&Spanned<SpannedValue>> {
+        match self {
+            SpannedValue::List(items) => items.get(index),
+            _ => None,
+        }
+    }
+
+    fn entry(&self, key: &str) -> Option<&(Spanned<String>, Spanned<SpannedValue>)> {
+        match self {
+            SpannedValue::Map(entries) => entries.iter().find(|(k, _)| k.value == key),
+            _ => None,
+        }
+    }
+}
+
+impl From<SpannedValue> for Value {
+    fn from(value: SpannedValue) -> Self {
+        match value {
+            SpannedValue::Scalar(value) => Value::Scalar(value),
+            SpannedValue::Multiline { value, hint } => Value::Multiline { value, hint },
+            SpannedValue::Map(entries) => Value::Map(
+                entries
+                    .into_iter()
+                    .map(|(key, value)| (key.value, value.value.into()))
+                    .collect(),
+            ),
+            SpannedValue::List(items) => {
+                Value::List(items.into_iter().map(|item| item.value.into()).collect())
+            }
+            SpannedValue::Null => Value::Null,
+        }
+    }
+}
+
+/// parse_value_spanned is like [parse_value], but records where each key and value is.
+///
+/// The location of a scalar is that of its value, of a key or list item with no value
+/// is that of the key or list item, and of a map or list is that of its first key or list item.
+/// An empty document is at the start of the input.
+pub fn parse_value_spanned(input: &[u8]) -> Result<Spanned<SpannedValue>, SyntaxError> {
+    parse_section(&mut parse(input))
+}
+
+// reads entries until the end of the current section
+fn parse_section(parser: &mut Parser<'_>) -> Result<Spanned<SpannedValue>, SyntaxError> {
+    let mut section = Spanned {
+        value: SpannedValue::Null,
+        location: Location {
+            lno: 1,
+            col: 1,
+            char_col: 1,
+            ..Default::default()
+        },
+    };
+    while let Some(token) = parser.next() {
+        let token = token?;
+        if let Token::MapKey(..) | Token::ListItem(..) = token {
+            if matches!(section.value, SpannedValue::Null) {
+                section.location = token.location();
+            }
+        }
+        match token {
+            Token::MapKey(location, _) => {
+                let key = Spanned {
+                    value: token.unescape()?.into_owned(),
+                    location,
+                };
+                let value = parse_item(parser)?;
+                match &mut section.value {
+                    SpannedValue::Map(entries) => entries.push((key, value)),
+                    _ => section.value = SpannedValue::Map(vec![(key, value)]),
+                }
+            }
+            Token::ListItem(..) => {
+                let value = parse_item(parser)?;
+                match &mut section.value {
+                    SpannedValue::List(items) => items.push(value),
+                    _ => section.value = SpannedValue::List(vec![value]),
+                }
+            }
+            Token::Outdent(..) => break,
+            _ => {}
+        }
+    }
+    Ok(section)
+}
+
+// reads the value following a key or list item
+fn parse_item(parser: &mut Parser<'_>) -> Result<Spanned<SpannedValue>, SyntaxError> {
+    let mut hint = String::new();
+    while let Some(token) = parser.next() {
+        let token = token?;
+        let location = token.location();
+        match token {
+            Token::Value(..) => {
+                return Ok(Spanned {
+                    value: SpannedValue::Scalar(token.unescape()?.into_owned()),
+                    location,
+                })
+            }
+            Token::MultilineHint(..) => hint = token.unescape()?.into_owned(),
+            Token::MultilineValue(..) => {
+                return Ok(Spanned {
+                    value: SpannedValue::Multiline {
+                        value: token.unescape()?.into_owned(),
+                        hint,
+                    },
+                    location,
+                })
+            }
+            Token::Indent(..) => return parse_section(parser),
+            Token::NoValue(..) => {
+                return Ok(Spanned {
+                    value: SpannedValue::Null,
+                    location,
+                })
+            }
+            _ => {}
+        }
+    }
+    Ok(Spanned {
+        value: SpannedValue::Null,
+        location: Location::default(),
+    })
+}
+
+impl<'tok> Parser<'tok> {
+    /// take_section reads the next key or list item of the current section and its value
+    /// (including any nested sections) as a [Value], leaving the parser positioned at the
+    /// entry after it. The key is None for a list item. Returns None at the end of the
+    /// section (consuming its [Token::Outdent]) or of the document.
+    ///
+    /// This makes it possible to process a large document one top-level entry at a time,
+    /// and to stop without reading the rest. It must be called between entries (not after
+    /// a [Token::MapKey] or [Token::ListItem] has been returned).
+    pub fn take_section(&mut self) -> Option<Result<(Option<String>, Value), SyntaxError>> {
+        loop {
+            let token = match self.next()? {
+                Ok(token) => token,
+                Err(e) => return Some(Err(e)),
+            };
+            let key = match token {
+                Token::MapKey(..) => match token.unescape() {
+                    Ok(key) => Some(key.into_owned()),
+                    Err(e) => return Some(Err(e)),
+                },
+                Token::ListItem(..) => None,
+                Token::Outdent(..) => return None,
+                _ => continue,
+            };
+            return Some(parse_item(self).map(|value| (key, value.value.into())));
+        }
+    }
+}
+
+/// parse_documents parses a sequence of CONL documents separated by lines that contain exactly
+/// `separator` (for example `---`). Each document is parsed independently (so a syntax error only
+/// affects the document it is in), and line numbers in errors count from the start of the input.
+///
+/// Lines in a multiline value are always indented, so they are never mistaken for a separator.
+/// A separator at the end of the input is followed by an empty document ([Value::Null]).
+///
+/// # Panics
+/// If the separator is empty, starts with whitespace, or contains a newline.
+pub fn parse_documents<'a>(input: &'a [u8], separator: &'a str) -> Documents<'a> {
+    assert!(
+        !separator.is_empty()
+            && !separator.as_bytes().iter().any(is_newline)
+            && !separator.starts_with([' ', '\t']),
+        "invalid separator: {:?}",
+        separator
+    );
+    Documents {
+        input,
+        separator: separator.as_bytes(),
+        // a byte order mark is not part of the first line
+        offset: if input.starts_with(BOM) { BOM.len() } else { 0 },
+        lno: 1,
+        finished: false,
+    }
+}
+
+/// See [parse_documents]
+pub struct Documents<'a> {
+    input: &'a [u8],
+    separator: &'a [u8],
+    // where the next document starts
+    offset: usize,
+    lno: usize,
+    finished: bool,
+}
+
+impl Documents<'_> {
+    // returns the next document, and the line number after it.
+    fn next_chunk(&mut self) -> (usize, &[u8]) {
+        let (start, lno) = (self.offset, self.lno);
+        let mut pos = start;
+        loop {
+            let rest = &self.input[pos..];
+            let end = rest.iter().position(is_newline).unwrap_or(rest.len());
+            let at_end = end == rest.len();
+            let next = pos
+                + end
+                + if at_end {
+                    0
+                } else {
+                    newline_size(&rest[end..])
+                };
+            if &rest[..end] == self.separator {
+                self.offset = next;
+                self.lno += 1;
+                return (lno, &self.input[start..pos]);
+            }
+            if at_end {
+                self.finished = true;
+                return (lno, &self.input[start..]);
+            }
+            pos = next;
+            self.lno += 1;
+        }
+    }
+}
+
+impl Iterator for Documents<'_> {
+    type Item = Result<Value, SyntaxError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.finished {
+            return None;
+        }
+        let offset = self.offset;
+        let (lno, chunk) = self.next_chunk();
+        let mut parser = Parser::new(Tokenizer {
+            lno,
+            source_offset: offset,
+            ..tokenize(chunk)
+        });
+        // a byte order mark at the start of the input was skipped by parse_documents,
+        // so one at the start of a chunk is part of the document
+        parser.tokenizer.input = chunk;
+        parser.tokenizer.line_start = 0;
+        Some(parse_section(&mut parser).map(|value| value.value.into()))
+    }
+}
+
+impl fmt::Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Value::Scalar(value) | Value::Multiline { value, .. } => {
+                f.write_str(&escape_scalar(value, false))
+            }
+            _ => write_section(self, 0, f),
+        }
+    }
+}
+
+// returns true if the value would be unchanged by writing it as a multiline value
+// (which has no escapes, and from which leading and trailing whitespace is removed).
+pub(crate) fn is_multiline_content(value: &str) -> bool {
+    let trimmed = value.trim_matches(|c| matches!(c, ' ' | '\t' | '\n'));
+    !value.is_empty()
+        && trimmed == value
+        && !value.contains(|c: char| c.is_control() && c != '\n' && c != '\t')
+}
+
+// writes a value for a key or list item at the given depth, as a multiline value if
+// it contains newlines (and can be written that way), otherwise escaped.
+pub(crate) fn write_scalar(value: &str, depth: usize, f: &mut impl fmt::Write) -> fmt::Result {
+    if !value.contains('\n') {
+        return f.write_str(&escape_scalar(value, false));
+    }
+    write_multiline(value, "", depth, f)
+}
+
+// writes a value as a multiline value with the given hint if it can be written that way,
+// otherwise escaped.
+fn write_multiline(value: &str, hint: &str, depth: usize, f: &mut impl fmt::Write) -> fmt::Result {
+    if !is_multiline_content(value) || hint.contains(|c: char| c == ';' || c.is_control()) {
+        return f.write_str(&escape_scalar(value, false));
+    }
+    f.write_str("\"\"\"")?;
+    f.write_str(hint)?;
+    for line in value.split('\n') {
+        f.write_str("\n")?;
+        if !line.is_empty() {
+            for _ in 0..=depth {
+                f.write_str("  ")?;
+            }
+            f.write_str(line)?;
+        }
+    }
+    Ok(())
+}
+
+// writes the entries of a map or list as CONL, indented to the given depth.
+// Empty maps and lists cannot be represented, so are written as no value, and
+// values with newlines are written as multiline values where possible.
+fn write_section(section: &Value, depth: usize, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    let entries: Vec<(Option<&str>, &Value)> = match section {
+        Value::Map(entries) => entries.iter().map(|(k, v)| (Some(&k[..]), v)).collect(),
+        Value::List(items) => items.iter().map(|v| (None, v)).collect(),
+        _ => return Ok(()),
+    };
+    for (key, value) in entries {
+        for _ in 0..depth {
+            f.write_str("  ")?;
+        }
+        match key {
+            Some(key) => f.write_str(&escape_scalar(key, true))?,
+            None => f.write_str("=")?,
+        }
+        match value {
+            Value::Scalar(value) => {
+                f.write_str(if key.is_some() { " = " } else { " " })?;
+                write_scalar(value, depth, f)?;
+            }
+            Value::Multiline { value, hint } => {
+                f.write_str(if key.is_some() { " = " } else { " " })?;
+                write_multiline(value, hint, depth, f)?;
+            }
+            _ => {}
+        }
+        f.write_str("\n")?;
+        write_section(value, depth + 1, f)?;
+    }
+    Ok(())
+}
@@ -0,0 +1,76 @@
+use core::ops::ControlFlow;
+
+use crate::{parse, Location, SyntaxError, Token};
+
+/// Visitor receives callbacks from [drive] as a CONL document is parsed.
+///
+/// Each method has a default implementation that does nothing, so you only need to
+/// implement the ones you care about. Return [ControlFlow::Break] from any method
+/// to stop parsing early.
+#[allow(unused_variables)]
+pub trait Visitor {
+    /// called for each map key (unescaped).
+    fn map_key(&mut self, key: &str, location: Location) -> ControlFlow<()> {
+        ControlFlow::Continue(())
+    }
+
+    /// called for each list item (before its value).
+    fn list_item(&mut self, location: Location) -> ControlFlow<()> {
+        ControlFlow::Continue(())
+    }
+
+    /// called for each single-line or multiline value (unescaped).
+    fn scalar(&mut self, value: &str, location: Location) -> ControlFlow<()> {
+        ControlFlow::Continue(())
+    }
+
+    /// called for a map key or list item that has no value.
+    fn no_value(&mut self, location: Location) -> ControlFlow<()> {
+        ControlFlow::Continue(())
+    }
+
+    /// called when the value of a map key or list item is a nested section.
+    fn start_section(&mut self, location: Location) -> ControlFlow<()> {
+        ControlFlow::Continue(())
+    }
+
+    /// called at the end of each section started with [Visitor::start_section].
+    fn end_section(&mut self, location: Location) -> ControlFlow<()> {
+        ControlFlow::Continue(())
+    }
+
+    /// called for each comment.
+    fn comment(&mut self, comment: &str, location: Location) -> ControlFlow<()> {
+        ControlFlow::Continue(())
+    }
+}
+
+/// drive parses the input and calls the methods of the [Visitor] for each part of it.
+///
+/// It returns [ControlFlow::Break] if the visitor stopped parsing early, or the first
+/// [SyntaxError] in the input (after calling the visitor for everything before it).
+pub fn drive<V: Visitor + ?Sized>(
+    input: &[u8],
+    visitor: &mut V,
+) -> Result<ControlFlow<()>, SyntaxError> {
+    for token in parse(input) {
+        let token = token?;
+        let location = token.location();
+        let flow = match token {
+            Token::MapKey(..) => visitor.map_key(&token.unescape()?, location),
+            Token::ListItem(..) => visitor.list_item(location),
+            Token::Value(..) | Token::MultilineValue(..) => {
+                visitor.scalar(&token.unescape()?, location)
+            }
+            Token::NoValue(..) => visitor.no_value(location),
+            Token::Indent(..) => visitor.start_section(location),
+            Token::Outdent(..) => visitor.end_section(location),
+            Token::Comment(_, comment) => visitor.comment(comment, location),
+            Token::Newline(..) | Token::MultilineHint(..) => ControlFlow::Continue(()),
+        };
+        if flow.is_break() {
+            return Ok(flow);
+        }
+    }
+    Ok(ControlFlow::Continue(()))
+}
@@ -0,0 +1,185 @@
+use alloc::vec::Vec;
+use core::fmt;
+
+use crate::value::write_scalar;
+use crate::{escape_scalar, SectionType};
+
+/// Writer writes a CONL document incrementally to a [fmt::Write] (such as a `String`).
+/// It is the write-side equivalent of [crate::events].
+///
+/// The document is a map or a list depending on whether the first call is to a map method
+/// ([Writer::map_key], [Writer::start_map], [Writer::start_list]) or a list method
+/// ([Writer::item], [Writer::null_item], [Writer::start_map_item], [Writer::start_list_item]).
+/// Each nested section is closed with [Writer::end], and [Writer::finish] checks that the
+/// document is complete. Keys and values are escaped as needed, values containing newlines
+/// are written as multiline values where possible, and a section with no entries is written
+/// as no value.
+pub struct Writer<W> {
+    sink: W,
+    // the type of each open section, None until its first entry
+    stack: Vec<Option<SectionType>>,
+    // true after map_key, until its value is written
+    needs_value: bool,
+}
+
+/// WriteError is returned by [Writer] if the sink fails, or if the calls would not produce
+/// a valid document.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum WriteError {
+    /// the sink returned an error
+    Fmt(fmt::Error),
+    /// a value was written without a preceding [Writer::map_key]
+    ValueWithoutKey,
+    /// [Writer::map_key] was not followed by [Writer::scalar] or [Writer::null]
+    MissingValue,
+    /// a map key was written in a list, or a list item in a map
+    WrongSectionType,
+    /// [Writer::end] was called with no nested section open
+    NoSectionOpen,
+    /// [Writer::finish] was called before every nested section was ended
+    Unfinished,
+}
+
+impl fmt::Display for WriteError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            WriteError::Fmt(e) => e.fmt(f),
+            WriteError::ValueWithoutKey => f.write_str("value written without a key"),
+            WriteError::MissingValue => f.write_str("key written without a value"),
+            WriteError::WrongSectionType => f.write_str("cannot mix map keys and list items"),
+            WriteError::NoSectionOpen => f.write_str("no section to end"),
+            WriteError::Unfinished => f.write_str("document has unended sections"),
+        }
+    }
+}
+
+impl core::error::Error for WriteError {}
+
+impl From<fmt::Error> for WriteError {
+    fn from(e: fmt::Error) -> Self {
+        WriteError::Fmt(e)
+    }
+}
+
+impl<W: fmt::Write> Writer<W> {
+    /// new returns a writer that writes to sink.
+    pub fn new(sink: W) -> Self {
+        Writer {
+            sink,
+            stack: alloc::vec![None],
+            needs_value: false,
+        }
+    }
+
+    /// map_key writes a key, which must be followed by [Writer::scalar] or [Writer::null].
+    pub fn map_key(&mut self, key: &str) -> Result<&mut Self, WriteError> {
+        self.start_entry(SectionType::Map)?;
+        self.sink.write_str(&escape_scalar(key, true))?;
+        self.needs_value = true;
+        Ok(self)
+    }
+
+    /// scalar writes the value of the preceding [Writer::map_key].
+    pub fn scalar(&mut self, value: &str) -> Result<&mut Self, WriteError> {
+        if !self.needs_value {
+            return Err(WriteError::ValueWithoutKey);
+        }
+        self.needs_value = false;
+        self.sink.write_str(" = ")?;
+        write_scalar(value, self.stack.len() - 1, &mut self.sink)?;
+        self.sink.write_str("\n")?;
+        Ok(self)
+    }
+
+    /// null leaves the preceding [Writer::map_key] with no value.
+    pub fn null(&mut self) -> Result<&mut Self, WriteError> {
+        if !self.needs_value {
+            return Err(WriteError::ValueWithoutKey);
+        }
+        self.needs_value = false;
+        self.sink.write_str("\n")?;
+        Ok(self)
+    }
+
+    /// start_map writes a key whose value is a map, which is closed by [Writer::end].
+    pub fn start_map(&mut self, key: &str) -> Result<&mut Self, WriteError> {
+        self.map_key(key)?.null()?;
+        self.stack.push(Some(SectionType::Map));
+        Ok(self)
+    }
+
+    /// start_list writes a key whose value is a list, which is closed by [Writer::end].
+    pub fn start_list(&mut self, key: &str) -> Result<&mut Self, WriteError> {
+        self.map_key(key)?.null()?;
+        self.stack.push(Some(SectionType::List));
+        Ok(self)
+    }
+
+    /// item writes a list item with the given value.
+    pub fn item(&mut self, value: &str) -> Result<&mut Self, WriteError> {
+        self.start_entry(SectionType::List)?;
+        self.sink.write_str("= ")?;
+        write_scalar(value, self.stack.len() - 1, &mut self.sink)?;
+        self.sink.write_str("\n")?;
+        Ok(self)
+    }
+
+    /// null_item writes a list item with no value.
+    pub fn null_item(&mut self) -> Result<&mut Self, WriteError> {
+        self.start_entry(SectionType::List)?;
+        self.sink.write_str("=\n")?;
+        Ok(self)
+    }
+
+    /// start_map_item writes a list item whose value is a map, which is closed by [Writer::end].
+    pub fn start_map_item(&mut self) -> Result<&mut Self, WriteError> {
+        self.null_item()?;
+        self.stack.push(Some(SectionType::Map));
+        Ok(self)
+    }
+
+    /// start_list_item writes a list item whose value is a list, which is closed by [Writer::end].
+    pub fn start_list_item(&mut self) -> Result<&mut Self, WriteError> {
+        self.null_item()?;
+        self.stack.push(Some(SectionType::List));
+        Ok(self)
+    }
+
+    /// end closes the section opened by the most recent start method.
+    pub fn end(&mut self) -> Result<&mut Self, WriteError> {
+        if self.needs_value {
+            return Err(WriteError::MissingValue);
+        }
+        if self.stack.len() == 1 {
+            return Err(WriteError::NoSectionOpen);
+        }
+        self.stack.pop();
+        Ok(self)
+    }
+
+    /// finish checks that the document is complete, and returns the sink.
+    pub fn finish(self) -> Result<W, WriteError> {
+        if self.needs_value {
+            return Err(WriteError::MissingValue);
+        }
+        if self.stack.len() > 1 {
+            return Err(WriteError::Unfinished);
+        }
+        Ok(self.sink)
+    }
+
+    // checks that an entry of the given type can be written, and writes its indentation.
+    fn start_entry(&mut self, section_type: SectionType) -> Result<(), WriteError> {
+        if self.needs_value {
+            return Err(WriteError::MissingValue);
+        }
+        let current = self.stack.last_mut().unwrap();
+        if *current.get_or_insert(section_type) != section_type {
+            return Err(WriteError::WrongSectionType);
+        }
+        for _ in 1..self.stack.len() {
+            self.sink.write_str("  ")?;
+        }
+        Ok(())
+    }
+}